@@ -0,0 +1,142 @@
+// Keeps each signed-in account's data apart. Account stores and the message
+// cache live under `accounts/<id>/` in the app data folder; machine-wide
+// settings like devices, language, proxy and hotkeys are shared.
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+pub const ACCOUNTS_DIR: &str = "accounts";
+const MAX_ID_LENGTH: usize = 64;
+// Stores holding one account's data
+const ACCOUNT_STORES: [&str; 11] = [
+    "account-settings.json",
+    "window-state.json",
+    "notification-settings.json",
+    "notifications.json",
+    "file-transfers.json",
+    "downloads.json",
+    "uploads.json",
+    "shared-files.json",
+    "drafts.json",
+    "message-log.json",
+    "emotes.json",
+];
+// The message cache with its write-ahead log, and the message logs folder
+const ACCOUNT_FILES: [&str; 4] = [
+    "message-cache.sqlite3",
+    "message-cache.sqlite3-wal",
+    "message-cache.sqlite3-shm",
+    "message-logs",
+];
+
+#[derive(Default)]
+pub struct AccountState(Mutex<Option<String>>);
+
+// Also brings data saved by older versions into the signed-in account's folder
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let account = crate::settings::load(app)
+        .ok()
+        .and_then(|settings| settings.active_account);
+    *app.state::<AccountState>().0.lock().unwrap() = account.clone();
+
+    if let Some(id) = &account {
+        adopt_unowned_data(app, id)?;
+    }
+    Ok(())
+}
+
+fn active(app: &AppHandle) -> Option<String> {
+    app.state::<AccountState>().0.lock().unwrap().clone()
+}
+
+// Relative to the app data folder, as the store plugin expects. Before anyone
+// signs in, account data sits in the app data folder itself.
+pub fn store_path(app: &AppHandle, file: &str) -> PathBuf {
+    match active(app) {
+        Some(id) => PathBuf::from(ACCOUNTS_DIR).join(id).join(file),
+        None => PathBuf::from(file),
+    }
+}
+
+pub fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(match active(app) {
+        Some(id) => dir.join(ACCOUNTS_DIR).join(id),
+        None => dir,
+    })
+}
+
+// Ids end up in paths, so only plain ids like the server's are accepted
+pub fn validate_id(id: &str) -> Result<(), String> {
+    if id.is_empty()
+        || id.len() > MAX_ID_LENGTH
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid account id: {}", id));
+    }
+    Ok(())
+}
+
+// Data saved outside any account's folder, whether from before accounts were
+// kept apart or while signed out, goes to the account signing in rather than
+// it starting from nothing. Files the account already has are left alone, so
+// this is safe to run on every sign-in.
+fn adopt_unowned_data(app: &AppHandle, id: &str) -> Result<(), String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let account = dir.join(ACCOUNTS_DIR).join(id);
+    std::fs::create_dir_all(&account).map_err(|e| e.to_string())?;
+
+    for file in ACCOUNT_STORES.iter().chain(&ACCOUNT_FILES) {
+        let path = dir.join(file);
+        if !path.exists() || account.join(file).exists() {
+            continue;
+        }
+        // An open store would write itself back to the old path on exit
+        if let Some(store) = app.get_store(file) {
+            store.close_resource();
+        }
+        std::fs::rename(&path, account.join(file)).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+// `None` when signing out. Everything account-specific is reloaded from the
+// new account's folder, then "active-account-changed" is emitted.
+#[tauri::command]
+pub async fn set_active_account(
+    app_handle: AppHandle,
+    account_id: Option<String>,
+) -> Result<(), String> {
+    if let Some(id) = &account_id {
+        validate_id(id)?;
+    }
+    if active(&app_handle) == account_id {
+        return Ok(());
+    }
+
+    let mut settings = crate::settings::load(&app_handle)?;
+    settings.active_account = account_id.clone();
+    crate::settings::save(&app_handle, &settings)?;
+
+    crate::message_cache::close(&app_handle);
+    if let Some(id) = &account_id {
+        adopt_unowned_data(&app_handle, id)?;
+    }
+    *app_handle.state::<AccountState>().0.lock().unwrap() = account_id.clone();
+
+    crate::store_recovery::recover_stores(&app_handle)?;
+    crate::migrations::migrate_stores(&app_handle)?;
+    crate::file_transfer::init(&app_handle)?;
+    crate::downloads::init(&app_handle)?;
+    crate::uploads::init(&app_handle)?;
+    crate::shared_files::init(&app_handle)?;
+    // Each account has its own quiet hours
+    crate::clock::refresh_quiet_hours(&app_handle, true).await?;
+
+    let _ = app_handle.emit("active-account-changed", &account_id);
+    Ok(())
+}
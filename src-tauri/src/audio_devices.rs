@@ -0,0 +1,151 @@
+// Which microphone and speakers the app uses for calls and sounds
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::cpal::{self, Device};
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AudioDevice {
+    // cpal has no stable device ids, so the name doubles as one
+    id: String,
+    name: String,
+    is_default: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AudioDevices {
+    inputs: Vec<AudioDevice>,
+    outputs: Vec<AudioDevice>,
+    // The saved choice, even while that device is unplugged
+    selected_input: Option<String>,
+    selected_output: Option<String>,
+}
+
+fn describe(devices: impl Iterator<Item = Device>, default: Option<Device>) -> Vec<AudioDevice> {
+    let default_name = default.and_then(|device| device.name().ok());
+    devices
+        .filter_map(|device| device.name().ok())
+        .map(|name| AudioDevice {
+            id: name.clone(),
+            is_default: default_name.as_ref() == Some(&name),
+            name,
+        })
+        .collect()
+}
+
+fn enumerate(app: &AppHandle) -> AudioDevices {
+    let host = cpal::default_host();
+    let settings = crate::settings::load(app).unwrap_or_default();
+
+    AudioDevices {
+        inputs: host
+            .input_devices()
+            .map(|devices| describe(devices, host.default_input_device()))
+            .unwrap_or_default(),
+        outputs: host
+            .output_devices()
+            .map(|devices| describe(devices, host.default_output_device()))
+            .unwrap_or_default(),
+        selected_input: settings.audio_input_device,
+        selected_output: settings.audio_output_device,
+    }
+}
+
+fn find(mut devices: impl Iterator<Item = Device>, id: &str) -> Option<Device> {
+    devices.find(|device| device.name().is_ok_and(|name| name == id))
+}
+
+// The chosen microphone if it's plugged in, otherwise the system default
+pub fn input_device(app: &AppHandle) -> Option<Device> {
+    let host = cpal::default_host();
+    crate::settings::load(app)
+        .ok()
+        .and_then(|settings| settings.audio_input_device)
+        .and_then(|id| find(host.input_devices().ok()?, &id))
+        .or_else(|| host.default_input_device())
+}
+
+// The chosen speakers if they're plugged in, otherwise the system default
+pub fn output_device(app: &AppHandle) -> Option<Device> {
+    let host = cpal::default_host();
+    crate::settings::load(app)
+        .ok()
+        .and_then(|settings| settings.audio_output_device)
+        .and_then(|id| find(host.output_devices().ok()?, &id))
+        .or_else(|| host.default_output_device())
+}
+
+fn output_name(app: &AppHandle) -> Option<String> {
+    output_device(app).and_then(|device| device.name().ok())
+}
+
+// Emits "audio-devices-changed" on hot-plug and moves sounds over when the
+// device they should play on appears or disappears
+pub async fn watch_devices(app: AppHandle) {
+    let mut previous: Option<(AudioDevices, Option<String>)> = None;
+
+    loop {
+        let poll_app = app.clone();
+        let current = tauri::async_runtime::spawn_blocking(move || {
+            (enumerate(&poll_app), output_name(&poll_app))
+        })
+        .await;
+
+        if let Ok(current) = current {
+            if let Some((devices, output)) = &previous {
+                if &current.1 != output {
+                    crate::sound::reset_output(&app);
+                }
+                if &current.0 != devices {
+                    let _ = app.emit("audio-devices-changed", &current.0);
+                }
+            }
+            previous = Some(current);
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[tauri::command]
+pub async fn list_audio_devices(app_handle: AppHandle) -> Result<AudioDevices, String> {
+    tauri::async_runtime::spawn_blocking(move || enumerate(&app_handle))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// `None` follows the system default device
+#[tauri::command]
+pub async fn set_audio_devices(
+    app_handle: AppHandle,
+    input_id: Option<String>,
+    output_id: Option<String>,
+) -> Result<AudioDevices, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let app = app_handle;
+        let devices = enumerate(&app);
+        if let Some(id) = &input_id {
+            if !devices.inputs.iter().any(|device| &device.id == id) {
+                return Err(format!("Unknown microphone: {}", id));
+            }
+        }
+        if let Some(id) = &output_id {
+            if !devices.outputs.iter().any(|device| &device.id == id) {
+                return Err(format!("Unknown speakers: {}", id));
+            }
+        }
+
+        let mut settings = crate::settings::load(&app)?;
+        settings.audio_input_device = input_id;
+        settings.audio_output_device = output_id;
+        crate::settings::save(&app, &settings)?;
+
+        crate::sound::reset_output(&app);
+        Ok(enumerate(&app))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,64 @@
+// Launch at login, optionally starting hidden in the tray
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_autostart::ManagerExt;
+
+// Passed by the OS login item so we can tell a login launch from a manual one
+pub const AUTOSTART_ARG: &str = "--autostart";
+
+#[derive(Debug, Serialize)]
+pub struct AutostartStatus {
+    enabled: bool,
+    minimized: bool,
+}
+
+#[tauri::command]
+pub async fn set_autostart(
+    app_handle: AppHandle,
+    enabled: bool,
+    minimized: bool,
+) -> Result<AutostartStatus, String> {
+    let autolaunch = app_handle.autolaunch();
+    if enabled {
+        autolaunch.enable().map_err(|e| e.to_string())?;
+    } else {
+        autolaunch.disable().map_err(|e| e.to_string())?;
+    }
+
+    let mut settings = crate::settings::load(&app_handle)?;
+    settings.start_minimized = minimized;
+    crate::settings::save(&app_handle, &settings)?;
+
+    get_autostart(app_handle).await
+}
+
+#[tauri::command]
+pub async fn get_autostart(app_handle: AppHandle) -> Result<AutostartStatus, String> {
+    let enabled = app_handle
+        .autolaunch()
+        .is_enabled()
+        .map_err(|e| e.to_string())?;
+    let minimized = crate::settings::load(&app_handle)?.start_minimized;
+
+    Ok(AutostartStatus { enabled, minimized })
+}
+
+// The main window is created hidden; show it unless this is a login launch
+// and the user asked to start in the tray.
+pub fn show_main_window_on_launch(app_handle: &AppHandle) -> Result<(), String> {
+    let launched_at_login = std::env::args().any(|arg| arg == AUTOSTART_ARG);
+    let start_minimized = crate::settings::load(app_handle)
+        .map(|settings| settings.start_minimized)
+        .unwrap_or(false);
+
+    if launched_at_login && start_minimized {
+        return Ok(());
+    }
+
+    if let Some(window) = app_handle.get_webview_window("main") {
+        window.show().map_err(|e| e.to_string())?;
+        window.set_focus().map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+// Camera and microphone permission on macOS. Capture from a blocked device
+// just delivers black frames or silence, so the UI checks here first and
+// points people at System Settings when access was turned down.
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    Camera,
+    Microphone,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub enum AvPermission {
+    // Never asked; `request_av_permissions` will show the system prompt
+    NotDetermined,
+    // Blocked by a device management profile or parental controls; the user
+    // can't change it
+    Restricted,
+    // Turned down; only System Settings can undo it
+    Denied,
+    Granted,
+    // This OS doesn't gate capture per app
+    NotApplicable,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AvPermissions {
+    camera: AvPermission,
+    microphone: AvPermission,
+}
+
+#[cfg(target_os = "macos")]
+fn media_type(kind: MediaKind) -> &'static objc2_foundation::NSString {
+    use objc2_foundation::NSString;
+
+    #[link(name = "AVFoundation", kind = "framework")]
+    extern "C" {
+        static AVMediaTypeVideo: &'static NSString;
+        static AVMediaTypeAudio: &'static NSString;
+    }
+
+    unsafe {
+        match kind {
+            MediaKind::Camera => AVMediaTypeVideo,
+            MediaKind::Microphone => AVMediaTypeAudio,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn authorization_status(kind: MediaKind) -> AvPermission {
+    use objc2::{class, msg_send};
+
+    // AVAuthorizationStatus
+    let status: isize = unsafe {
+        msg_send![
+            class!(AVCaptureDevice),
+            authorizationStatusForMediaType: media_type(kind)
+        ]
+    };
+    match status {
+        0 => AvPermission::NotDetermined,
+        1 => AvPermission::Restricted,
+        3 => AvPermission::Granted,
+        _ => AvPermission::Denied,
+    }
+}
+
+// Shows the system prompt; macOS only ever asks once per app
+#[cfg(target_os = "macos")]
+async fn request_access(kind: MediaKind) -> Result<AvPermission, String> {
+    use block2::RcBlock;
+    use objc2::runtime::Bool;
+    use objc2::{class, msg_send};
+    use std::sync::Mutex;
+
+    // The handler runs on an arbitrary queue once the user answers
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    // The block isn't Send, so it's dropped before the await; macOS keeps
+    // its own copy until the handler has run
+    {
+        let sender = Mutex::new(Some(sender));
+        let handler = RcBlock::new(move |granted: Bool| {
+            if let Some(sender) = sender.lock().unwrap().take() {
+                let _ = sender.send(granted.as_bool());
+            }
+        });
+        unsafe {
+            let _: () = msg_send![
+                class!(AVCaptureDevice),
+                requestAccessForMediaType: media_type(kind),
+                completionHandler: &*handler
+            ];
+        }
+    }
+    receiver.await.map_err(|e| e.to_string())?;
+
+    Ok(authorization_status(kind))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn authorization_status(_kind: MediaKind) -> AvPermission {
+    AvPermission::NotApplicable
+}
+
+#[cfg(not(target_os = "macos"))]
+async fn request_access(kind: MediaKind) -> Result<AvPermission, String> {
+    Ok(authorization_status(kind))
+}
+
+#[tauri::command]
+pub async fn check_av_permissions() -> Result<AvPermissions, String> {
+    Ok(AvPermissions {
+        camera: authorization_status(MediaKind::Camera),
+        microphone: authorization_status(MediaKind::Microphone),
+    })
+}
+
+// Prompts if the user hasn't been asked yet, otherwise returns the answer
+// they already gave
+#[tauri::command]
+pub async fn request_av_permissions(kind: MediaKind) -> Result<AvPermission, String> {
+    match authorization_status(kind) {
+        AvPermission::NotDetermined => request_access(kind).await,
+        status => Ok(status),
+    }
+}
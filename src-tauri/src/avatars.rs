@@ -0,0 +1,264 @@
+// Contact display pictures, cached on disk and served to the webview at
+// `avatar://localhost/<percent-encoded picture URL>` (`http://avatar.localhost/…`
+// on Windows; `convertFileSrc(url, "avatar")` builds either). Each picture is
+// resized once and stored under the hash of the result, so contacts sharing a
+// picture share a file, then checked with the server again after TTL_MS.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, UriSchemeResponder};
+
+use crate::{checksum, net};
+
+pub const SCHEME: &str = "avatar";
+const AVATARS_DIR: &str = "avatars";
+const INDEX_FILE: &str = "index.json";
+const MAX_DIMENSION: u32 = 256;
+const MAX_DOWNLOAD_BYTES: usize = 10 * 1024 * 1024;
+// Cached pictures are checked with the server again after this
+const TTL_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedAvatar {
+    hash: String,
+    fetched_at: i64, // ms since the epoch, last fetched or revalidated
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+#[derive(Default)]
+pub struct AvatarCacheState {
+    // By picture URL; loaded from disk on first use
+    index: Mutex<Option<HashMap<String, CachedAvatar>>>,
+    // One fetch per URL at a time, however many windows ask for it
+    fetching: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+pub fn avatars_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join(AVATARS_DIR))
+}
+
+fn picture_path(dir: &Path, hash: &str) -> PathBuf {
+    dir.join(format!("{}.png", hash))
+}
+
+fn with_index<T>(app: &AppHandle, f: impl FnOnce(&mut HashMap<String, CachedAvatar>) -> T) -> T {
+    let state = app.state::<AvatarCacheState>();
+    let mut index = state.index.lock().unwrap();
+    let index = index.get_or_insert_with(|| {
+        avatars_dir(app)
+            .ok()
+            .and_then(|dir| std::fs::read(dir.join(INDEX_FILE)).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    });
+    f(index)
+}
+
+// Best effort; a lost index only means pictures are fetched again
+fn save_index(dir: &Path, index: &HashMap<String, CachedAvatar>) {
+    let Ok(bytes) = serde_json::to_vec(index) else {
+        return;
+    };
+    let partial = dir.join(format!("{}.part", INDEX_FILE));
+    if std::fs::write(&partial, bytes).is_ok() {
+        let _ = std::fs::rename(&partial, dir.join(INDEX_FILE));
+    }
+}
+
+// Shrinks the picture and stores it under its hash. Returns the hash.
+fn store_picture(dir: &Path, bytes: &[u8]) -> Result<String, String> {
+    let mut image = image::load_from_memory(bytes).map_err(|e| e.to_string())?;
+    if image.width() > MAX_DIMENSION || image.height() > MAX_DIMENSION {
+        image = image.thumbnail(MAX_DIMENSION, MAX_DIMENSION);
+    }
+    let mut png = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+
+    let hash = checksum::sha256_bytes(&png);
+    let path = picture_path(dir, &hash);
+    if !path.exists() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let partial = path.with_extension("png.part");
+        std::fs::write(&partial, &png).map_err(|e| e.to_string())?;
+        std::fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+    }
+    Ok(hash)
+}
+
+fn header_value(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)?
+        .to_str()
+        .ok()
+        .map(str::to_string)
+}
+
+// Fetches `url`, conditionally when there's a cached copy to check
+async fn fetch(
+    app: &AppHandle,
+    url: &str,
+    cached: Option<&CachedAvatar>,
+) -> Result<CachedAvatar, String> {
+    let mut request = net::client(app)?.get(url);
+    if let Some(cached) = cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().timestamp_millis();
+
+    if let Some(cached) = cached.filter(|_| response.status() == reqwest::StatusCode::NOT_MODIFIED)
+    {
+        return Ok(CachedAvatar {
+            fetched_at: now,
+            ..cached.clone()
+        });
+    }
+    let mut response = response.error_for_status().map_err(|e| e.to_string())?;
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_DOWNLOAD_BYTES as u64)
+    {
+        return Err("This picture is too large".to_string());
+    }
+
+    let etag = header_value(&response, reqwest::header::ETAG);
+    let last_modified = header_value(&response, reqwest::header::LAST_MODIFIED);
+    // Read a chunk at a time, since the length header can be missing or wrong
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        if bytes.len() + chunk.len() > MAX_DOWNLOAD_BYTES {
+            return Err("This picture is too large".to_string());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let dir = avatars_dir(app)?;
+    let hash = tauri::async_runtime::spawn_blocking(move || store_picture(&dir, &bytes))
+        .await
+        .map_err(|e| e.to_string())??;
+    Ok(CachedAvatar {
+        hash,
+        fetched_at: now,
+        etag,
+        last_modified,
+    })
+}
+
+// Path of the cached picture for `url`, fetching or revalidating it first if
+// needed. A stale copy is still used while the server can't be reached.
+async fn resolve(app: &AppHandle, url: &str) -> Result<PathBuf, String> {
+    let parsed = url::Url::parse(url).map_err(|e| e.to_string())?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Unsupported picture URL: {}", url));
+    }
+    let dir = avatars_dir(app)?;
+
+    let state = app.state::<AvatarCacheState>();
+    let lock = state
+        .fetching
+        .lock()
+        .unwrap()
+        .entry(url.to_string())
+        .or_default()
+        .clone();
+    let fetching = lock.lock().await;
+    let result = resolve_locked(app, url, &dir).await;
+    drop(fetching);
+
+    // The last one done with the URL takes its lock out of the map. Clones
+    // are only made with the map held, so none can appear in between.
+    let mut fetching = state.fetching.lock().unwrap();
+    if Arc::strong_count(&lock) == 2 {
+        fetching.remove(url);
+    }
+    result
+}
+
+async fn resolve_locked(app: &AppHandle, url: &str, dir: &Path) -> Result<PathBuf, String> {
+    let cached = with_index(app, |index| index.get(url).cloned())
+        .filter(|cached| picture_path(dir, &cached.hash).exists());
+    let now = chrono::Utc::now().timestamp_millis();
+    if let Some(cached) = cached
+        .as_ref()
+        .filter(|cached| now - cached.fetched_at < TTL_MS)
+    {
+        return Ok(picture_path(dir, &cached.hash));
+    }
+
+    let fetched = match (fetch(app, url, cached.as_ref()).await, cached) {
+        (Ok(fetched), _) => fetched,
+        (Err(_), Some(stale)) => return Ok(picture_path(dir, &stale.hash)),
+        (Err(error), None) => return Err(error),
+    };
+    let path = picture_path(dir, &fetched.hash);
+
+    with_index(app, |index| {
+        let replaced = index.insert(url.to_string(), fetched);
+        // Drop the old picture once nothing else uses it
+        if let Some(replaced) = replaced {
+            if !index.values().any(|entry| entry.hash == replaced.hash) {
+                let _ = std::fs::remove_file(picture_path(dir, &replaced.hash));
+            }
+        }
+        save_index(dir, index);
+    });
+    Ok(path)
+}
+
+fn respond(responder: UriSchemeResponder, status: StatusCode, content: Vec<u8>) {
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+    if status == StatusCode::OK {
+        response = response
+            .header(header::CONTENT_TYPE, "image/png")
+            .header(header::CACHE_CONTROL, "max-age=3600");
+    }
+    responder.respond(response.body(content).unwrap());
+}
+
+// Handler for the avatar:// protocol, registered on the app builder
+pub fn handle_request(app: &AppHandle, request: Request<Vec<u8>>, responder: UriSchemeResponder) {
+    let encoded = request.uri().path().trim_start_matches('/').to_string();
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let Ok(url) = percent_encoding::percent_decode_str(&encoded).decode_utf8() else {
+            return respond(responder, StatusCode::BAD_REQUEST, Vec::new());
+        };
+        let picture = match resolve(&app, &url).await {
+            Ok(path) => tokio::fs::read(path).await.map_err(|e| e.to_string()),
+            Err(error) => Err(error),
+        };
+        match picture {
+            Ok(bytes) => respond(responder, StatusCode::OK, bytes),
+            Err(error) => respond(responder, StatusCode::NOT_FOUND, error.into_bytes()),
+        }
+    });
+}
+
+// Empties the cache; pictures are fetched again as they're shown
+pub fn clear(app: &AppHandle) -> Result<(), String> {
+    let dir = avatars_dir(app)?;
+    with_index(app, |index| {
+        index.clear();
+        match std::fs::remove_dir_all(&dir) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.to_string()),
+            _ => Ok(()),
+        }
+    })
+}
@@ -0,0 +1,311 @@
+// Whole-profile backups: every store, for every account, plus the installed sound schemes in one
+// archive, encrypted with the storage key. That key stays in this machine's
+// credential store, so a backup restores for the same user here, e.g. after
+// a reinstall or a damaged profile.
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::migrations::{self, StoreContents};
+use crate::{accounts, sound, sound_schemes, storage};
+
+const MANIFEST: &str = "backup.json";
+// Bumped when the archive layout changes
+const FORMAT_VERSION: u32 = 1;
+const STORES_PREFIX: &str = "stores/";
+const SOUND_SCHEMES_PREFIX: &str = "sound-schemes/";
+// Click data for notifications already shown; stale by the time it's restored
+const SKIPPED_STORES: [&str; 1] = ["notifications.json"];
+const MAX_BACKUP_BYTES: u64 = 512 * 1024 * 1024;
+const MAX_MANIFEST_BYTES: u64 = 64 * 1024;
+const MAX_ENTRY_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    format_version: u32,
+    app_version: String,
+    created_at: i64, // ms since the epoch
+}
+
+// Everything in a backup, checked before anything on disk is replaced
+struct Contents {
+    stores: Vec<(String, StoreContents)>,
+    // (scheme id, file name, bytes)
+    sound_files: Vec<(String, String, Vec<u8>)>,
+}
+
+// Store files sit directly in the app data folder, or in an account's folder
+// under it, and are named by their path relative to it
+fn store_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut files = json_files(dir, "");
+    let accounts = dir.join(accounts::ACCOUNTS_DIR);
+    for account in std::fs::read_dir(&accounts).into_iter().flatten().flatten() {
+        let Some(id) = account.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        let prefix = format!("{}/{}/", accounts::ACCOUNTS_DIR, id);
+        files.extend(json_files(&account.path(), &prefix));
+    }
+    files
+}
+
+fn json_files(dir: &Path, prefix: &str) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            (name.ends_with(".json") && !SKIPPED_STORES.contains(&name.as_str()))
+                .then(|| (format!("{}{}", prefix, name), entry.path()))
+        })
+        .collect()
+}
+
+fn is_plain_file_name(name: &str) -> bool {
+    Path::new(name).file_name().and_then(|file| file.to_str()) == Some(name)
+}
+
+// A store's name in the archive, either "<file>" or "accounts/<id>/<file>".
+// Returns the file name on its own.
+fn store_file_name(name: &str) -> Option<&str> {
+    let file = match name.strip_prefix(accounts::ACCOUNTS_DIR) {
+        Some(rest) => {
+            let (id, file) = rest.strip_prefix('/')?.split_once('/')?;
+            accounts::validate_id(id).ok()?;
+            file
+        }
+        None => name,
+    };
+    (is_plain_file_name(file) && file.ends_with(".json")).then_some(file)
+}
+
+fn write_archive(app: &AppHandle) -> Result<Vec<u8>, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file(MANIFEST, deflated)
+        .map_err(|e| e.to_string())?;
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        app_version: app.package_info().version.to_string(),
+        created_at: chrono::Utc::now().timestamp_millis(),
+    };
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|e| e.to_string())?;
+
+    // Stored decrypted; the archive as a whole is encrypted below
+    for (name, path) in store_files(&dir) {
+        let Ok(store) = std::fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| storage::deserialize_store(&bytes).map_err(|e| e.to_string()))
+        else {
+            continue;
+        };
+        zip.start_file(format!("{}{}", STORES_PREFIX, name), deflated)
+            .map_err(|e| e.to_string())?;
+        serde_json::to_writer_pretty(&mut zip, &store).map_err(|e| e.to_string())?;
+    }
+
+    let schemes = sound_schemes::schemes_dir(app)?;
+    for scheme in std::fs::read_dir(&schemes).into_iter().flatten().flatten() {
+        let Some(id) = scheme.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        // Skips half-imported `.part` folders
+        if uuid::Uuid::parse_str(&id).is_err() {
+            continue;
+        }
+        for file in std::fs::read_dir(scheme.path())
+            .map_err(|e| e.to_string())?
+            .flatten()
+        {
+            let Some(name) = file.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let bytes = std::fs::read(file.path()).map_err(|e| e.to_string())?;
+            zip.start_file(format!("{}{}/{}", SOUND_SCHEMES_PREFIX, id, name), deflated)
+                .map_err(|e| e.to_string())?;
+            zip.write_all(&bytes).map_err(|e| e.to_string())?;
+        }
+    }
+
+    let archive = zip.finish().map_err(|e| e.to_string())?.into_inner();
+    storage::encrypt(&archive)
+}
+
+fn read_entry(entry: impl Read, name: &str, limit: u64) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    entry
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > limit {
+        return Err(format!("{} in the backup is too large", name));
+    }
+    Ok(bytes)
+}
+
+fn read_archive(path: &Path) -> Result<Contents, String> {
+    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    if file.metadata().map_err(|e| e.to_string())?.len() > MAX_BACKUP_BYTES {
+        return Err("This file is too large to be a backup".to_string());
+    }
+    let mut bytes = Vec::new();
+    file.take(MAX_BACKUP_BYTES)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    // Backups are always written encrypted, and decrypt passes plaintext through
+    if !bytes.starts_with(storage::MAGIC) {
+        return Err("This file isn't a backup".to_string());
+    }
+    let bytes = storage::decrypt(&bytes)
+        .map_err(|_| "This backup was made on another machine or user account".to_string())?;
+    let mut archive =
+        ZipArchive::new(Cursor::new(bytes)).map_err(|_| "This file isn't a backup".to_string())?;
+
+    let manifest = archive
+        .by_name(MANIFEST)
+        .map_err(|_| "This file isn't a backup".to_string())?;
+    let manifest: Manifest =
+        serde_json::from_slice(&read_entry(manifest, MANIFEST, MAX_MANIFEST_BYTES)?)
+            .map_err(|e| e.to_string())?;
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "This backup was made by version {}; update the app to restore it",
+            manifest.app_version
+        ));
+    }
+
+    let mut contents = Contents {
+        stores: Vec::new(),
+        sound_files: Vec::new(),
+    };
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index).map_err(|e| e.to_string())?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+
+        if let Some(path) = name.strip_prefix(STORES_PREFIX) {
+            let Some(file) = store_file_name(path) else {
+                return Err(format!("Unexpected file in the backup: {}", name));
+            };
+            let store: StoreContents =
+                serde_json::from_slice(&read_entry(entry, &name, MAX_ENTRY_BYTES)?)
+                    .map_err(|e| format!("{} in the backup is damaged: {}", path, e))?;
+            if !migrations::is_supported(file, &store) {
+                return Err(format!(
+                    "This backup was made by version {}; update the app to restore it",
+                    manifest.app_version
+                ));
+            }
+            contents.stores.push((path.to_string(), store));
+        } else if let Some(path) = name.strip_prefix(SOUND_SCHEMES_PREFIX) {
+            let Some((id, file)) = path
+                .split_once('/')
+                .filter(|(id, file)| uuid::Uuid::parse_str(id).is_ok() && is_plain_file_name(file))
+            else {
+                return Err(format!("Unexpected file in the backup: {}", name));
+            };
+            let (id, file) = (id.to_string(), file.to_string());
+            let bytes = read_entry(entry, &name, MAX_ENTRY_BYTES)?;
+            contents.sound_files.push((id, file, bytes));
+        }
+    }
+
+    Ok(contents)
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+    std::fs::write(&partial, bytes).map_err(|e| e.to_string())?;
+    std::fs::rename(&partial, path).map_err(|e| e.to_string())
+}
+
+// Stores missing from the backup are left as they are, and so are the
+// installed sound schemes when the backup has none
+fn apply(app: &AppHandle, contents: Contents) -> Result<(), String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    // Schemes are unpacked beside the current ones and swapped in whole
+    if !contents.sound_files.is_empty() {
+        let schemes = sound_schemes::schemes_dir(app)?;
+        let restored = schemes.with_extension("restore");
+        let _ = std::fs::remove_dir_all(&restored);
+        for (id, file, bytes) in &contents.sound_files {
+            std::fs::create_dir_all(restored.join(id)).map_err(|e| e.to_string())?;
+            std::fs::write(restored.join(id).join(file), bytes).map_err(|e| e.to_string())?;
+        }
+        if schemes.exists() {
+            std::fs::remove_dir_all(&schemes).map_err(|e| e.to_string())?;
+        }
+        std::fs::rename(&restored, &schemes).map_err(|e| e.to_string())?;
+    }
+
+    for (file, store) in &contents.stores {
+        let path = dir.join(file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let bytes = storage::serialize_store(store).map_err(|e| e.to_string())?;
+        write_file(&path, &bytes)?;
+    }
+    // Backups from older versions get the same upgrades as at startup
+    migrations::migrate_stores(app)?;
+
+    // Open stores would otherwise write their old contents back over these
+    for (file, _) in &contents.stores {
+        if let Some(store) = app.get_store(file) {
+            store.reload_ignore_defaults().map_err(|e| e.to_string())?;
+        }
+    }
+    // As do the records these keep in memory
+    crate::file_transfer::init(app)?;
+    crate::downloads::init(app)?;
+    crate::uploads::init(app)?;
+    crate::shared_files::init(app)?;
+    sound::init(app);
+    sound::clear_cache(app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn backup_app_data(app_handle: AppHandle, path: String) -> Result<(), String> {
+    if !storage::is_available() {
+        return Err("Backups need the system keychain to encrypt them".to_string());
+    }
+    let path = PathBuf::from(path);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let archive = write_archive(&app_handle)?;
+        write_file(&path, &archive)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Emits "app-data-restored" so windows reload their settings. Hotkeys and the
+// UI language take effect on the next launch.
+#[tauri::command]
+pub async fn restore_app_data(app_handle: AppHandle, path: String) -> Result<(), String> {
+    let app = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let contents = read_archive(Path::new(&path))?;
+        apply(&app, contents)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let _ = app_handle.emit("app-data-restored", ());
+    Ok(())
+}
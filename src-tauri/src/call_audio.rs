@@ -0,0 +1,154 @@
+// How the app's own audio behaves around voice and video calls
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::sound::{self, SoundId};
+
+// Roughly when a caller would give up anyway
+const RING_TIMEOUT: Duration = Duration::from_secs(45);
+
+// Whether the microphone is muted, while a call is connected
+#[derive(Default)]
+pub struct CallMuteState(Mutex<Option<bool>>);
+
+// When each ringing call started, so a stale timeout can't stop a newer ring
+#[derive(Default)]
+pub struct RingtoneState(Mutex<HashMap<String, Instant>>);
+
+#[derive(Debug, Clone, Serialize)]
+struct RingtoneEvent<'a> {
+    call_id: &'a str,
+}
+
+fn loop_key(call_id: &str) -> String {
+    format!("ringtone:{}", call_id)
+}
+
+// Muted sounds, quiet hours and another user at the machine all keep it silent
+async fn may_ring(app: &AppHandle) -> Result<bool, String> {
+    let settings = crate::load_notification_settings(app.clone()).await?;
+    Ok(settings.sound_enabled
+        && !crate::is_within_quiet_hours(&settings, chrono::Local::now().time())
+        && crate::session::is_active(app))
+}
+
+pub fn is_call_muted(app: &AppHandle) -> bool {
+    app.state::<CallMuteState>()
+        .0
+        .lock()
+        .unwrap()
+        .unwrap_or(false)
+}
+
+// The frontend mutes the actual track when it sees "call-mute-toggled"
+fn apply_call_mute(app: &AppHandle, muted: bool) {
+    let _ = app.emit("call-mute-toggled", muted);
+    crate::theme::refresh_tray_icon(app);
+}
+
+// For the push-to-mute hotkey; does nothing outside a call
+pub fn toggle_call_mute(app: &AppHandle) {
+    let muted = {
+        let state = app.state::<CallMuteState>();
+        let mut state = state.0.lock().unwrap();
+        let Some(muted) = state.as_mut() else {
+            return;
+        };
+        *muted = !*muted;
+        *muted
+    };
+    apply_call_mute(app, muted);
+}
+
+// Call when a call connects; notification sounds play quieter until it ends
+#[tauri::command]
+pub async fn begin_call_audio_session(app_handle: AppHandle) -> Result<(), String> {
+    *app_handle.state::<CallMuteState>().0.lock().unwrap() = Some(false);
+    sound::set_ducked(&app_handle, true);
+    let _ = app_handle.emit("call-audio-session-changed", true);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn end_call_audio_session(app_handle: AppHandle) -> Result<(), String> {
+    *app_handle.state::<CallMuteState>().0.lock().unwrap() = None;
+    crate::theme::refresh_tray_icon(&app_handle);
+    sound::set_ducked(&app_handle, false);
+    let _ = app_handle.emit("call-audio-session-changed", false);
+    Ok(())
+}
+
+// For the call window's own mute button, so it and the hotkey stay in step
+#[tauri::command]
+pub async fn set_call_muted(app_handle: AppHandle, muted: bool) -> Result<(), String> {
+    {
+        let state = app_handle.state::<CallMuteState>();
+        let mut state = state.0.lock().unwrap();
+        let Some(current) = state.as_mut() else {
+            return Err("There's no call in progress".to_string());
+        };
+        *current = muted;
+    }
+    apply_call_mute(&app_handle, muted);
+    Ok(())
+}
+
+// Loops until `stop_ringtone` or the timeout, which emits "ringtone-timed-out".
+// Returns whether it's ringing at all.
+#[tauri::command]
+pub async fn start_ringtone(
+    app_handle: AppHandle,
+    call_id: String,
+    sound: Option<SoundId>,
+) -> Result<bool, String> {
+    if !may_ring(&app_handle).await? {
+        return Ok(false);
+    }
+
+    let app = app_handle.clone();
+    let key = loop_key(&call_id);
+    let sound = sound.unwrap_or(SoundId::Ringtone);
+    tauri::async_runtime::spawn_blocking(move || sound::start_loop(&app, &key, sound))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let started = Instant::now();
+    app_handle
+        .state::<RingtoneState>()
+        .0
+        .lock()
+        .unwrap()
+        .insert(call_id.clone(), started);
+
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(RING_TIMEOUT).await;
+
+        let state = app_handle.state::<RingtoneState>();
+        let mut ringing = state.0.lock().unwrap();
+        if ringing.get(&call_id) != Some(&started) {
+            return;
+        }
+        ringing.remove(&call_id);
+        drop(ringing);
+
+        sound::stop_loop(&app_handle, &loop_key(&call_id));
+        let _ = app_handle.emit("ringtone-timed-out", RingtoneEvent { call_id: &call_id });
+    });
+
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn stop_ringtone(app_handle: AppHandle, call_id: String) -> Result<(), String> {
+    app_handle
+        .state::<RingtoneState>()
+        .0
+        .lock()
+        .unwrap()
+        .remove(&call_id);
+    sound::stop_loop(&app_handle, &loop_key(&call_id));
+    Ok(())
+}
@@ -0,0 +1,276 @@
+// Webcams for video calls and display-picture capture. There's no camera crate
+// that covers every platform, so this goes through ffmpeg like video thumbnails.
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Resolution {
+    width: u32,
+    height: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Camera {
+    // The device node on Linux; elsewhere the name doubles as an id
+    id: String,
+    name: String,
+    // Largest first; empty when the camera wouldn't report its modes
+    resolutions: Vec<Resolution>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CameraSnapshot {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+}
+
+// ffmpeg prints device listings to stderr and exits with an error
+fn ffmpeg_listing(args: &[&str]) -> Result<String, String> {
+    match Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .args(args)
+        .output()
+    {
+        Ok(output) => Ok(String::from_utf8_lossy(&output.stderr).into_owned()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err("Camera access needs ffmpeg installed".to_string())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Picks every "640x480"-style size out of ffmpeg's output, including the
+// "s=640x480" and "640x480@[30.000000]fps" forms
+fn parse_resolutions(listing: &str) -> Vec<Resolution> {
+    let mut resolutions: Vec<Resolution> = listing
+        .split_whitespace()
+        .filter_map(|token| {
+            let token = token.strip_prefix("s=").unwrap_or(token);
+            let token = token.split('@').next()?;
+            let (width, height) = token.split_once('x')?;
+            let resolution = Resolution {
+                width: width.parse().ok()?,
+                height: height.parse().ok()?,
+            };
+            (resolution.width > 0 && resolution.height > 0).then_some(resolution)
+        })
+        .collect();
+    resolutions.sort_by(|a, b| b.cmp(a));
+    resolutions.dedup();
+    resolutions
+}
+
+#[cfg(target_os = "linux")]
+pub fn enumerate() -> Result<Vec<Camera>, String> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/video4linux") else {
+        return Ok(Vec::new());
+    };
+
+    let mut cameras = Vec::new();
+    for entry in entries.flatten() {
+        let read = |file: &str| {
+            std::fs::read_to_string(entry.path().join(file))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        // Webcams also expose a metadata node; only the first one captures
+        if read("index") != "0" {
+            continue;
+        }
+
+        let id = format!("/dev/{}", entry.file_name().to_string_lossy());
+        let listing = ffmpeg_listing(&["-f", "v4l2", "-list_formats", "all", "-i", &id])?;
+        cameras.push(Camera {
+            name: read("name"),
+            resolutions: parse_resolutions(&listing),
+            id,
+        });
+    }
+    cameras.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(cameras)
+}
+
+#[cfg(target_os = "linux")]
+fn input_args(id: &str) -> Vec<String> {
+    vec!["-f".into(), "v4l2".into(), "-i".into(), id.into()]
+}
+
+#[cfg(target_os = "macos")]
+pub fn enumerate() -> Result<Vec<Camera>, String> {
+    let listing = ffmpeg_listing(&["-f", "avfoundation", "-list_devices", "true", "-i", ""])?;
+
+    let mut cameras = Vec::new();
+    for line in listing
+        .lines()
+        .skip_while(|line| !line.contains("AVFoundation video devices"))
+        .skip(1)
+        .take_while(|line| !line.contains("AVFoundation audio devices"))
+    {
+        // "[AVFoundation indev @ 0x…] [0] FaceTime HD Camera"
+        let Some((_, name)) = line.rsplit_once("] ") else {
+            continue;
+        };
+        if name.starts_with("Capture screen") {
+            continue;
+        }
+
+        // avfoundation only lists a camera's modes when asked for one it
+        // doesn't support
+        let modes = ffmpeg_listing(&["-f", "avfoundation", "-video_size", "1x1", "-i", name])?;
+        cameras.push(Camera {
+            id: name.to_string(),
+            name: name.to_string(),
+            resolutions: parse_resolutions(&modes),
+        });
+    }
+    Ok(cameras)
+}
+
+#[cfg(target_os = "macos")]
+fn input_args(id: &str) -> Vec<String> {
+    // Most Mac cameras refuse ffmpeg's default 29.97fps
+    vec![
+        "-f".into(),
+        "avfoundation".into(),
+        "-framerate".into(),
+        "30".into(),
+        "-i".into(),
+        id.into(),
+    ]
+}
+
+#[cfg(target_os = "windows")]
+pub fn enumerate() -> Result<Vec<Camera>, String> {
+    let listing = ffmpeg_listing(&["-list_devices", "true", "-f", "dshow", "-i", "dummy"])?;
+
+    // Newer ffmpeg tags each device "(video)"; older ones list video devices
+    // under their own heading instead
+    let mut in_video_section = false;
+    let mut cameras = Vec::new();
+    for line in listing.lines() {
+        if line.contains("DirectShow video devices") {
+            in_video_section = true;
+            continue;
+        }
+        if line.contains("DirectShow audio devices") {
+            in_video_section = false;
+            continue;
+        }
+        if line.contains("Alternative name") {
+            continue;
+        }
+
+        let mut quoted = line.split('"');
+        let (Some(_), Some(name), Some(rest)) = (quoted.next(), quoted.next(), quoted.next())
+        else {
+            continue;
+        };
+        let is_video = rest.contains("(video)") || (in_video_section && !rest.contains("(audio)"));
+        if !is_video {
+            continue;
+        }
+
+        let input = format!("video={}", name);
+        let options = ffmpeg_listing(&["-f", "dshow", "-list_options", "true", "-i", &input])?;
+        cameras.push(Camera {
+            id: name.to_string(),
+            name: name.to_string(),
+            resolutions: parse_resolutions(&options),
+        });
+    }
+    Ok(cameras)
+}
+
+#[cfg(target_os = "windows")]
+fn input_args(id: &str) -> Vec<String> {
+    vec![
+        "-f".into(),
+        "dshow".into(),
+        "-i".into(),
+        format!("video={}", id),
+    ]
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+pub fn enumerate() -> Result<Vec<Camera>, String> {
+    Ok(Vec::new())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn input_args(_id: &str) -> Vec<String> {
+    Vec::new()
+}
+
+fn capture(id: &str, out: &std::path::Path) -> Result<(), String> {
+    // Cameras hand back dark frames while their exposure settles, so skip
+    // the first second
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-y"])
+        .args(input_args(id))
+        .args(["-ss", "1", "-frames:v", "1"])
+        .arg(out)
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() && out.exists() => Ok(()),
+        Ok(output) => {
+            let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            Err(if error.is_empty() {
+                "Could not read a frame from the camera".to_string()
+            } else {
+                error
+            })
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err("Camera access needs ffmpeg installed".to_string())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn list_cameras() -> Result<Vec<Camera>, String> {
+    tauri::async_runtime::spawn_blocking(enumerate)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// Saves a single frame as a PNG, e.g. for the settings preview or a new
+// display picture
+#[tauri::command]
+pub async fn capture_camera_snapshot(
+    app_handle: AppHandle,
+    device_id: String,
+) -> Result<CameraSnapshot, String> {
+    let directory = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join("camera");
+    std::fs::create_dir_all(&directory).map_err(|e| e.to_string())?;
+    let path = directory.join(format!(
+        "snapshot-{}.png",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+
+    let out = path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        // Only hand ffmpeg devices it listed itself, never an arbitrary input
+        if !enumerate()?.iter().any(|camera| camera.id == device_id) {
+            return Err(format!("Unknown camera: {}", device_id));
+        }
+        capture(&device_id, &out)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let (width, height) = image::image_dimensions(&path).map_err(|e| e.to_string())?;
+    Ok(CameraSnapshot {
+        path,
+        width,
+        height,
+    })
+}
@@ -0,0 +1,448 @@
+// Displays and windows that can be shared in a call, with small previews so
+// the picker shows what will actually be sent
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+const THUMBNAIL_SIZE: u32 = 320;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureSourceKind {
+    Display,
+    Window,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureSource {
+    // "display:<index>" or "window:<native window id>"
+    id: String,
+    kind: CaptureSourceKind,
+    name: String,
+    // The application that owns a window
+    owner: Option<String>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    // None when the platform couldn't render a preview
+    thumbnail: Option<PathBuf>,
+}
+
+// The source the user picked for the current share
+#[derive(Default)]
+pub struct CaptureState(Mutex<Option<CaptureSource>>);
+
+struct WindowInfo {
+    native_id: String,
+    name: String,
+    owner: Option<String>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+fn save_thumbnail(image: image::RgbaImage, out: &Path) -> Option<PathBuf> {
+    image::DynamicImage::ImageRgba8(image)
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .save_with_format(out, image::ImageFormat::Png)
+        .ok()?;
+    Some(out.to_path_buf())
+}
+
+// Lets a platform screenshot tool write `out`, then shrinks it in place
+#[cfg(not(target_os = "windows"))]
+fn thumbnail_with(program: &str, args: &[&str], out: &Path) -> Option<PathBuf> {
+    let status = std::process::Command::new(program)
+        .args(args)
+        .arg(out)
+        .status()
+        .ok()?;
+    if !status.success() {
+        return None;
+    }
+    save_thumbnail(image::open(out).ok()?.into_rgba8(), out)
+}
+
+#[cfg(target_os = "windows")]
+fn display_thumbnail(
+    _index: usize,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    out: &Path,
+) -> Option<PathBuf> {
+    let rgba = unsafe { crate::screenshot::capture_rect(x, y, width as i32, height as i32) }?;
+    save_thumbnail(image::RgbaImage::from_raw(width, height, rgba)?, out)
+}
+
+#[cfg(target_os = "macos")]
+fn display_thumbnail(
+    index: usize,
+    _x: i32,
+    _y: i32,
+    _width: u32,
+    _height: u32,
+    out: &Path,
+) -> Option<PathBuf> {
+    // screencapture counts displays from 1, in the same order as the monitors
+    thumbnail_with(
+        "screencapture",
+        &["-x", "-D", &(index + 1).to_string()],
+        out,
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn display_thumbnail(
+    _index: usize,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    out: &Path,
+) -> Option<PathBuf> {
+    // ImageMagick's `import`; X11 only
+    let crop = format!("{}x{}+{}+{}", width, height, x, y);
+    thumbnail_with(
+        "import",
+        &["-silent", "-window", "root", "-crop", &crop],
+        out,
+    )
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn display_thumbnail(
+    _index: usize,
+    _x: i32,
+    _y: i32,
+    _width: u32,
+    _height: u32,
+    _out: &Path,
+) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(target_os = "windows")]
+fn list_windows() -> Vec<WindowInfo> {
+    use windows_sys::core::BOOL;
+    use windows_sys::Win32::Foundation::{HWND, LPARAM, RECT};
+    use windows_sys::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, GetWindowRect, GetWindowTextW, GetWindowThreadProcessId, IsIconic,
+        IsWindowVisible,
+    };
+
+    unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        (*(lparam as *mut Vec<HWND>)).push(hwnd);
+        1
+    }
+
+    let mut handles: Vec<HWND> = Vec::new();
+    unsafe { EnumWindows(Some(collect), &mut handles as *mut Vec<HWND> as LPARAM) };
+
+    handles
+        .into_iter()
+        .filter_map(|hwnd| unsafe {
+            if IsWindowVisible(hwnd) == 0 || IsIconic(hwnd) != 0 {
+                return None;
+            }
+            // Suspended store apps stay "visible" but are cloaked by the compositor
+            let mut cloaked: u32 = 0;
+            DwmGetWindowAttribute(
+                hwnd,
+                DWMWA_CLOAKED as u32,
+                &mut cloaked as *mut u32 as *mut _,
+                std::mem::size_of::<u32>() as u32,
+            );
+            if cloaked != 0 {
+                return None;
+            }
+            let mut process_id = 0;
+            GetWindowThreadProcessId(hwnd, &mut process_id);
+            if process_id == std::process::id() {
+                return None;
+            }
+
+            let mut title = [0u16; 256];
+            let length = GetWindowTextW(hwnd, title.as_mut_ptr(), title.len() as i32);
+            if length <= 0 {
+                return None;
+            }
+            let mut rect: RECT = std::mem::zeroed();
+            if GetWindowRect(hwnd, &mut rect) == 0 {
+                return None;
+            }
+
+            Some(WindowInfo {
+                native_id: (hwnd as isize).to_string(),
+                name: String::from_utf16_lossy(&title[..length as usize]),
+                owner: None,
+                x: rect.left,
+                y: rect.top,
+                width: (rect.right - rect.left).max(0) as u32,
+                height: (rect.bottom - rect.top).max(0) as u32,
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn window_thumbnail(window: &WindowInfo, out: &Path) -> Option<PathBuf> {
+    use windows_sys::Win32::Foundation::HWND;
+    use windows_sys::Win32::Storage::Xps::PrintWindow;
+    use windows_sys::Win32::UI::WindowsAndMessaging::PW_RENDERFULLCONTENT;
+
+    // Renders the window itself rather than whatever happens to cover it
+    let hwnd = window.native_id.parse::<isize>().ok()? as HWND;
+    let rgba = unsafe {
+        crate::screenshot::render_to_pixels(
+            window.width as i32,
+            window.height as i32,
+            |memory, _| PrintWindow(hwnd, memory, PW_RENDERFULLCONTENT) != 0,
+        )
+    }?;
+    save_thumbnail(
+        image::RgbaImage::from_raw(window.width, window.height, rgba)?,
+        out,
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn list_windows() -> Vec<WindowInfo> {
+    use objc2::rc::Retained;
+    use objc2_foundation::{NSArray, NSDictionary, NSNumber, NSString};
+    use std::ffi::c_void;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGWindowListCopyWindowInfo(option: u32, relative_to_window: u32) -> *mut c_void;
+    }
+    // kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements
+    const OPTIONS: u32 = (1 << 0) | (1 << 4);
+
+    let Some(windows) = (unsafe {
+        Retained::from_raw(CGWindowListCopyWindowInfo(OPTIONS, 0) as *mut NSArray<NSDictionary>)
+    }) else {
+        return Vec::new();
+    };
+
+    fn get<T: objc2::DowncastTarget>(window: &NSDictionary, key: &str) -> Option<Retained<T>> {
+        window
+            .objectForKey(&NSString::from_str(key))?
+            .downcast::<T>()
+            .ok()
+    }
+    fn number(dictionary: &NSDictionary, key: &str) -> Option<f64> {
+        get::<NSNumber>(dictionary, key).map(|number| number.as_f64())
+    }
+
+    windows
+        .iter()
+        .filter_map(|window| {
+            // Layer 0 holds normal app windows; menus and the dock sit above it
+            if number(&window, "kCGWindowLayer")? != 0.0 {
+                return None;
+            }
+            if number(&window, "kCGWindowOwnerPID")? as u32 == std::process::id() {
+                return None;
+            }
+
+            let owner = get::<NSString>(&window, "kCGWindowOwnerName").map(|name| name.to_string());
+            // Window titles need Screen Recording permission; fall back to the app
+            let name = get::<NSString>(&window, "kCGWindowName")
+                .map(|name| name.to_string())
+                .filter(|name| !name.is_empty())
+                .or_else(|| owner.clone())?;
+            let bounds = get::<NSDictionary>(&window, "kCGWindowBounds")?;
+
+            Some(WindowInfo {
+                native_id: (number(&window, "kCGWindowNumber")? as u32).to_string(),
+                name,
+                owner,
+                x: number(&bounds, "X")? as i32,
+                y: number(&bounds, "Y")? as i32,
+                width: number(&bounds, "Width")? as u32,
+                height: number(&bounds, "Height")? as u32,
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn window_thumbnail(window: &WindowInfo, out: &Path) -> Option<PathBuf> {
+    // -o leaves out the window's shadow
+    thumbnail_with("screencapture", &["-x", "-o", "-l", &window.native_id], out)
+}
+
+// wmctrl only sees X11 windows; on Wayland the portal picker does this job
+#[cfg(target_os = "linux")]
+fn list_windows() -> Vec<WindowInfo> {
+    let Ok(output) = std::process::Command::new("wmctrl")
+        .args(["-l", "-p", "-G"])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    // "<id> <desktop> <pid> <x> <y> <width> <height> <host> <title…>"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let native_id = fields.next()?.to_string();
+            // Desktop -1 is sticky panels and docks
+            if fields.next()? == "-1" {
+                return None;
+            }
+            if fields.next()?.parse::<u32>().ok() == Some(std::process::id()) {
+                return None;
+            }
+            let x = fields.next()?.parse().ok()?;
+            let y = fields.next()?.parse().ok()?;
+            let width = fields.next()?.parse().ok()?;
+            let height = fields.next()?.parse().ok()?;
+            fields.next()?;
+            let name = fields.collect::<Vec<_>>().join(" ");
+            (!name.is_empty()).then_some(WindowInfo {
+                native_id,
+                name,
+                owner: None,
+                x,
+                y,
+                width,
+                height,
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn window_thumbnail(window: &WindowInfo, out: &Path) -> Option<PathBuf> {
+    thumbnail_with("import", &["-silent", "-window", &window.native_id], out)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn list_windows() -> Vec<WindowInfo> {
+    Vec::new()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn window_thumbnail(_window: &WindowInfo, _out: &Path) -> Option<PathBuf> {
+    None
+}
+
+fn enumerate(app: &AppHandle, thumbnails: Option<&Path>) -> Result<Vec<CaptureSource>, String> {
+    let mut sources = Vec::new();
+
+    for (index, monitor) in app
+        .available_monitors()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .enumerate()
+    {
+        let (position, size) = (monitor.position(), monitor.size());
+        sources.push(CaptureSource {
+            id: format!("display:{}", index),
+            kind: CaptureSourceKind::Display,
+            name: monitor
+                .name()
+                .cloned()
+                .unwrap_or_else(|| format!("Display {}", index + 1)),
+            owner: None,
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            thumbnail: thumbnails.and_then(|directory| {
+                display_thumbnail(
+                    index,
+                    position.x,
+                    position.y,
+                    size.width,
+                    size.height,
+                    &directory.join(format!("display-{}.png", index)),
+                )
+            }),
+        });
+    }
+
+    for window in list_windows() {
+        if window.width == 0 || window.height == 0 {
+            continue;
+        }
+        sources.push(CaptureSource {
+            id: format!("window:{}", window.native_id),
+            kind: CaptureSourceKind::Window,
+            thumbnail: thumbnails.and_then(|directory| {
+                window_thumbnail(
+                    &window,
+                    &directory.join(format!("window-{}.png", window.native_id)),
+                )
+            }),
+            name: window.name,
+            owner: window.owner,
+            x: window.x,
+            y: window.y,
+            width: window.width,
+            height: window.height,
+        });
+    }
+
+    Ok(sources)
+}
+
+// Previews are rendered fresh on every call, replacing the last set
+#[tauri::command]
+pub async fn list_capture_sources(app_handle: AppHandle) -> Result<Vec<CaptureSource>, String> {
+    let directory = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join("capture-sources");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).map_err(|e| e.to_string())?;
+
+    tauri::async_runtime::spawn_blocking(move || enumerate(&app_handle, Some(&directory)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// Records the user's pick and emits "capture-source-granted" so the call
+// layer can start sharing it
+#[tauri::command]
+pub async fn grant_capture_source(
+    app_handle: AppHandle,
+    id: String,
+) -> Result<CaptureSource, String> {
+    let app = app_handle.clone();
+    let source = tauri::async_runtime::spawn_blocking(move || enumerate(&app, None))
+        .await
+        .map_err(|e| e.to_string())??
+        .into_iter()
+        .find(|source| source.id == id)
+        .ok_or_else(|| format!("Capture source is gone: {}", id))?;
+
+    *app_handle.state::<CaptureState>().0.lock().unwrap() = Some(source.clone());
+    let _ = app_handle.emit("capture-source-granted", &source);
+    Ok(source)
+}
+
+// Call when sharing stops
+#[tauri::command]
+pub async fn revoke_capture_source(app_handle: AppHandle) -> Result<(), String> {
+    if app_handle
+        .state::<CaptureState>()
+        .0
+        .lock()
+        .unwrap()
+        .take()
+        .is_some()
+    {
+        let _ = app_handle.emit("capture-source-revoked", ());
+    }
+    Ok(())
+}
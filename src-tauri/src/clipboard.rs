@@ -0,0 +1,41 @@
+// Clipboard images (screenshots, copied pictures) for pasting into chats
+use base64::Engine;
+use serde::Serialize;
+use std::io::Cursor;
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipboardImage {
+    width: u32,
+    height: u32,
+    png_base64: String,
+}
+
+// `None` when the clipboard holds no image
+#[tauri::command]
+pub async fn read_clipboard_image(app_handle: AppHandle) -> Result<Option<ClipboardImage>, String> {
+    let Ok(image) = app_handle.clipboard().read_image() else {
+        return Ok(None);
+    };
+    let (width, height) = (image.width(), image.height());
+    let rgba = image.rgba().to_vec();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let buffer = image::RgbaImage::from_raw(width, height, rgba)
+            .ok_or_else(|| "Clipboard image has an unexpected size".to_string())?;
+
+        let mut png = Vec::new();
+        buffer
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .map_err(|e| e.to_string())?;
+
+        Ok(Some(ClipboardImage {
+            width,
+            height,
+            png_base64: base64::engine::general_purpose::STANDARD.encode(png),
+        }))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
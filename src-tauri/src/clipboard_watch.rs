@@ -0,0 +1,152 @@
+// Opt-in clipboard watcher that spots invite links and offers to open them
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(1500);
+// At most one offer per this window, however busy the clipboard is
+const MIN_EMIT_INTERVAL: Duration = Duration::from_secs(10);
+// Ignore huge clipboard contents; invite links are short
+const MAX_TEXT_LEN: usize = 2048;
+// Links already offered this session, so copying one again doesn't re-prompt
+const SEEN_LINKS_CAPACITY: usize = 50;
+// The site emailed invitations link to, the same SITE_URL the backend builds
+// them from. Set at build time; without it only msn-messenger:// links count.
+const SITE_URL: Option<&str> = option_env!("SITE_URL");
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum DetectedLink {
+    ContactInvite { url: String, token: String },
+    GroupInvite { url: String, group_id: String },
+}
+
+impl DetectedLink {
+    fn url(&self) -> &str {
+        match self {
+            DetectedLink::ContactInvite { url, .. } | DetectedLink::GroupInvite { url, .. } => url,
+        }
+    }
+}
+
+// `<SITE_URL>/signup`, so links to other sites aren't taken for invites
+fn is_signup_page(url: &url::Url) -> bool {
+    let Some(site) = SITE_URL.and_then(|site| url::Url::parse(site).ok()) else {
+        return false;
+    };
+    site.origin() == url.origin()
+        && url.path() == format!("{}/signup", site.path().trim_end_matches('/'))
+}
+
+// Recognizes:
+// - <SITE_URL>/signup?invitation=TOKEN   (emailed invitations)
+// - msn-messenger://invite/TOKEN
+// - msn-messenger://group/GROUP_ID
+fn parse_link(candidate: &str) -> Option<DetectedLink> {
+    let url = url::Url::parse(candidate).ok()?;
+
+    match url.scheme() {
+        "https" | "http" if is_signup_page(&url) => {
+            let token = url
+                .query_pairs()
+                .find(|(key, _)| key == "invitation")
+                .map(|(_, value)| value.into_owned())
+                .filter(|token| !token.is_empty())?;
+            Some(DetectedLink::ContactInvite {
+                url: url.to_string(),
+                token,
+            })
+        }
+        "msn-messenger" => {
+            let id = url.path().trim_matches('/').to_string();
+            if id.is_empty() || id.contains('/') {
+                return None;
+            }
+            match url.host_str()? {
+                "invite" => Some(DetectedLink::ContactInvite {
+                    url: url.to_string(),
+                    token: id,
+                }),
+                "group" => Some(DetectedLink::GroupInvite {
+                    url: url.to_string(),
+                    group_id: id,
+                }),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn find_link(text: &str) -> Option<DetectedLink> {
+    if text.len() > MAX_TEXT_LEN {
+        return None;
+    }
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| matches!(c, '<' | '>' | '"' | '\'' | '(' | ')')))
+        .find_map(parse_link)
+}
+
+#[tauri::command]
+pub async fn set_clipboard_link_detection(
+    app_handle: AppHandle,
+    enabled: bool,
+) -> Result<(), String> {
+    let mut settings = crate::settings::load(&app_handle)?;
+    settings.clipboard_link_detection = enabled;
+    crate::settings::save(&app_handle, &settings)
+}
+
+#[tauri::command]
+pub async fn get_clipboard_link_detection(app_handle: AppHandle) -> Result<bool, String> {
+    Ok(crate::settings::load(&app_handle)?.clipboard_link_detection)
+}
+
+// The clipboard is never read while the setting is off
+pub async fn watch_clipboard(app: AppHandle) {
+    let mut last_text: Option<String> = None;
+    let mut last_emit: Option<Instant> = None;
+    let mut seen: VecDeque<String> = VecDeque::new();
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        let enabled = crate::settings::load(&app)
+            .map(|settings| settings.clipboard_link_detection)
+            .unwrap_or(false);
+        if !enabled {
+            last_text = None;
+            continue;
+        }
+
+        let Ok(text) = app.clipboard().read_text() else {
+            continue;
+        };
+        if last_text.as_deref() == Some(text.as_str()) {
+            continue;
+        }
+
+        let link = find_link(&text);
+        last_text = Some(text);
+
+        let Some(link) = link else {
+            continue;
+        };
+        if seen.iter().any(|url| url == link.url()) {
+            continue;
+        }
+        if last_emit.is_some_and(|at| at.elapsed() < MIN_EMIT_INTERVAL) {
+            continue;
+        }
+
+        if seen.len() == SEEN_LINKS_CAPACITY {
+            seen.pop_front();
+        }
+        seen.push_back(link.url().to_string());
+        last_emit = Some(Instant::now());
+
+        let _ = app.emit("clipboard-link-detected", &link);
+    }
+}
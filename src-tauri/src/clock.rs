@@ -0,0 +1,110 @@
+// System clock and timezone change handling
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+// How often the watcher samples the wall clock and timezone
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+// Wall-clock drift against the monotonic clock that counts as a clock change
+const CLOCK_JUMP_THRESHOLD_SECS: i64 = 60;
+
+#[derive(Debug, Clone, Serialize)]
+struct ClockChange {
+    reason: String, // "timezone", "utc_offset", "clock"
+    timezone: Option<String>,
+    utc_offset_minutes: i32,
+    previous_utc_offset_minutes: i32,
+    drift_seconds: i64,
+}
+
+// Last known quiet-hours state, so changes can be emitted as events
+#[derive(Default)]
+pub struct QuietHoursState(Mutex<Option<bool>>);
+
+fn current_utc_offset_minutes() -> i32 {
+    chrono::Local::now().offset().local_minus_utc() / 60
+}
+
+// Re-evaluates quiet hours against the current local time and emits
+// `quiet-hours-changed` when the state flips (or always, when `force_emit`).
+pub async fn refresh_quiet_hours(app: &AppHandle, force_emit: bool) -> Result<bool, String> {
+    let settings = crate::load_notification_settings(app.clone()).await?;
+    let active = crate::is_within_quiet_hours(&settings, chrono::Local::now().time());
+
+    let changed = {
+        let state = app.state::<QuietHoursState>();
+        let mut last = state.0.lock().unwrap();
+        let changed = *last != Some(active);
+        *last = Some(active);
+        changed
+    };
+
+    if changed || force_emit {
+        app.emit(
+            "quiet-hours-changed",
+            serde_json::json!({ "active": active }),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(active)
+}
+
+#[tauri::command]
+pub async fn get_quiet_hours_state(app_handle: AppHandle) -> Result<bool, String> {
+    refresh_quiet_hours(&app_handle, false).await
+}
+
+// Polls the wall clock and local timezone, emitting `system-clock-changed` when
+// the user travels, DST kicks in, or the clock is set manually. Resuming from
+// sleep also shows up as a clock jump, which is fine: quiet hours need a
+// re-check then too.
+pub async fn watch_system_clock(app: AppHandle) {
+    let mut last_wall = chrono::Utc::now();
+    let mut last_instant = Instant::now();
+    let mut last_offset = current_utc_offset_minutes();
+    let mut last_timezone = iana_time_zone::get_timezone().ok();
+
+    let _ = refresh_quiet_hours(&app, false).await;
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        let now_wall = chrono::Utc::now();
+        let elapsed = chrono::Duration::from_std(last_instant.elapsed()).unwrap_or_default();
+        let drift_seconds = (now_wall - (last_wall + elapsed)).num_seconds();
+        let offset = current_utc_offset_minutes();
+        let timezone = iana_time_zone::get_timezone().ok();
+
+        let reason = if timezone != last_timezone {
+            Some("timezone")
+        } else if offset != last_offset {
+            Some("utc_offset")
+        } else if drift_seconds.abs() >= CLOCK_JUMP_THRESHOLD_SECS {
+            Some("clock")
+        } else {
+            None
+        };
+
+        if let Some(reason) = reason {
+            let change = ClockChange {
+                reason: reason.to_string(),
+                timezone: timezone.clone(),
+                utc_offset_minutes: offset,
+                previous_utc_offset_minutes: last_offset,
+                drift_seconds,
+            };
+            let _ = app.emit("system-clock-changed", change);
+        }
+
+        // Quiet hours are re-checked on every tick so start/end boundaries are
+        // reported too; a clock change always re-announces the current state.
+        let _ = refresh_quiet_hours(&app, reason.is_some()).await;
+
+        last_wall = now_wall;
+        last_instant = Instant::now();
+        last_offset = offset;
+        last_timezone = timezone;
+    }
+}
@@ -0,0 +1,73 @@
+// What the main window's close button does
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogResult};
+
+use crate::i18n;
+
+// Minimizing to the tray is the default everywhere; on macOS that's also the
+// platform convention of the app staying alive with no windows open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseBehavior {
+    #[default]
+    MinimizeToTray,
+    Quit,
+    Ask,
+}
+
+fn hide_main_window(app_handle: &AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.hide();
+    }
+}
+
+fn ask(app_handle: &AppHandle) {
+    let minimize = i18n::t(app_handle, "close.minimize");
+    let quit = i18n::t(app_handle, "close.quit");
+    let cancel = i18n::t(app_handle, "close.cancel");
+
+    let app = app_handle.clone();
+    app_handle
+        .dialog()
+        .message(i18n::t(app_handle, "close.prompt"))
+        .title(i18n::t(app_handle, "close.title"))
+        .buttons(MessageDialogButtons::YesNoCancelCustom(
+            minimize.clone(),
+            quit.clone(),
+            cancel,
+        ))
+        .show_with_result(move |result| match result {
+            MessageDialogResult::Yes => hide_main_window(&app),
+            MessageDialogResult::No => app.exit(0),
+            MessageDialogResult::Custom(label) if label == minimize => hide_main_window(&app),
+            MessageDialogResult::Custom(label) if label == quit => app.exit(0),
+            _ => {}
+        });
+}
+
+// Called with the close already prevented
+pub fn handle_main_window_close(app_handle: &AppHandle) {
+    let behavior = crate::settings::load(app_handle)
+        .map(|settings| settings.close_behavior)
+        .unwrap_or_default();
+
+    match behavior {
+        CloseBehavior::MinimizeToTray => hide_main_window(app_handle),
+        CloseBehavior::Quit => app_handle.exit(0),
+        CloseBehavior::Ask => ask(app_handle),
+    }
+}
+
+// macOS: clicking the Dock icon with nothing visible brings the main window back
+#[cfg(target_os = "macos")]
+pub fn handle_reopen(app_handle: &AppHandle, has_visible_windows: bool) {
+    if has_visible_windows {
+        return;
+    }
+
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
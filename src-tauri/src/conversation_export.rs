@@ -0,0 +1,396 @@
+// Archives a whole conversation, messages and attachments, into one zip, or
+// writes its saved history out as a log in one of the classic formats
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::message_cache::{self, CachedMessage};
+use crate::shared_files::{self, SharedDirection, SharedFile};
+
+// Messages further apart than this start a new session in the log
+const SESSION_GAP_MS: i64 = 30 * 60 * 1000;
+const TEXT_RULE: &str = ".--------------------------------------------------------------------.";
+
+// A cached message reduced to what the exports show
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportMessage {
+    sender: String,
+    body: String,
+    sent_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    // A page like the one in conversation bundles
+    Html,
+    // Messenger's own message history files
+    Xml,
+    // Session-framed plain text, like the logs of the old add-ons
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    path: PathBuf,
+    messages: usize,
+    attachments: usize,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn format_time(millis: i64) -> String {
+    local_time(millis, "%Y-%m-%d %H:%M")
+}
+
+// Reads the fields the frontend's message objects carry. Senders go by their
+// name, or their email if they never set one.
+fn from_cache(message: CachedMessage) -> ExportMessage {
+    let payload = &message.payload;
+    let text = |value: &JsonValue| {
+        value
+            .as_str()
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+    let sender = text(&payload["sender"]["name"])
+        .or_else(|| text(&payload["sender"]["email"]))
+        .unwrap_or_else(|| "Unknown".to_string());
+    let body = match payload["messageType"].as_str() {
+        Some("file") => format!(
+            "Sent a file: {}",
+            text(&payload["fileName"]).unwrap_or_default()
+        ),
+        _ => text(&payload["content"]).unwrap_or_default(),
+    };
+    ExportMessage {
+        sender,
+        body,
+        sent_at: message.sent_at,
+    }
+}
+
+// The conversation's history from the local message cache, oldest first, so
+// it only reaches back as far as the cache does
+async fn cached_messages(app: &AppHandle, chat_id: &str) -> Result<Vec<ExportMessage>, String> {
+    let app = app.clone();
+    let chat_id = chat_id.to_string();
+    let cached =
+        tauri::async_runtime::spawn_blocking(move || message_cache::chat_history(&app, &chat_id))
+            .await
+            .map_err(|e| e.to_string())??;
+    Ok(cached.into_iter().map(from_cache).collect())
+}
+
+// One line of a message log, e.g. "[2024-05-01 21:14] Ana: hi"
+pub fn log_line(message: &CachedMessage) -> String {
+    let message = from_cache(message.clone());
+    format!(
+        "[{}] {}: {}",
+        format_time(message.sent_at),
+        message.sender,
+        message.body
+    )
+}
+
+fn sessions(messages: &[ExportMessage]) -> impl Iterator<Item = &[ExportMessage]> {
+    messages.chunk_by(|previous, next| next.sent_at - previous.sent_at <= SESSION_GAP_MS)
+}
+
+fn participants(session: &[ExportMessage]) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    for message in session {
+        if !names.contains(&message.sender.as_str()) {
+            names.push(&message.sender);
+        }
+    }
+    names
+}
+
+fn local_time(millis: i64, format: &str) -> String {
+    chrono::DateTime::from_timestamp_millis(millis)
+        .map(|time| {
+            time.with_timezone(&chrono::Local)
+                .format(format)
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+fn log_xml(messages: &[ExportMessage]) -> String {
+    let session_count = sessions(messages).count();
+    let mut xml = format!(
+        "<?xml version=\"1.0\"?>\n<?xml-stylesheet type='text/xsl' href='MessageLog.xsl'?>\n\
+         <Log FirstSessionID=\"1\" LastSessionID=\"{}\">",
+        session_count
+    );
+
+    let user = |name: &str| format!("<User FriendlyName=\"{}\"/>", escape_html(name));
+    for (index, session) in sessions(messages).enumerate() {
+        let participants = participants(session);
+        for message in session {
+            let recipients: String = participants
+                .iter()
+                .filter(|name| **name != message.sender)
+                .map(|name| user(name))
+                .collect();
+            let utc = chrono::DateTime::from_timestamp_millis(message.sent_at)
+                .map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+                .unwrap_or_default();
+            xml.push_str(&format!(
+                "<Message Date=\"{}\" Time=\"{}\" DateTime=\"{}\" SessionID=\"{}\">\
+                 <From>{}</From><To>{}</To>\
+                 <Text Style=\"font-family:Tahoma; color:#000000; \">{}</Text></Message>",
+                local_time(message.sent_at, "%Y-%m-%d"),
+                local_time(message.sent_at, "%H:%M:%S"),
+                utc,
+                index + 1,
+                user(&message.sender),
+                recipients,
+                escape_html(&message.body)
+            ));
+        }
+    }
+
+    xml.push_str("</Log>\n");
+    xml
+}
+
+fn log_text(messages: &[ExportMessage]) -> String {
+    let mut text = String::new();
+    for session in sessions(messages) {
+        let (Some(first), Some(last)) = (session.first(), session.last()) else {
+            continue;
+        };
+        text.push_str(&format!(
+            "{0}\n| Session Start: {1}\n| Participants:\n",
+            TEXT_RULE,
+            local_time(first.sent_at, "%A, %B %-d, %Y")
+        ));
+        for name in participants(session) {
+            text.push_str(&format!("|    {}\n", name));
+        }
+        text.push_str(TEXT_RULE);
+        text.push('\n');
+
+        for message in session {
+            text.push_str(&format!(
+                "[{}] {}: {}\n",
+                local_time(message.sent_at, "%H:%M"),
+                message.sender,
+                message.body
+            ));
+        }
+
+        text.push_str(&format!(
+            "{0}\n| Session Close: {1}\n{0}\n\n",
+            TEXT_RULE,
+            local_time(last.sent_at, "%A, %B %-d, %Y")
+        ));
+    }
+    text
+}
+
+fn index_html(
+    chat_id: &str,
+    messages: &[ExportMessage],
+    attachments: &[(String, SharedFile)],
+) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+         <style>body{{font-family:Tahoma,sans-serif;font-size:13px;max-width:800px;margin:2em auto}}\
+         .time{{color:#888}}.sender{{font-weight:bold}}p{{margin:.3em 0;white-space:pre-wrap}}</style>\n\
+         </head>\n<body>\n<h1>{0}</h1>\n<h2>Messages</h2>\n",
+        escape_html(chat_id)
+    );
+
+    for message in messages {
+        html.push_str(&format!(
+            "<p><span class=\"time\">[{}]</span> <span class=\"sender\">{}:</span> {}</p>\n",
+            format_time(message.sent_at),
+            escape_html(&message.sender),
+            escape_html(&message.body)
+        ));
+    }
+
+    if !attachments.is_empty() {
+        html.push_str("<h2>Attachments</h2>\n<ul>\n");
+        for (entry, file) in attachments {
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a> &ndash; {} {}</li>\n",
+                escape_html(entry),
+                escape_html(&file.file_name),
+                match file.direction {
+                    SharedDirection::Sent => "sent",
+                    SharedDirection::Received => "received",
+                },
+                format_time(file.shared_at)
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+// "attachments/<name>" inside the zip, numbered like "photo (1).jpg" when
+// another file already took the name
+fn attachment_entry(file_name: &str, taken: &mut HashSet<String>) -> String {
+    let name = Path::new(file_name);
+    let stem = name
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = name
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    let entry = std::iter::once(format!("attachments/{}", file_name))
+        .chain((1..).map(|n| format!("attachments/{} ({}){}", stem, n, extension)))
+        .find(|entry| !taken.contains(entry))
+        .unwrap();
+    taken.insert(entry.clone());
+    entry
+}
+
+fn write_bundle(
+    out: &Path,
+    chat_id: &str,
+    messages: &[ExportMessage],
+    files: Vec<SharedFile>,
+) -> Result<usize, String> {
+    let mut taken = HashSet::new();
+    let attachments: Vec<(String, SharedFile)> = files
+        .into_iter()
+        .map(|file| (attachment_entry(&file.file_name, &mut taken), file))
+        .collect();
+
+    let mut zip = ZipWriter::new(std::fs::File::create(out).map_err(|e| e.to_string())?);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // Photos and videos are already compressed
+    let stored = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+
+    zip.start_file("index.html", deflated)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(index_html(chat_id, messages, &attachments).as_bytes())
+        .map_err(|e| e.to_string())?;
+
+    zip.start_file("messages.json", deflated)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_writer_pretty(&mut zip, messages).map_err(|e| e.to_string())?;
+
+    for (entry, file) in &attachments {
+        let mut source = std::fs::File::open(&file.path).map_err(|e| e.to_string())?;
+        zip.start_file(entry.as_str(), stored)
+            .map_err(|e| e.to_string())?;
+        std::io::copy(&mut source, &mut zip).map_err(|e| e.to_string())?;
+    }
+
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(attachments.len())
+}
+
+// Writes `<path>.part` first so a failed export never leaves a broken zip.
+// Messages come from the message cache, like `export_conversation`, and
+// attachments from the shared files history; ones since deleted from disk
+// are skipped.
+#[tauri::command]
+pub async fn export_conversation_bundle(
+    app_handle: AppHandle,
+    chat_id: String,
+    path: String,
+) -> Result<ExportSummary, String> {
+    let path = PathBuf::from(path);
+    let messages = cached_messages(&app_handle, &chat_id).await?;
+    let files: Vec<SharedFile> = shared_files::list_for_chat(&app_handle, &chat_id)
+        .into_iter()
+        .filter(|file| file.path.is_file())
+        .collect();
+    if messages.is_empty() && files.is_empty() {
+        return Err("There is nothing saved from this conversation".to_string());
+    }
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    let message_count = messages.len();
+    let out = partial.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        write_bundle(&out, &chat_id, &messages, files)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let attachments = match result {
+        Ok(attachments) => attachments,
+        Err(error) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(error);
+        }
+    };
+    std::fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+
+    Ok(ExportSummary {
+        path,
+        messages: message_count,
+        attachments,
+    })
+}
+
+// Renders the conversation's history from the local message cache
+#[tauri::command]
+pub async fn export_conversation(
+    app_handle: AppHandle,
+    chat_id: String,
+    format: LogFormat,
+    path: String,
+) -> Result<ExportSummary, String> {
+    let path = PathBuf::from(path);
+    let messages = cached_messages(&app_handle, &chat_id).await?;
+    if messages.is_empty() {
+        return Err("There are no saved messages in this conversation".to_string());
+    }
+
+    let contents = match format {
+        LogFormat::Html => index_html(&chat_id, &messages, &[]),
+        LogFormat::Xml => log_xml(&messages),
+        LogFormat::Text => log_text(&messages),
+        LogFormat::Json => serde_json::to_string_pretty(&messages).map_err(|e| e.to_string())?,
+    };
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+    std::fs::write(&partial, contents).map_err(|e| e.to_string())?;
+    std::fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+
+    Ok(ExportSummary {
+        path,
+        messages: messages.len(),
+        attachments: 0,
+    })
+}
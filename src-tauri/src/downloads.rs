@@ -0,0 +1,685 @@
+// Download manager for incoming attachments
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+use tauri_plugin_opener::OpenerExt;
+use tokio::io::AsyncWriteExt;
+
+use crate::shared_files::{self, SharedDirection, SharedFile};
+
+const DOWNLOADS_STORE: &str = "downloads.json";
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+// Control values a running download task polls between chunks
+const RUN: u8 = 0;
+const PAUSE: u8 = 1;
+const CANCEL: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadStatus {
+    InProgress,
+    Paused,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadRecord {
+    id: String,
+    url: String,
+    chat_id: Option<String>,
+    file_name: String,
+    path: PathBuf,
+    downloaded: u64,
+    total: Option<u64>,
+    status: DownloadStatus,
+    error: Option<String>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct DownloadProgress<'a> {
+    download_id: &'a str,
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct DownloadState {
+    records: Mutex<Vec<DownloadRecord>>,
+    controls: Mutex<HashMap<String, Arc<AtomicU8>>>,
+}
+
+// The user's chosen directory, or the OS Downloads folder
+pub fn download_directory(app: &AppHandle) -> Result<PathBuf, String> {
+    match crate::settings::load_account(app)?.download_directory {
+        Some(directory) => Ok(directory),
+        None => app.path().download_dir().map_err(|e| e.to_string()),
+    }
+}
+
+// Bytes land in `<path>.part` until the download completes
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+fn sanitize_file_name(name: &str) -> Option<String> {
+    Path::new(name)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .filter(|name| !name.is_empty())
+}
+
+fn file_name_from_url(url: &reqwest::Url) -> Option<String> {
+    url.path_segments()?
+        .next_back()
+        .map(|segment| percent_encoding::percent_decode_str(segment).decode_utf8_lossy())
+        .and_then(|segment| sanitize_file_name(&segment))
+}
+
+// "photo.jpg" -> "photo (1).jpg" -> "photo (2).jpg" ... skipping names that are
+// taken on disk or already claimed by another unfinished download
+pub fn unique_path(directory: &Path, file_name: &str, claimed: &[PathBuf]) -> PathBuf {
+    let candidate = directory.join(file_name);
+    let is_free = |path: &Path| {
+        !path.exists() && !part_path(path).exists() && !claimed.iter().any(|c| c == path)
+    };
+    if is_free(&candidate) {
+        return candidate;
+    }
+
+    let name = Path::new(file_name);
+    let stem = name
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = name
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| directory.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|path| is_free(path))
+        .unwrap()
+}
+
+fn save_records(app: &AppHandle, records: &[DownloadRecord]) -> Result<(), String> {
+    let path = crate::accounts::store_path(app, DOWNLOADS_STORE);
+    let store = crate::store_recovery::open(app, &path)?;
+
+    store.set("downloads", serde_json::to_value(records).unwrap());
+    crate::store_recovery::save(app, path, &store)
+}
+
+// Downloads that were running when the app quit come back paused, ready to resume
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let store =
+        crate::store_recovery::open(app, crate::accounts::store_path(app, DOWNLOADS_STORE))?;
+
+    let mut records: Vec<DownloadRecord> = store
+        .get("downloads")
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+
+    for record in records
+        .iter_mut()
+        .filter(|record| record.status == DownloadStatus::InProgress)
+    {
+        record.status = DownloadStatus::Paused;
+        record.downloaded = std::fs::metadata(part_path(&record.path))
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+    }
+
+    save_records(app, &records)?;
+    *app.state::<DownloadState>().records.lock().unwrap() = records;
+
+    Ok(())
+}
+
+fn update_record(
+    app: &AppHandle,
+    id: &str,
+    update: impl FnOnce(&mut DownloadRecord),
+) -> Option<DownloadRecord> {
+    let state = app.state::<DownloadState>();
+    let mut records = state.records.lock().unwrap();
+    let record = records.iter_mut().find(|record| record.id == id)?;
+    update(record);
+    record.updated_at = chrono::Utc::now().timestamp_millis();
+    let record = record.clone();
+    let _ = save_records(app, &records);
+    drop(records);
+
+    let _ = app.emit("download-updated", &record);
+    Some(record)
+}
+
+fn get_record(app: &AppHandle, id: &str) -> Result<DownloadRecord, String> {
+    app.state::<DownloadState>()
+        .records
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|record| record.id == id)
+        .cloned()
+        .ok_or_else(|| format!("Unknown download: {}", id))
+}
+
+// "bytes 100-999/1000" -> 1000
+fn total_from_content_range(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .parse()
+        .ok()
+}
+
+async fn fetch(
+    app: &AppHandle,
+    record: &DownloadRecord,
+    control: &AtomicU8,
+) -> Result<u64, String> {
+    let partial = part_path(&record.path);
+    let offset = tokio::fs::metadata(&partial)
+        .await
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+
+    let mut request = crate::net::client(app)?.get(&record.url);
+    if offset > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+    }
+    let mut response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    // Servers without range support send the whole file again
+    let resumed = offset > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    let mut downloaded = if resumed { offset } else { 0 };
+    let total = if resumed {
+        total_from_content_range(&response)
+    } else {
+        response.content_length()
+    };
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&partial)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    update_record(app, &record.id, |record| {
+        record.downloaded = downloaded;
+        record.total = total;
+    });
+
+    let mut last_progress = Instant::now();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        if control.load(Ordering::Relaxed) != RUN {
+            file.flush().await.map_err(|e| e.to_string())?;
+            return Err("Stopped".to_string());
+        }
+
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+        downloaded += chunk.len() as u64;
+
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            let _ = app.emit(
+                "download-progress",
+                DownloadProgress {
+                    download_id: &record.id,
+                    downloaded,
+                    total,
+                },
+            );
+        }
+    }
+
+    file.flush().await.map_err(|e| e.to_string())?;
+    drop(file);
+    tokio::fs::rename(&partial, &record.path)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(downloaded)
+}
+
+fn spawn_download(app: &AppHandle, record: DownloadRecord) {
+    let control = Arc::new(AtomicU8::new(RUN));
+    app.state::<DownloadState>()
+        .controls
+        .lock()
+        .unwrap()
+        .insert(record.id.clone(), control.clone());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = fetch(&app, &record, &control).await;
+
+        app.state::<DownloadState>()
+            .controls
+            .lock()
+            .unwrap()
+            .remove(&record.id);
+
+        let partial = part_path(&record.path);
+        // A pause or cancel that lands after the last chunk is too late; the
+        // file is already in place
+        match (result, control.load(Ordering::Relaxed)) {
+            (Ok(downloaded), _) => {
+                update_record(&app, &record.id, |record| {
+                    record.status = DownloadStatus::Completed;
+                    record.downloaded = downloaded;
+                    record.total = Some(downloaded);
+                });
+                if let Some(chat_id) = record.chat_id.clone() {
+                    shared_files::record(
+                        &app,
+                        SharedFile {
+                            id: record.id.clone(),
+                            chat_id,
+                            peer_id: None,
+                            direction: SharedDirection::Received,
+                            file_name: record.file_name.clone(),
+                            path: record.path.clone(),
+                            size: downloaded,
+                            shared_at: chrono::Utc::now().timestamp_millis(),
+                        },
+                    );
+                }
+            }
+            (_, CANCEL) => {
+                let _ = tokio::fs::remove_file(&partial).await;
+                update_record(&app, &record.id, |record| {
+                    record.status = DownloadStatus::Cancelled;
+                });
+            }
+            (_, PAUSE) => {
+                let downloaded = tokio::fs::metadata(&partial)
+                    .await
+                    .map(|metadata| metadata.len())
+                    .unwrap_or(0);
+                update_record(&app, &record.id, |record| {
+                    record.status = DownloadStatus::Paused;
+                    record.downloaded = downloaded;
+                });
+            }
+            // The .part file stays so a retry can pick up where this stopped
+            (Err(error), _) => {
+                update_record(&app, &record.id, |record| {
+                    record.status = DownloadStatus::Failed;
+                    record.error = Some(error);
+                });
+            }
+        }
+    });
+}
+
+fn set_control(app: &AppHandle, id: &str, value: u8) -> bool {
+    match app
+        .state::<DownloadState>()
+        .controls
+        .lock()
+        .unwrap()
+        .get(id)
+    {
+        Some(control) => {
+            control.store(value, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+// Partial files of paused or failed downloads, which only resuming would use
+pub fn stale_partials(app: &AppHandle) -> Vec<PathBuf> {
+    let state = app.state::<DownloadState>();
+    let controls = state.controls.lock().unwrap();
+    let records = state.records.lock().unwrap();
+    records
+        .iter()
+        .filter(|record| !controls.contains_key(&record.id))
+        .map(|record| part_path(&record.path))
+        .filter(|partial| partial.exists())
+        .collect()
+}
+
+// Deletes those partial files. Their downloads are cancelled, since there's
+// nothing left to resume.
+pub fn discard_stale_partials(app: &AppHandle) {
+    let stale: Vec<String> = {
+        let state = app.state::<DownloadState>();
+        let controls = state.controls.lock().unwrap();
+        let records = state.records.lock().unwrap();
+        records
+            .iter()
+            .filter(|record| !controls.contains_key(&record.id) && part_path(&record.path).exists())
+            .map(|record| record.id.clone())
+            .collect()
+    };
+    for id in stale {
+        update_record(app, &id, |record| {
+            let _ = std::fs::remove_file(part_path(&record.path));
+            if record.status != DownloadStatus::Completed {
+                record.status = DownloadStatus::Cancelled;
+            }
+        });
+    }
+}
+
+#[tauri::command]
+pub async fn start_download(
+    app_handle: AppHandle,
+    url: String,
+    file_name: Option<String>,
+    chat_id: Option<String>,
+) -> Result<DownloadRecord, String> {
+    let parsed = reqwest::Url::parse(&url).map_err(|e| e.to_string())?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Unsupported URL: {}", url));
+    }
+
+    let file_name = file_name
+        .as_deref()
+        .and_then(sanitize_file_name)
+        .or_else(|| file_name_from_url(&parsed))
+        .unwrap_or_else(|| "download".to_string());
+
+    let directory = download_directory(&app_handle)?;
+    tokio::fs::create_dir_all(&directory)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let record = {
+        let state = app_handle.state::<DownloadState>();
+        let mut records = state.records.lock().unwrap();
+
+        let claimed: Vec<PathBuf> = records
+            .iter()
+            .filter(|record| {
+                matches!(
+                    record.status,
+                    DownloadStatus::InProgress | DownloadStatus::Paused
+                )
+            })
+            .map(|record| record.path.clone())
+            .collect();
+        let path = unique_path(&directory, &file_name, &claimed);
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let record = DownloadRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            url,
+            chat_id,
+            file_name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or(file_name),
+            path,
+            downloaded: 0,
+            total: None,
+            status: DownloadStatus::InProgress,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        records.push(record.clone());
+        save_records(&app_handle, &records)?;
+        record
+    };
+
+    let _ = app_handle.emit("download-updated", &record);
+    spawn_download(&app_handle, record.clone());
+
+    Ok(record)
+}
+
+#[tauri::command]
+pub async fn pause_download(app_handle: AppHandle, download_id: String) -> Result<(), String> {
+    get_record(&app_handle, &download_id)?;
+    set_control(&app_handle, &download_id, PAUSE);
+    Ok(())
+}
+
+// Also retries failed downloads, continuing from the partial file
+#[tauri::command]
+pub async fn resume_download(
+    app_handle: AppHandle,
+    download_id: String,
+) -> Result<DownloadRecord, String> {
+    let record = get_record(&app_handle, &download_id)?;
+    if !matches!(
+        record.status,
+        DownloadStatus::Paused | DownloadStatus::Failed
+    ) {
+        return Err("Only paused or failed downloads can be resumed".to_string());
+    }
+
+    let record = update_record(&app_handle, &download_id, |record| {
+        record.status = DownloadStatus::InProgress;
+        record.error = None;
+    })
+    .ok_or_else(|| format!("Unknown download: {}", download_id))?;
+    spawn_download(&app_handle, record.clone());
+
+    Ok(record)
+}
+
+#[tauri::command]
+pub async fn cancel_download(app_handle: AppHandle, download_id: String) -> Result<(), String> {
+    let record = get_record(&app_handle, &download_id)?;
+
+    // Not running: clean up the partial file ourselves
+    if !set_control(&app_handle, &download_id, CANCEL) && record.status != DownloadStatus::Completed
+    {
+        let _ = tokio::fs::remove_file(part_path(&record.path)).await;
+        update_record(&app_handle, &download_id, |record| {
+            record.status = DownloadStatus::Cancelled;
+        });
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_downloads(app_handle: AppHandle) -> Result<Vec<DownloadRecord>, String> {
+    Ok(app_handle
+        .state::<DownloadState>()
+        .records
+        .lock()
+        .unwrap()
+        .clone())
+}
+
+#[tauri::command]
+pub async fn get_download_directory(app_handle: AppHandle) -> Result<PathBuf, String> {
+    download_directory(&app_handle)
+}
+
+// `None` goes back to the OS Downloads folder
+#[tauri::command]
+pub async fn set_download_directory(
+    app_handle: AppHandle,
+    path: Option<String>,
+) -> Result<PathBuf, String> {
+    let directory = path.map(PathBuf::from);
+
+    if let Some(directory) = &directory {
+        if !directory.is_absolute() {
+            return Err("Download directory must be an absolute path".to_string());
+        }
+        tokio::fs::create_dir_all(directory)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // Catch read-only locations now rather than on the first download
+        let probe = directory.join(".bootleg-msn-write-test");
+        tokio::fs::write(&probe, b"")
+            .await
+            .map_err(|e| format!("Can't save files to {}: {}", directory.display(), e))?;
+        let _ = tokio::fs::remove_file(&probe).await;
+    }
+
+    let mut settings = crate::settings::load_account(&app_handle)?;
+    settings.download_directory = directory;
+    crate::settings::save_account(&app_handle, &settings)?;
+
+    download_directory(&app_handle)
+}
+
+// Asks where to save, then writes either `bytes` or the contents of `url`.
+// The OS dialog confirms overwrites, and the existing file is only replaced
+// once the new contents are completely written. `None` if the user cancelled.
+#[tauri::command]
+pub async fn save_file_as(
+    app_handle: AppHandle,
+    suggested_name: String,
+    bytes: Option<Vec<u8>>,
+    url: Option<String>,
+) -> Result<Option<PathBuf>, String> {
+    if bytes.is_none() && url.is_none() {
+        return Err("Nothing to save: pass either bytes or a url".to_string());
+    }
+
+    let file_name = sanitize_file_name(&suggested_name).unwrap_or_else(|| "download".to_string());
+    let directory = download_directory(&app_handle)?;
+
+    let dialog_app = app_handle.clone();
+    let chosen = tauri::async_runtime::spawn_blocking(move || {
+        dialog_app
+            .dialog()
+            .file()
+            .set_directory(directory)
+            .set_file_name(file_name)
+            .blocking_save_file()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let Some(chosen) = chosen else {
+        return Ok(None);
+    };
+    let path = chosen.into_path().map_err(|e| e.to_string())?;
+    if path.is_dir() {
+        return Err(format!("{} is a folder", path.display()));
+    }
+
+    let partial = part_path(&path);
+    let written = match (bytes, url) {
+        (Some(bytes), _) => tokio::fs::write(&partial, bytes)
+            .await
+            .map_err(|e| e.to_string()),
+        (None, Some(url)) => save_url_to(&app_handle, &url, &partial).await,
+        (None, None) => unreachable!(),
+    };
+
+    match written {
+        Ok(()) => {
+            tokio::fs::rename(&partial, &path)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(Some(path))
+        }
+        Err(error) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            Err(error)
+        }
+    }
+}
+
+async fn save_url_to(app: &AppHandle, url: &str, path: &Path) -> Result<(), String> {
+    let mut response = crate::net::client(app)?
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+    }
+    file.flush().await.map_err(|e| e.to_string())
+}
+
+// Files the app saved or sent itself; anything else could be a program the
+// webview has no business launching
+fn is_known_file(app: &AppHandle, path: &Path) -> bool {
+    let downloaded = app
+        .state::<DownloadState>()
+        .records
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|record| record.status == DownloadStatus::Completed && record.path == path);
+    downloaded
+        || crate::file_transfer::is_completed_file(app, path)
+        || shared_files::contains_path(app, path)
+}
+
+// Only files from a completed download, transfer or the shared files list
+#[tauri::command]
+pub async fn open_file(app_handle: AppHandle, path: String) -> Result<(), String> {
+    if !Path::new(&path).exists() {
+        return Err(format!("File not found: {}", path));
+    }
+    if !is_known_file(&app_handle, Path::new(&path)) {
+        return Err(format!("Not a downloaded or shared file: {}", path));
+    }
+
+    app_handle
+        .opener()
+        .open_path(path, None::<&str>)
+        .map_err(|e| e.to_string())
+}
+
+// Opens the file manager with `path` selected (Explorer, Finder, or the
+// freedesktop FileManager1 service), falling back to just opening its folder
+#[tauri::command]
+pub async fn show_in_folder(app_handle: AppHandle, path: String) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    if !path.exists() {
+        return Err(format!("File not found: {}", path.display()));
+    }
+
+    let app = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if app.opener().reveal_item_in_dir(&path).is_ok() {
+            return Ok(());
+        }
+
+        let folder = path
+            .parent()
+            .filter(|folder| folder.is_dir())
+            .ok_or_else(|| format!("Folder not found for: {}", path.display()))?;
+        app.opener()
+            .open_path(folder.to_string_lossy(), None::<&str>)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,50 @@
+// Half-written messages, kept per chat so they survive closing the chat
+// window or the app
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreBuilder};
+
+const DRAFTS_STORE: &str = "drafts.json";
+// Drafts change on every keystroke, so writes wait for typing to pause
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+fn drafts_store(app: &AppHandle) -> Result<Arc<Store<Wry>>, String> {
+    let path = crate::accounts::store_path(app, DRAFTS_STORE);
+    crate::store_recovery::check(app, &path)?;
+    StoreBuilder::new(app, path)
+        .auto_save(SAVE_DELAY)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+// An empty draft clears it. Pending writes are flushed when the app exits.
+#[tauri::command]
+pub async fn save_draft(
+    app_handle: AppHandle,
+    chat_id: String,
+    content: String,
+) -> Result<(), String> {
+    let store = drafts_store(&app_handle)?;
+    if content.trim().is_empty() {
+        store.delete(&chat_id);
+    } else {
+        store.set(chat_id, content);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_draft(app_handle: AppHandle, chat_id: String) -> Result<Option<String>, String> {
+    let store = drafts_store(&app_handle)?;
+    Ok(store
+        .get(&chat_id)
+        .and_then(|value| value.as_str().map(str::to_string)))
+}
+
+// Called once the draft has been sent
+#[tauri::command]
+pub async fn clear_draft(app_handle: AppHandle, chat_id: String) -> Result<(), String> {
+    drafts_store(&app_handle)?.delete(&chat_id);
+    Ok(())
+}
@@ -0,0 +1,143 @@
+// OS file drops onto app windows, handled natively so sends skip the webview sandbox
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, DragDropEvent, Emitter, Manager, Window};
+
+// Upper bound for a single `read_dropped_file` call
+const MAX_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+// Paths the user dropped; only these can be streamed back to the webview
+#[derive(Default)]
+pub struct DropState(Mutex<HashSet<PathBuf>>);
+
+#[derive(Debug, Clone, Serialize)]
+struct DroppedFile {
+    path: PathBuf,
+    name: String,
+    size: u64,
+    mime_type: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RejectedFile {
+    path: PathBuf,
+    reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FilesDropped {
+    chat_id: Option<String>,
+    files: Vec<DroppedFile>,
+    rejected: Vec<RejectedFile>,
+}
+
+fn validate(path: &Path) -> Result<DroppedFile, String> {
+    let path = path.canonicalize().map_err(|e| e.to_string())?;
+    let metadata = std::fs::metadata(&path).map_err(|e| e.to_string())?;
+    if metadata.is_dir() {
+        return Err("Folders can't be sent".to_string());
+    }
+    if !metadata.is_file() {
+        return Err("Not a regular file".to_string());
+    }
+    if metadata.len() == 0 {
+        return Err("File is empty".to_string());
+    }
+    // Catch files we can't actually read (permissions, cloud placeholders)
+    std::fs::File::open(&path).map_err(|e| e.to_string())?;
+
+    Ok(DroppedFile {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        size: metadata.len(),
+        mime_type: mime_guess::from_path(&path)
+            .first_or_octet_stream()
+            .essence_str()
+            .to_string(),
+        path,
+    })
+}
+
+// Chat windows are opened at `/?chat=<id>&window=chat`
+fn chat_id_for(window: &Window) -> Option<String> {
+    let webview = window.app_handle().get_webview_window(window.label())?;
+    let url = webview.url().ok()?;
+    url.query_pairs()
+        .find(|(key, _)| key == "chat")
+        .map(|(_, value)| value.into_owned())
+}
+
+pub fn handle_drag_drop(window: &Window, event: &DragDropEvent) {
+    let DragDropEvent::Drop { paths, .. } = event else {
+        return;
+    };
+
+    let mut files = Vec::new();
+    let mut rejected = Vec::new();
+    for path in paths {
+        match validate(path) {
+            Ok(file) => files.push(file),
+            Err(reason) => rejected.push(RejectedFile {
+                path: path.clone(),
+                reason,
+            }),
+        }
+    }
+
+    let app = window.app_handle();
+    app.state::<DropState>()
+        .0
+        .lock()
+        .unwrap()
+        .extend(files.iter().map(|file| file.path.clone()));
+
+    let _ = app.emit_to(
+        window.label(),
+        "files-dropped",
+        FilesDropped {
+            chat_id: chat_id_for(window),
+            files,
+            rejected,
+        },
+    );
+}
+
+// Streams part of a dropped file for previews or uploads that need the bytes
+#[tauri::command]
+pub async fn read_dropped_file(
+    app_handle: AppHandle,
+    path: String,
+    offset: u64,
+    length: u64,
+) -> Result<tauri::ipc::Response, String> {
+    let path = PathBuf::from(path);
+    if !app_handle
+        .state::<DropState>()
+        .0
+        .lock()
+        .unwrap()
+        .contains(&path)
+    {
+        return Err("File was not dropped onto the app".to_string());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut file = std::fs::File::open(&path).map_err(|e| e.to_string())?;
+        file.seek(SeekFrom::Start(offset))
+            .map_err(|e| e.to_string())?;
+
+        let mut bytes = Vec::new();
+        file.take(length.min(MAX_CHUNK_SIZE))
+            .read_to_end(&mut bytes)
+            .map_err(|e| e.to_string())?;
+
+        Ok(tauri::ipc::Response::new(bytes))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,178 @@
+// Dragging files out of a chat window into Explorer, Finder or the desktop
+use std::path::{Path, PathBuf};
+use tauri::WebviewWindow;
+
+#[cfg(target_os = "windows")]
+fn begin_drag(window: &WebviewWindow, path: &Path) -> Result<(), String> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::core::{IUnknown_Vtbl, GUID};
+    use windows_sys::Win32::System::Ole::DROPEFFECT_COPY;
+    use windows_sys::Win32::UI::Shell::{
+        ILClone, ILCreateFromPathW, ILFindLastID, ILFree, ILRemoveLastID, SHCreateDataObject,
+        SHDoDragDrop,
+    };
+
+    const IID_IDATAOBJECT: GUID = GUID::from_u128(0x0000010e_0000_0000_c000_000000000046);
+
+    let hwnd = window.hwnd().map_err(|e| e.to_string())?.0;
+    let wide: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+
+    unsafe {
+        let absolute = ILCreateFromPathW(wide.as_ptr());
+        if absolute.is_null() {
+            return Err(format!("Could not drag {}", path.display()));
+        }
+        // The shell wants the parent folder plus the item relative to it
+        let folder = ILClone(absolute);
+        ILRemoveLastID(folder);
+        let item = ILFindLastID(absolute) as *const _;
+
+        let mut data_object = std::ptr::null_mut();
+        let created = SHCreateDataObject(
+            folder,
+            1,
+            &item,
+            std::ptr::null_mut(),
+            &IID_IDATAOBJECT,
+            &mut data_object,
+        );
+        ILFree(folder);
+        ILFree(absolute);
+        if created < 0 {
+            return Err(format!("Could not drag {}", path.display()));
+        }
+
+        // Runs a modal loop until the mouse is released; a null drop source
+        // gets the shell's default cursor feedback
+        let mut effect = 0;
+        let result = SHDoDragDrop(
+            hwnd,
+            data_object,
+            std::ptr::null_mut(),
+            DROPEFFECT_COPY,
+            &mut effect,
+        );
+        let vtable = *(data_object as *const *const IUnknown_Vtbl);
+        ((*vtable).Release)(data_object);
+
+        if result < 0 {
+            return Err(format!("Drag failed (0x{:08x})", result));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn begin_drag(window: &WebviewWindow, path: &Path) -> Result<(), String> {
+    use objc2::msg_send;
+    use objc2::runtime::{AnyObject, Bool};
+    use objc2_foundation::{NSPoint, NSRect, NSSize, NSString};
+
+    let ns_window = window.ns_window().map_err(|e| e.to_string())? as *mut AnyObject;
+    let file = NSString::from_str(&path.to_string_lossy());
+
+    unsafe {
+        // The drag has to be tied to the mouse event that's being handled
+        let app: *mut AnyObject = msg_send![objc2::class!(NSApplication), sharedApplication];
+        let event: *mut AnyObject = msg_send![app, currentEvent];
+        if event.is_null() {
+            return Err("No mouse event to start the drag from".to_string());
+        }
+        let view: *mut AnyObject = msg_send![ns_window, contentView];
+        let location: NSPoint = msg_send![event, locationInWindow];
+        let rect = NSRect::new(location, NSSize::new(1.0, 1.0));
+
+        let started: Bool = msg_send![
+            view,
+            dragFile: &*file,
+            fromRect: rect,
+            slideBack: Bool::YES,
+            event: event
+        ];
+        if !started.as_bool() {
+            return Err(format!("Could not drag {}", path.display()));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn begin_drag(window: &WebviewWindow, path: &Path) -> Result<(), String> {
+    use gtk::prelude::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let gtk_window = window.gtk_window().map_err(|e| e.to_string())?;
+    let uri = gtk::glib::filename_to_uri(path, None).map_err(|e| e.to_string())?;
+
+    // Handlers only live for this drag; drag-end removes both of them
+    let handlers = Rc::new(RefCell::new(Vec::new()));
+    handlers
+        .borrow_mut()
+        .push(gtk_window.connect_drag_data_get(move |_, _, data, _, _| {
+            data.set_uris(&[uri.as_str()]);
+        }));
+    let end_handlers = handlers.clone();
+    handlers
+        .borrow_mut()
+        .push(gtk_window.connect_drag_end(move |widget, _| {
+            for handler in end_handlers.borrow_mut().drain(..) {
+                widget.disconnect(handler);
+            }
+        }));
+
+    let targets = gtk::TargetList::new(&[gtk::TargetEntry::new(
+        "text/uri-list",
+        gtk::TargetFlags::OTHER_APP,
+        0,
+    )]);
+    let event = gtk::current_event();
+    let started = gtk_window.drag_begin_with_coordinates(
+        &targets,
+        gtk::gdk::DragAction::COPY,
+        1,
+        event.as_ref(),
+        -1,
+        -1,
+    );
+
+    if started.is_none() {
+        for handler in handlers.borrow_mut().drain(..) {
+            gtk_window.disconnect(handler);
+        }
+        return Err(format!("Could not drag {}", path.display()));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn begin_drag(_window: &WebviewWindow, _path: &Path) -> Result<(), String> {
+    Err("Dragging files out is not supported on this platform".to_string())
+}
+
+// Call from the webview's dragstart (after preventing the default) so the
+// mouse button is still held when the native drag takes over
+#[tauri::command]
+pub async fn start_file_drag(window: WebviewWindow, path: String) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    if !path.is_absolute() || !path.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+
+    // Native drag sessions must start on the UI thread
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let target = window.clone();
+    window
+        .run_on_main_thread(move || {
+            let _ = tx.send(begin_drag(&target, &path));
+        })
+        .map_err(|e| e.to_string())?;
+
+    rx.await.map_err(|e| e.to_string())?
+}
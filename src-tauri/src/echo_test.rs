@@ -0,0 +1,73 @@
+// Records a few seconds from the microphone and plays them straight back, so
+// users can hear what the other side of a call would
+use rodio::buffer::SamplesBuffer;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::AppHandle;
+
+const MAX_SECONDS: u64 = 10;
+// Below this the mic is most likely muted or the wrong one is selected
+const SILENCE_PEAK: f32 = 0.01;
+const CLIPPING_PEAK: f32 = 0.99;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EchoTestResult {
+    rms: f32,
+    peak: f32,
+    silent: bool,
+    clipping: bool,
+}
+
+fn record(app: &AppHandle, seconds: u64) -> Result<(EchoTestResult, SamplesBuffer), String> {
+    let samples = Arc::new(Mutex::new(Vec::new()));
+    let mut format = None;
+
+    let recorded = samples.clone();
+    let stream = crate::mic_level::open_input(app, |config| {
+        format = Some((config.channels, config.sample_rate.0));
+        move |sample| recorded.lock().unwrap().push(sample)
+    })?;
+    std::thread::sleep(Duration::from_secs(seconds));
+    drop(stream);
+
+    let (channels, sample_rate) = format.ok_or("No microphone found")?;
+    let samples = std::mem::take(&mut *samples.lock().unwrap());
+    Ok((
+        measure(&samples),
+        SamplesBuffer::new(channels, sample_rate, samples),
+    ))
+}
+
+fn measure(samples: &[f32]) -> EchoTestResult {
+    let peak = samples
+        .iter()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    let rms = if samples.is_empty() {
+        0.0
+    } else {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    };
+
+    EchoTestResult {
+        rms,
+        peak,
+        silent: peak < SILENCE_PEAK,
+        clipping: peak >= CLIPPING_PEAK,
+    }
+}
+
+// Records for `seconds` (at most 10) on the selected microphone, then plays
+// it back on the selected speakers before returning the levels
+#[tauri::command]
+pub async fn run_echo_test(app_handle: AppHandle, seconds: u64) -> Result<EchoTestResult, String> {
+    let seconds = seconds.clamp(1, MAX_SECONDS);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let (result, recording) = record(&app_handle, seconds)?;
+        crate::sound::play_to_end(&app_handle, recording)?;
+        Ok(result)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
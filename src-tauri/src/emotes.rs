@@ -0,0 +1,98 @@
+// Emoticon usage for the picker's "recent" row. Kept natively so every chat
+// window sees the same list.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::store_recovery;
+
+const EMOTES_STORE: &str = "emotes.json";
+const MAX_ID_LENGTH: usize = 64;
+// The longest unused emoticons are forgotten past this many. Going by use
+// count instead would forget each new one straight away once the list fills.
+const MAX_TRACKED: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EmoteUsage {
+    count: u32,
+    last_used: i64, // ms since the epoch
+}
+
+// Held while recording, so uses from two windows at once both count
+#[derive(Default)]
+pub struct EmotesState(Mutex<()>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FrequentEmote {
+    id: String,
+    count: u32,
+    last_used: i64,
+}
+
+fn load_usage(app: &AppHandle) -> Result<HashMap<String, EmoteUsage>, String> {
+    let path = crate::accounts::store_path(app, EMOTES_STORE);
+    Ok(store_recovery::read(app, path, "usage")?.unwrap_or_default())
+}
+
+// Most used first; ties go to the most recent
+fn ranked(usage: HashMap<String, EmoteUsage>) -> Vec<FrequentEmote> {
+    let mut emotes: Vec<FrequentEmote> = usage
+        .into_iter()
+        .map(|(id, usage)| FrequentEmote {
+            id,
+            count: usage.count,
+            last_used: usage.last_used,
+        })
+        .collect();
+    emotes.sort_by(|a, b| b.count.cmp(&a.count).then(b.last_used.cmp(&a.last_used)));
+    emotes
+}
+
+// Emits "frequent-emotes-changed" so open pickers refresh
+#[tauri::command]
+pub async fn record_emote_use(app_handle: AppHandle, id: String) -> Result<(), String> {
+    if id.is_empty() || id.len() > MAX_ID_LENGTH {
+        return Err(format!("Invalid emoticon id: {}", id));
+    }
+
+    let state = app_handle.state::<EmotesState>();
+    let _recording = state.0.lock().unwrap();
+    let mut usage = load_usage(&app_handle)?;
+    let entry = usage.entry(id).or_insert(EmoteUsage {
+        count: 0,
+        last_used: 0,
+    });
+    entry.count = entry.count.saturating_add(1);
+    entry.last_used = chrono::Utc::now().timestamp_millis();
+
+    if usage.len() > MAX_TRACKED {
+        let mut by_last_use: Vec<(String, i64)> = usage
+            .iter()
+            .map(|(id, usage)| (id.clone(), usage.last_used))
+            .collect();
+        by_last_use.sort_by_key(|(_, last_used)| *last_used);
+        let excess = usage.len() - MAX_TRACKED;
+        for (id, _) in &by_last_use[..excess] {
+            usage.remove(id);
+        }
+    }
+
+    let path = crate::accounts::store_path(&app_handle, EMOTES_STORE);
+    let store = store_recovery::open(&app_handle, &path)?;
+    store.set("usage", serde_json::to_value(&usage).unwrap());
+    store_recovery::save(&app_handle, path, &store)?;
+
+    let _ = app_handle.emit("frequent-emotes-changed", ());
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_frequent_emotes(
+    app_handle: AppHandle,
+    limit: u32,
+) -> Result<Vec<FrequentEmote>, String> {
+    let mut emotes = ranked(load_usage(&app_handle)?);
+    emotes.truncate(limit as usize);
+    Ok(emotes)
+}
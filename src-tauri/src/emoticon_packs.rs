@@ -0,0 +1,302 @@
+// Installable custom emoticon packs. A pack is a zip holding `pack.json` plus
+// the images it names:
+// { "name": "Party pack", "emoticons": [{ "shortcut": "(party)", "file": "party.gif", "name": "Party" }] }
+// Images are served at `emoticon://localhost/<pack id>%2F<file>`; pass each
+// emoticon's `path` to `convertFileSrc(path, "emoticon")` for the URL.
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+use zip::ZipArchive;
+
+pub const SCHEME: &str = "emoticon";
+const MANIFEST: &str = "pack.json";
+const MAX_MANIFEST_BYTES: u64 = 256 * 1024;
+const MAX_ARCHIVE_BYTES: u64 = 20 * 1024 * 1024;
+const MAX_IMAGE_BYTES: u64 = 1024 * 1024;
+const MAX_EMOTICONS: usize = 500;
+const MAX_NAME_LENGTH: usize = 64;
+// Messenger's own limit, so shortcuts stay quick to type
+const MAX_SHORTCUT_LENGTH: usize = 7;
+// What the bundled decoders can read
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "gif", "jpg", "jpeg", "webp", "bmp"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestEmoticon {
+    shortcut: String,
+    file: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Manifest {
+    name: String,
+    emoticons: Vec<ManifestEmoticon>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Emoticon {
+    shortcut: String,
+    name: Option<String>,
+    path: String, // "<pack id>/<file>", for the emoticon:// protocol
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmoticonPack {
+    id: String,
+    name: String,
+    emoticons: Vec<Emoticon>,
+}
+
+fn packs_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("emoticon-packs"))
+}
+
+// Ids are generated on import, so anything else can't be a pack of ours
+fn pack_dir(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    uuid::Uuid::parse_str(id).map_err(|_| format!("Unknown emoticon pack: {}", id))?;
+    Ok(packs_dir(app)?.join(id))
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest, String> {
+    let file = std::fs::File::open(dir.join(MANIFEST)).map_err(|e| e.to_string())?;
+    serde_json::from_reader(file).map_err(|e| e.to_string())
+}
+
+fn is_plain_file_name(name: &str) -> bool {
+    Path::new(name).file_name().and_then(|file| file.to_str()) == Some(name)
+}
+
+// Only plain file names with an image extension; no paths into or out of
+// the pack's folder
+fn validate_file_name(name: &str) -> Result<(), String> {
+    if !is_plain_file_name(name) || name == MANIFEST {
+        return Err(format!("Invalid image file name: {}", name));
+    }
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    if !extension.is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.as_str())) {
+        return Err(format!(
+            "{} must be a PNG, GIF, JPEG, WebP or BMP image",
+            name
+        ));
+    }
+    Ok(())
+}
+
+// Reads one entry, trusting neither its declared size nor its contents
+fn read_entry(
+    archive: &mut ZipArchive<std::fs::File>,
+    name: &str,
+    limit: u64,
+) -> Result<Vec<u8>, String> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|_| format!("The pack is missing {}", name))?;
+    if entry.size() > limit {
+        return Err(format!("{} is larger than {} KB", name, limit / 1024));
+    }
+
+    let mut bytes = Vec::new();
+    entry
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > limit {
+        return Err(format!("{} is larger than {} KB", name, limit / 1024));
+    }
+    Ok(bytes)
+}
+
+fn validate_manifest(manifest: &mut Manifest) -> Result<(), String> {
+    manifest.name = manifest.name.trim().chars().take(MAX_NAME_LENGTH).collect();
+    if manifest.name.is_empty() {
+        return Err("The pack has no name".to_string());
+    }
+    if manifest.emoticons.is_empty() {
+        return Err("The pack has no emoticons".to_string());
+    }
+    if manifest.emoticons.len() > MAX_EMOTICONS {
+        return Err(format!(
+            "Packs can hold at most {} emoticons",
+            MAX_EMOTICONS
+        ));
+    }
+
+    let mut shortcuts = HashSet::new();
+    for emoticon in &mut manifest.emoticons {
+        emoticon.shortcut = emoticon.shortcut.trim().to_string();
+        let length = emoticon.shortcut.chars().count();
+        if length == 0 || length > MAX_SHORTCUT_LENGTH {
+            return Err(format!(
+                "Shortcuts must be 1 to {} characters: '{}'",
+                MAX_SHORTCUT_LENGTH, emoticon.shortcut
+            ));
+        }
+        if !shortcuts.insert(emoticon.shortcut.clone()) {
+            return Err(format!(
+                "'{}' is used by more than one emoticon",
+                emoticon.shortcut
+            ));
+        }
+        emoticon.name = emoticon
+            .name
+            .as_deref()
+            .map(|name| {
+                name.trim()
+                    .chars()
+                    .take(MAX_NAME_LENGTH)
+                    .collect::<String>()
+            })
+            .filter(|name| !name.is_empty());
+        validate_file_name(&emoticon.file)?;
+    }
+    Ok(())
+}
+
+fn install(archive_path: &Path, packs: &Path) -> Result<(String, Manifest), String> {
+    let file = std::fs::File::open(archive_path).map_err(|e| e.to_string())?;
+    if file.metadata().map_err(|e| e.to_string())?.len() > MAX_ARCHIVE_BYTES {
+        return Err(format!(
+            "Emoticon packs can be at most {} MB",
+            MAX_ARCHIVE_BYTES / 1024 / 1024
+        ));
+    }
+    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+
+    let manifest = read_entry(&mut archive, MANIFEST, MAX_MANIFEST_BYTES)?;
+    let mut manifest: Manifest = serde_json::from_slice(&manifest).map_err(|e| e.to_string())?;
+    validate_manifest(&mut manifest)?;
+
+    let mut names: Vec<&String> = manifest
+        .emoticons
+        .iter()
+        .map(|emoticon| &emoticon.file)
+        .collect();
+    names.sort();
+    names.dedup();
+    let mut images = Vec::new();
+    for name in names {
+        let bytes = read_entry(&mut archive, name, MAX_IMAGE_BYTES)?;
+        image::load_from_memory(&bytes).map_err(|_| format!("{} isn't a readable image", name))?;
+        images.push((name.clone(), bytes));
+    }
+
+    // Unpacked next to the others and renamed into place once complete
+    let id = uuid::Uuid::new_v4().to_string();
+    let partial = packs.join(format!("{}.part", id));
+    let result = (|| {
+        std::fs::create_dir_all(&partial).map_err(|e| e.to_string())?;
+        for (name, bytes) in &images {
+            std::fs::write(partial.join(name), bytes).map_err(|e| e.to_string())?;
+        }
+        let manifest_file =
+            std::fs::File::create(partial.join(MANIFEST)).map_err(|e| e.to_string())?;
+        serde_json::to_writer_pretty(manifest_file, &manifest).map_err(|e| e.to_string())?;
+        std::fs::rename(&partial, packs.join(&id)).map_err(|e| e.to_string())
+    })();
+    if let Err(error) = result {
+        let _ = std::fs::remove_dir_all(&partial);
+        return Err(error);
+    }
+
+    Ok((id, manifest))
+}
+
+fn describe(id: String, manifest: Manifest) -> EmoticonPack {
+    let emoticons = manifest
+        .emoticons
+        .into_iter()
+        .map(|emoticon| Emoticon {
+            path: format!("{}/{}", id, emoticon.file),
+            shortcut: emoticon.shortcut,
+            name: emoticon.name,
+        })
+        .collect();
+    EmoticonPack {
+        id,
+        name: manifest.name,
+        emoticons,
+    }
+}
+
+fn image_path(app: &AppHandle, path: &str) -> Option<PathBuf> {
+    let decoded = percent_encoding::percent_decode_str(path.trim_start_matches('/'))
+        .decode_utf8()
+        .ok()?;
+    let (id, file) = decoded.split_once('/')?;
+    if !is_plain_file_name(file) || file == MANIFEST {
+        return None;
+    }
+    Some(pack_dir(app, id).ok()?.join(file))
+}
+
+// Handler for the emoticon:// protocol, registered on the app builder
+pub fn handle_request(app: &AppHandle, request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let image = image_path(app, request.uri().path())
+        .and_then(|path| Some((std::fs::read(&path).ok()?, path)));
+    let Some((bytes, path)) = image else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Vec::new())
+            .unwrap();
+    };
+
+    let mime = mime_guess::from_path(&path).first_or_octet_stream();
+    Response::builder()
+        .header(header::CONTENT_TYPE, mime.as_ref())
+        // A pack's files never change under its id
+        .header(header::CACHE_CONTROL, "max-age=31536000, immutable")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(bytes)
+        .unwrap()
+}
+
+#[tauri::command]
+pub async fn list_emoticon_packs(app_handle: AppHandle) -> Result<Vec<EmoticonPack>, String> {
+    let Ok(entries) = std::fs::read_dir(packs_dir(&app_handle)?) else {
+        return Ok(Vec::new());
+    };
+
+    let mut packs: Vec<EmoticonPack> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry.file_name().to_str()?.to_string();
+            uuid::Uuid::parse_str(&id).ok()?;
+            let manifest = read_manifest(&entry.path()).ok()?;
+            Some(describe(id, manifest))
+        })
+        .collect();
+    packs.sort_by_key(|pack| pack.name.to_lowercase());
+    Ok(packs)
+}
+
+#[tauri::command]
+pub async fn import_emoticon_pack(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<EmoticonPack, String> {
+    let packs = packs_dir(&app_handle)?;
+    std::fs::create_dir_all(&packs).map_err(|e| e.to_string())?;
+
+    let (id, manifest) =
+        tauri::async_runtime::spawn_blocking(move || install(Path::new(&path), &packs))
+            .await
+            .map_err(|e| e.to_string())??;
+    Ok(describe(id, manifest))
+}
+
+#[tauri::command]
+pub async fn remove_emoticon_pack(app_handle: AppHandle, pack_id: String) -> Result<(), String> {
+    let dir = pack_dir(&app_handle, &pack_id)?;
+    std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())
+}
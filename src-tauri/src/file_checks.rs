@@ -0,0 +1,255 @@
+// Checks run on incoming files: type/size policy before accepting, and an
+// optional virus scan once the bytes are on disk
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IncomingFilePolicy {
+    pub allowed_extensions: Vec<String>, // empty allows any extension
+    pub allowed_mime_types: Vec<String>, // "image/*" wildcards; empty allows any
+    pub max_size_mb: Option<u64>,
+    pub scan_with_system_scanner: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileVerdict {
+    pub allowed: bool,
+    pub reason: Option<String>,
+    pub scanner: Option<&'static str>,
+}
+
+impl FileVerdict {
+    fn allow() -> Self {
+        Self {
+            allowed: true,
+            reason: None,
+            scanner: None,
+        }
+    }
+
+    fn block(reason: String) -> Self {
+        Self {
+            allowed: false,
+            reason: Some(reason),
+            scanner: None,
+        }
+    }
+}
+
+enum ScanOutcome {
+    Clean(&'static str),
+    Infected(&'static str, Option<String>),
+    Unavailable,
+}
+
+fn load_policy(app: &AppHandle) -> IncomingFilePolicy {
+    crate::settings::load(app)
+        .map(|settings| settings.incoming_files)
+        .unwrap_or_default()
+}
+
+fn mime_allowed(mime: &str, allowed: &[String]) -> bool {
+    allowed.iter().any(|pattern| {
+        let pattern = pattern.to_lowercase();
+        match pattern.strip_suffix("/*") {
+            Some(kind) => mime.split('/').next() == Some(kind),
+            None => mime == pattern,
+        }
+    })
+}
+
+// Type and size policy, evaluated from what the sender told us
+pub fn check_incoming(app: &AppHandle, file_name: &str, size: u64) -> FileVerdict {
+    let policy = load_policy(app);
+
+    if let Some(max_size_mb) = policy.max_size_mb {
+        if size > max_size_mb.saturating_mul(1024 * 1024) {
+            return FileVerdict::block(format!("Larger than the {} MB limit", max_size_mb));
+        }
+    }
+
+    let extension = Path::new(file_name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if !policy.allowed_extensions.is_empty()
+        && !policy.allowed_extensions.iter().any(|allowed| {
+            allowed
+                .trim_start_matches('.')
+                .eq_ignore_ascii_case(&extension)
+        })
+    {
+        return FileVerdict::block(format!("'.{}' files are not allowed", extension));
+    }
+
+    let mime = mime_guess::from_path(file_name)
+        .first_or_octet_stream()
+        .essence_str()
+        .to_string();
+    if !policy.allowed_mime_types.is_empty() && !mime_allowed(&mime, &policy.allowed_mime_types) {
+        return FileVerdict::block(format!("{} files are not allowed", mime));
+    }
+
+    FileVerdict::allow()
+}
+
+#[cfg(target_os = "windows")]
+fn scan_with_amsi(path: &Path) -> ScanOutcome {
+    use windows_sys::Win32::System::Antimalware::{
+        AmsiCloseSession, AmsiInitialize, AmsiOpenSession, AmsiScanBuffer, AmsiUninitialize,
+        AMSI_RESULT_DETECTED,
+    };
+
+    // AMSI scans an in-memory buffer; very large files go to the next scanner
+    const MAX_AMSI_BYTES: u64 = 256 * 1024 * 1024;
+
+    let too_large = std::fs::metadata(path)
+        .map(|metadata| metadata.len() > MAX_AMSI_BYTES)
+        .unwrap_or(true);
+    if too_large {
+        return ScanOutcome::Unavailable;
+    }
+    let Ok(bytes) = std::fs::read(path) else {
+        return ScanOutcome::Unavailable;
+    };
+
+    let name: Vec<u16> = path
+        .to_string_lossy()
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+
+    unsafe {
+        let mut context = std::ptr::null_mut();
+        if AmsiInitialize(windows_sys::w!("BootlegMSN"), &mut context) < 0 {
+            return ScanOutcome::Unavailable;
+        }
+        let mut session = std::ptr::null_mut();
+        if AmsiOpenSession(context, &mut session) < 0 {
+            AmsiUninitialize(context);
+            return ScanOutcome::Unavailable;
+        }
+
+        let mut result = 0;
+        let status = AmsiScanBuffer(
+            context,
+            bytes.as_ptr() as *const _,
+            bytes.len() as u32,
+            name.as_ptr(),
+            session,
+            &mut result,
+        );
+
+        AmsiCloseSession(context, session);
+        AmsiUninitialize(context);
+
+        if status < 0 {
+            ScanOutcome::Unavailable
+        } else if result >= AMSI_RESULT_DETECTED {
+            ScanOutcome::Infected("amsi", None)
+        } else {
+            ScanOutcome::Clean("amsi")
+        }
+    }
+}
+
+// clamdscan talks to a running clamd; exit code 1 means a signature matched
+fn scan_with_clamav(path: &Path) -> ScanOutcome {
+    let Ok(output) = std::process::Command::new("clamdscan")
+        .args(["--no-summary", "--fdpass"])
+        .arg(path)
+        .output()
+    else {
+        return ScanOutcome::Unavailable;
+    };
+
+    match output.status.code() {
+        Some(0) => ScanOutcome::Clean("clamav"),
+        Some(1) => {
+            // "<path>: Eicar-Signature FOUND"
+            let threat = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .find_map(|line| {
+                    let (_, rest) = line.rsplit_once(": ")?;
+                    rest.strip_suffix(" FOUND").map(str::to_string)
+                });
+            ScanOutcome::Infected("clamav", threat)
+        }
+        _ => ScanOutcome::Unavailable,
+    }
+}
+
+fn scan(path: &Path) -> ScanOutcome {
+    #[cfg(target_os = "windows")]
+    if let outcome @ (ScanOutcome::Clean(_) | ScanOutcome::Infected(..)) = scan_with_amsi(path) {
+        return outcome;
+    }
+
+    scan_with_clamav(path)
+}
+
+// A missing scanner doesn't block the file; the verdict says so instead
+fn verdict_for(outcome: ScanOutcome) -> FileVerdict {
+    match outcome {
+        ScanOutcome::Clean(scanner) => FileVerdict {
+            allowed: true,
+            reason: None,
+            scanner: Some(scanner),
+        },
+        ScanOutcome::Infected(scanner, threat) => FileVerdict {
+            allowed: false,
+            reason: Some(match threat {
+                Some(threat) => format!("Malware detected: {}", threat),
+                None => "Malware detected".to_string(),
+            }),
+            scanner: Some(scanner),
+        },
+        ScanOutcome::Unavailable => FileVerdict {
+            allowed: true,
+            reason: Some("No virus scanner available".to_string()),
+            scanner: None,
+        },
+    }
+}
+
+// Runs the system scanner when the user enabled it
+pub fn scan_if_enabled(app: &AppHandle, path: &Path) -> Option<FileVerdict> {
+    load_policy(app)
+        .scan_with_system_scanner
+        .then(|| verdict_for(scan(path)))
+}
+
+pub fn emit_verdict(app: &AppHandle, transfer_id: &str, file_name: &str, verdict: &FileVerdict) {
+    let _ = app.emit(
+        "file-verdict",
+        serde_json::json!({
+            "transfer_id": transfer_id,
+            "file_name": file_name,
+            "verdict": verdict,
+        }),
+    );
+}
+
+#[tauri::command]
+pub async fn check_incoming_file(
+    app_handle: AppHandle,
+    file_name: String,
+    size: u64,
+) -> Result<FileVerdict, String> {
+    Ok(check_incoming(&app_handle, &file_name, size))
+}
+
+// Scans regardless of the setting, for files that arrived another way
+#[tauri::command]
+pub async fn scan_file(path: String) -> Result<FileVerdict, String> {
+    let path = std::path::PathBuf::from(path);
+    if !path.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || verdict_for(scan(&path)))
+        .await
+        .map_err(|e| e.to_string())
+}
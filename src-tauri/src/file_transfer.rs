@@ -0,0 +1,1029 @@
+// Direct peer-to-peer file transfers, like classic MSN
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+
+use crate::rate_limit::RateLimiter;
+use crate::shared_files::{self, SharedDirection, SharedFile};
+use crate::{checksum, downloads, file_checks, trusted_contacts};
+
+const TRANSFERS_STORE: &str = "file-transfers.json";
+const CHUNK_SIZE: usize = 64 * 1024;
+// How long a send offer waits for the other side to accept
+const OFFER_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const ACCEPT_POLL: Duration = Duration::from_secs(1);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+// A peer that neither sends nor takes data for this long is given up on
+const STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferDirection {
+    Send,
+    Receive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    Pending,
+    InProgress,
+    Completed,
+    Cancelled,
+    Failed,
+    // Stopped partway by a crash, restart or dropped connection; resumable
+    Interrupted,
+}
+
+impl TransferStatus {
+    fn is_finished(self) -> bool {
+        matches!(
+            self,
+            TransferStatus::Completed | TransferStatus::Cancelled | TransferStatus::Failed
+        )
+    }
+}
+
+// What the sender shares with the peer over chat so it can connect back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferOffer {
+    transfer_id: String,
+    file_name: String,
+    size: u64,
+    addresses: Vec<IpAddr>,
+    port: u16,
+    token: String,
+    // Hex SHA-256 of the whole file; the receiver checks what arrived against it
+    sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferRecord {
+    id: String,
+    chat_id: String,
+    // Who sent the file, for incoming transfers
+    peer_id: Option<String>,
+    direction: TransferDirection,
+    file_name: String,
+    // Source file when sending, destination once a receive is accepted
+    path: Option<PathBuf>,
+    size: u64,
+    sha256: String,
+    transferred: u64,
+    status: TransferStatus,
+    error: Option<String>,
+    created_at: i64,
+    updated_at: i64,
+    // Bytes received so far, kept across restarts so a receive can resume
+    partial_path: Option<PathBuf>,
+    // Incoming offers keep what's needed to connect to the sender
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offer: Option<TransferOffer>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TransferProgress<'a> {
+    transfer_id: &'a str,
+    transferred: u64,
+    total: u64,
+}
+
+// Controls for a transfer that's currently running or waiting for its peer
+#[derive(Default)]
+struct ActiveTransfer {
+    cancel: AtomicBool,
+    // Wakes a read or write that's waiting on the peer when cancelled
+    cancelled: Notify,
+    limit: RateLimiter,
+}
+
+#[derive(Default)]
+pub struct TransferState {
+    records: Mutex<Vec<TransferRecord>>,
+    active: Mutex<HashMap<String, Arc<ActiveTransfer>>>,
+    // Global caps shared by every transfer in that direction
+    upload_limit: RateLimiter,
+    download_limit: RateLimiter,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferSpeedLimits {
+    upload_kbps: Option<u64>,
+    download_kbps: Option<u64>,
+}
+
+// Size of a receive's partial file, if one is still on disk
+fn received_bytes(record: &TransferRecord) -> Option<u64> {
+    let partial = record.partial_path.as_ref()?;
+    std::fs::metadata(partial)
+        .ok()
+        .map(|metadata| metadata.len())
+}
+
+fn save_records(app: &AppHandle, records: &[TransferRecord]) -> Result<(), String> {
+    let path = crate::accounts::store_path(app, TRANSFERS_STORE);
+    let store = crate::store_recovery::open(app, &path)?;
+
+    store.set("transfers", serde_json::to_value(records).unwrap());
+    crate::store_recovery::save(app, path, &store)
+}
+
+// Loads persisted records at startup. Sends and receives that have bytes on
+// disk are marked interrupted so they can be resumed; stale offers fail.
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let store =
+        crate::store_recovery::open(app, crate::accounts::store_path(app, TRANSFERS_STORE))?;
+
+    let mut records: Vec<TransferRecord> = store
+        .get("transfers")
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+
+    let now = chrono::Utc::now().timestamp_millis();
+    for record in records
+        .iter_mut()
+        .filter(|record| !record.status.is_finished())
+    {
+        match (record.direction, record.status, received_bytes(record)) {
+            (TransferDirection::Send, _, _) => record.status = TransferStatus::Interrupted,
+            (
+                TransferDirection::Receive,
+                TransferStatus::InProgress | TransferStatus::Interrupted,
+                Some(received),
+            ) => {
+                record.status = TransferStatus::Interrupted;
+                record.transferred = received;
+            }
+            _ => record.status = TransferStatus::Failed,
+        }
+        record.error = Some("Interrupted".to_string());
+        record.updated_at = now;
+    }
+
+    save_records(app, &records)?;
+
+    let settings = crate::settings::load(app)?;
+    let state = app.state::<TransferState>();
+    *state.records.lock().unwrap() = records;
+    state
+        .upload_limit
+        .set_kbps(settings.transfer_upload_limit_kbps);
+    state
+        .download_limit
+        .set_kbps(settings.transfer_download_limit_kbps);
+
+    Ok(())
+}
+
+fn insert_record(app: &AppHandle, record: TransferRecord) -> Result<(), String> {
+    let state = app.state::<TransferState>();
+    let mut records = state.records.lock().unwrap();
+    records.push(record.clone());
+    save_records(app, &records)?;
+    drop(records);
+
+    let _ = app.emit("file-transfer-updated", &record);
+    Ok(())
+}
+
+fn update_record(
+    app: &AppHandle,
+    id: &str,
+    update: impl FnOnce(&mut TransferRecord),
+) -> Option<TransferRecord> {
+    let state = app.state::<TransferState>();
+    let mut records = state.records.lock().unwrap();
+    let record = records.iter_mut().find(|record| record.id == id)?;
+    update(record);
+    record.updated_at = chrono::Utc::now().timestamp_millis();
+    let record = record.clone();
+    let _ = save_records(app, &records);
+    drop(records);
+
+    let _ = app.emit("file-transfer-updated", &record);
+    Some(record)
+}
+
+fn get_record(app: &AppHandle, id: &str) -> Option<TransferRecord> {
+    app.state::<TransferState>()
+        .records
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|record| record.id == id)
+        .cloned()
+}
+
+fn register_active(app: &AppHandle, id: &str) -> Arc<ActiveTransfer> {
+    let active = Arc::new(ActiveTransfer::default());
+    app.state::<TransferState>()
+        .active
+        .lock()
+        .unwrap()
+        .insert(id.to_string(), active.clone());
+    active
+}
+
+fn get_active(app: &AppHandle, id: &str) -> Option<Arc<ActiveTransfer>> {
+    app.state::<TransferState>()
+        .active
+        .lock()
+        .unwrap()
+        .get(id)
+        .cloned()
+}
+
+fn finish(app: &AppHandle, id: &str, result: Result<u64, String>, active: &ActiveTransfer) {
+    app.state::<TransferState>()
+        .active
+        .lock()
+        .unwrap()
+        .remove(id);
+
+    let record = update_record(app, id, |record| match result {
+        Ok(transferred) => {
+            record.transferred = transferred;
+            record.status = TransferStatus::Completed;
+            record.partial_path = None;
+        }
+        Err(_) if active.cancel.load(Ordering::Relaxed) => {
+            record.status = TransferStatus::Cancelled
+        }
+        // A receive that kept its partial file can pick up where it stopped
+        Err(error) => {
+            match received_bytes(record) {
+                Some(received) => {
+                    record.status = TransferStatus::Interrupted;
+                    record.transferred = received;
+                }
+                None => record.status = TransferStatus::Failed,
+            }
+            record.error = Some(error);
+        }
+    });
+
+    if let Some(record) = record.filter(|record| record.status == TransferStatus::Completed) {
+        if let Some(path) = record.path {
+            shared_files::record(
+                app,
+                SharedFile {
+                    id: record.id,
+                    chat_id: record.chat_id,
+                    peer_id: record.peer_id,
+                    direction: match record.direction {
+                        TransferDirection::Send => SharedDirection::Sent,
+                        TransferDirection::Receive => SharedDirection::Received,
+                    },
+                    file_name: record.file_name,
+                    path,
+                    size: record.size,
+                    shared_at: record.updated_at,
+                },
+            );
+        }
+    }
+}
+
+// A file this app finished sending or receiving
+pub fn is_completed_file(app: &AppHandle, path: &Path) -> bool {
+    app.state::<TransferState>()
+        .records
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|record| {
+            record.status == TransferStatus::Completed && record.path.as_deref() == Some(path)
+        })
+}
+
+// Partial files of receives that aren't running, which only resuming would use
+pub fn stale_partials(app: &AppHandle) -> Vec<PathBuf> {
+    let state = app.state::<TransferState>();
+    let active = state.active.lock().unwrap();
+    let records = state.records.lock().unwrap();
+    records
+        .iter()
+        .filter(|record| !active.contains_key(&record.id))
+        .filter_map(|record| record.partial_path.clone())
+        .filter(|partial| partial.exists())
+        .collect()
+}
+
+// Deletes those partial files. Their receives are cancelled, since there's
+// nothing left to resume.
+pub fn discard_stale_partials(app: &AppHandle) {
+    let stale: Vec<String> = {
+        let state = app.state::<TransferState>();
+        let active = state.active.lock().unwrap();
+        let records = state.records.lock().unwrap();
+        records
+            .iter()
+            .filter(|record| {
+                !active.contains_key(&record.id)
+                    && record
+                        .partial_path
+                        .as_ref()
+                        .is_some_and(|partial| partial.exists())
+            })
+            .map(|record| record.id.clone())
+            .collect()
+    };
+    for id in stale {
+        update_record(app, &id, |record| {
+            if let Some(partial) = record.partial_path.take() {
+                let _ = std::fs::remove_file(partial);
+            }
+            if !record.status.is_finished() {
+                record.status = TransferStatus::Cancelled;
+            }
+        });
+    }
+}
+
+fn partial_path(dest: &Path) -> PathBuf {
+    dest.with_extension(match dest.extension() {
+        Some(extension) => format!("{}.part", extension.to_string_lossy()),
+        None => "part".to_string(),
+    })
+}
+
+// Addresses the peer can try to reach us on. Connecting a UDP socket sends
+// nothing; it just asks the OS which interface would route there.
+fn local_addresses() -> Vec<IpAddr> {
+    let mut addresses = Vec::new();
+    if let Ok(socket) = UdpSocket::bind("0.0.0.0:0") {
+        if socket.connect("8.8.8.8:80").is_ok() {
+            if let Ok(address) = socket.local_addr() {
+                addresses.push(address.ip());
+            }
+        }
+    }
+    addresses
+}
+
+// Never trust a peer-supplied name to pick a directory
+fn sanitize_file_name(name: &str) -> String {
+    Path::new(name)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "download".to_string())
+}
+
+// One read or write on the connection, cut short by a stalled peer or a cancel
+async fn guarded<T>(
+    active: &ActiveTransfer,
+    io: impl std::future::Future<Output = std::io::Result<T>>,
+) -> Result<T, String> {
+    tokio::select! {
+        result = tokio::time::timeout(STALL_TIMEOUT, io) => match result {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(_) => Err("The other side stopped responding".to_string()),
+        },
+        _ = active.cancelled.notified() => Err("Cancelled".to_string()),
+    }
+}
+
+async fn copy_with_progress<R, W>(
+    app: &AppHandle,
+    id: &str,
+    reader: &mut R,
+    writer: &mut W,
+    range: Range<u64>,
+    active: &ActiveTransfer,
+    global_limit: &RateLimiter,
+) -> Result<u64, String>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let (mut transferred, total) = (range.start, range.end);
+    let mut last_progress = Instant::now();
+
+    while transferred < total {
+        if active.cancel.load(Ordering::Relaxed) {
+            return Err("Cancelled".to_string());
+        }
+
+        let want = CHUNK_SIZE.min((total - transferred) as usize);
+        let read = guarded(active, reader.read(&mut buffer[..want])).await?;
+        if read == 0 {
+            return Err("Connection closed before the transfer finished".to_string());
+        }
+        guarded(active, writer.write_all(&buffer[..read])).await?;
+        transferred += read as u64;
+
+        // Both the global and this transfer's own cap apply
+        global_limit.consume(read).await;
+        active.limit.consume(read).await;
+
+        if last_progress.elapsed() >= PROGRESS_INTERVAL || transferred == total {
+            last_progress = Instant::now();
+            let _ = app.emit(
+                "file-transfer-progress",
+                TransferProgress {
+                    transfer_id: id,
+                    transferred,
+                    total,
+                },
+            );
+        }
+    }
+
+    guarded(active, writer.flush()).await?;
+    Ok(transferred)
+}
+
+// Waits for the peer that knows the token, then streams the file to it from
+// the offset it asks for. Connections with the wrong token are dropped.
+async fn serve_file(
+    app: &AppHandle,
+    id: &str,
+    listener: TcpListener,
+    path: &Path,
+    token: &str,
+    active: &ActiveTransfer,
+) -> Result<u64, String> {
+    let deadline = Instant::now() + OFFER_TIMEOUT;
+
+    let (mut stream, offset) = loop {
+        if active.cancel.load(Ordering::Relaxed) {
+            return Err("Cancelled".to_string());
+        }
+        if Instant::now() >= deadline {
+            return Err("The offer expired before it was accepted".to_string());
+        }
+
+        let Ok(accepted) = tokio::time::timeout(ACCEPT_POLL, listener.accept()).await else {
+            continue;
+        };
+        let (mut stream, _) = accepted.map_err(|e| e.to_string())?;
+
+        let mut line = String::new();
+        let mut reader = BufReader::new(&mut stream);
+        let read = tokio::time::timeout(CONNECT_TIMEOUT, reader.read_line(&mut line)).await;
+        if !matches!(read, Ok(Ok(_))) {
+            continue;
+        }
+        // "<token> <offset>"; a bare token starts from the beginning
+        let line = line.trim_end();
+        let (peer_token, offset) = line.split_once(' ').unwrap_or((line, "0"));
+        if let (true, Ok(offset)) = (peer_token == token, offset.parse::<u64>()) {
+            break (stream, offset);
+        }
+    };
+
+    update_record(app, id, |record| record.status = TransferStatus::InProgress);
+
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let size = file.metadata().await.map_err(|e| e.to_string())?.len();
+    if offset > size {
+        return Err("The peer asked to resume past the end of the file".to_string());
+    }
+    file.seek(std::io::SeekFrom::Start(offset))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    guarded(active, stream.write_all(&size.to_be_bytes())).await?;
+    let limit = &app.state::<TransferState>().upload_limit;
+    copy_with_progress(app, id, &mut file, &mut stream, offset..size, active, limit).await
+}
+
+async fn connect_to_sender(offer: &TransferOffer) -> Result<TcpStream, String> {
+    let mut last_error = "The sender didn't advertise any address".to_string();
+
+    for address in &offer.addresses {
+        let target = SocketAddr::new(*address, offer.port);
+        match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(target)).await {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(e)) => last_error = e.to_string(),
+            Err(_) => last_error = format!("Timed out connecting to {}", target),
+        }
+    }
+
+    Err(last_error)
+}
+
+// Downloads into `<dest>.part` and only renames once every byte arrived.
+// When resuming, this transfer's partial file is continued; otherwise any
+// file already there, e.g. left by another transfer, is started over.
+async fn receive_file(
+    app: &AppHandle,
+    id: &str,
+    offer: &TransferOffer,
+    dest: &Path,
+    resume: bool,
+    active: &ActiveTransfer,
+) -> Result<u64, String> {
+    let partial = partial_path(dest);
+    let offset = match tokio::fs::metadata(&partial).await {
+        Ok(metadata) if resume && metadata.len() <= offer.size => metadata.len(),
+        _ => 0,
+    };
+
+    let mut stream = connect_to_sender(offer).await?;
+    let request = format!("{} {}\n", offer.token, offset);
+    guarded(active, stream.write_all(request.as_bytes())).await?;
+
+    let mut size = [0u8; 8];
+    guarded(active, stream.read_exact(&mut size)).await?;
+    let size = u64::from_be_bytes(size);
+    // The size was checked against policy when the offer came in
+    if size != offer.size {
+        return Err("The incoming file doesn't match what was offered".to_string());
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(offset > 0)
+        .write(true)
+        .truncate(offset == 0)
+        .open(&partial)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let limit = &app.state::<TransferState>().download_limit;
+    let result =
+        copy_with_progress(app, id, &mut stream, &mut file, offset..size, active, limit).await;
+    drop(file);
+
+    // Dropped connections keep the partial file so the transfer can resume
+    let transferred = match result {
+        Err(error) if !active.cancel.load(Ordering::Relaxed) => return Err(error),
+        result => result,
+    };
+
+    let result = match transferred {
+        Ok(transferred) => match verify_checksum(&partial, &offer.sha256).await {
+            Ok(()) => scan_received(app, id, &partial, dest)
+                .await
+                .map(|_| transferred),
+            Err(error) => Err(error),
+        },
+        Err(error) => Err(error),
+    };
+
+    match result {
+        Ok(transferred) => {
+            tokio::fs::rename(&partial, dest)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(transferred)
+        }
+        Err(error) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            Err(error)
+        }
+    }
+}
+
+async fn verify_checksum(partial: &Path, expected: &str) -> Result<(), String> {
+    let path = partial.to_path_buf();
+    let actual = tauri::async_runtime::spawn_blocking(move || checksum::sha256_file(&path))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err("Checksum mismatch: the file was corrupted or altered in transit".to_string())
+    }
+}
+
+// Runs the optional virus scan before the file gets its real name
+async fn scan_received(
+    app: &AppHandle,
+    id: &str,
+    partial: &Path,
+    dest: &Path,
+) -> Result<(), String> {
+    let scan_app = app.clone();
+    let scan_path = partial.to_path_buf();
+    let verdict = tauri::async_runtime::spawn_blocking(move || {
+        file_checks::scan_if_enabled(&scan_app, &scan_path)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let Some(verdict) = verdict else {
+        return Ok(());
+    };
+    let file_name = dest
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    file_checks::emit_verdict(app, id, &file_name, &verdict);
+
+    if verdict.allowed {
+        Ok(())
+    } else {
+        Err(verdict
+            .reason
+            .unwrap_or_else(|| "Blocked by the virus scanner".to_string()))
+    }
+}
+
+// Starts listening for the peer and returns the offer to send them over chat
+#[tauri::command]
+pub async fn start_file_send(
+    app_handle: AppHandle,
+    chat_id: String,
+    path: String,
+) -> Result<TransferOffer, String> {
+    let path = PathBuf::from(path);
+    let metadata = tokio::fs::metadata(&path)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !metadata.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+
+    // Sent along with the offer so the receiver can verify what arrived
+    let sha256 = hash_source(&path).await?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let file_name = sanitize_file_name(&path.to_string_lossy());
+    let now = chrono::Utc::now().timestamp_millis();
+
+    insert_record(
+        &app_handle,
+        TransferRecord {
+            id: id.clone(),
+            chat_id,
+            peer_id: None,
+            direction: TransferDirection::Send,
+            file_name: file_name.clone(),
+            path: Some(path.clone()),
+            size: metadata.len(),
+            sha256: sha256.clone(),
+            transferred: 0,
+            status: TransferStatus::Pending,
+            error: None,
+            created_at: now,
+            updated_at: now,
+            partial_path: None,
+            offer: None,
+        },
+    )?;
+
+    let offer = TransferOffer {
+        transfer_id: id.clone(),
+        file_name,
+        size: metadata.len(),
+        addresses: Vec::new(),
+        port: 0,
+        token: String::new(),
+        sha256,
+    };
+    start_serving(&app_handle, offer, path)
+        .await
+        .inspect_err(|error| {
+            update_record(&app_handle, &id, |record| {
+                record.status = TransferStatus::Failed;
+                record.error = Some(error.clone());
+            });
+        })
+}
+
+async fn hash_source(path: &Path) -> Result<String, String> {
+    let path = path.to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || checksum::sha256_file(&path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// Opens a fresh listener for `offer.transfer_id` and fills in how to reach it
+async fn start_serving(
+    app_handle: &AppHandle,
+    mut offer: TransferOffer,
+    path: PathBuf,
+) -> Result<TransferOffer, String> {
+    offer.addresses = local_addresses();
+    if offer.addresses.is_empty() {
+        return Err("No network connection available for a direct transfer".to_string());
+    }
+
+    let listener = TcpListener::bind("0.0.0.0:0")
+        .await
+        .map_err(|e| e.to_string())?;
+    offer.port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    offer.token = uuid::Uuid::new_v4().simple().to_string();
+
+    let active = register_active(app_handle, &offer.transfer_id);
+    let app = app_handle.clone();
+    let transfer_id = offer.transfer_id.clone();
+    let token = offer.token.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = serve_file(&app, &transfer_id, listener, &path, &token, &active).await;
+        finish(&app, &transfer_id, result, &active);
+    });
+
+    Ok(offer)
+}
+
+// Records an offer a contact sent us so it can be accepted or declined.
+// `sender_id` is needed in group chats; direct chats imply the sender.
+#[tauri::command]
+pub async fn receive_file_offer(
+    app_handle: AppHandle,
+    chat_id: String,
+    sender_id: Option<String>,
+    offer: TransferOffer,
+) -> Result<TransferRecord, String> {
+    // Without a hash there'd be no telling whether the file was altered
+    if offer.sha256.len() != 64 || !offer.sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("The offer is missing the file's checksum".to_string());
+    }
+    if let Some(existing) = get_record(&app_handle, &offer.transfer_id) {
+        // The sender re-offered a transfer we'd already accepted: pick it up
+        // again, provided it's still the same file
+        if existing.direction == TransferDirection::Receive
+            && existing.status == TransferStatus::Interrupted
+            && existing.sha256 == offer.sha256
+        {
+            update_record(&app_handle, &existing.id, |record| {
+                record.offer = Some(offer)
+            });
+            return resume_receive(&app_handle, existing.id);
+        }
+        return Ok(existing);
+    }
+
+    // Offers that break the type/size policy are refused before any bytes move
+    let file_name = sanitize_file_name(&offer.file_name);
+    let verdict = file_checks::check_incoming(&app_handle, &file_name, offer.size);
+    file_checks::emit_verdict(&app_handle, &offer.transfer_id, &file_name, &verdict);
+
+    let peer_id =
+        sender_id.or_else(|| trusted_contacts::contact_id_for_chat(&chat_id).map(str::to_string));
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let record = TransferRecord {
+        id: offer.transfer_id.clone(),
+        chat_id,
+        peer_id: peer_id.clone(),
+        direction: TransferDirection::Receive,
+        file_name,
+        path: None,
+        size: offer.size,
+        sha256: offer.sha256.clone(),
+        transferred: 0,
+        status: if verdict.allowed {
+            TransferStatus::Pending
+        } else {
+            TransferStatus::Failed
+        },
+        error: verdict.reason.filter(|_| !verdict.allowed),
+        created_at: now,
+        updated_at: now,
+        partial_path: None,
+        offer: Some(offer),
+    };
+    insert_record(&app_handle, record.clone())?;
+
+    // Trusted contacts skip the prompt; their files go to a separate folder
+    let trusted = peer_id
+        .as_deref()
+        .is_some_and(|peer_id| trusted_contacts::should_auto_accept(&app_handle, peer_id));
+    if record.status == TransferStatus::Pending && trusted {
+        let directory = trusted_contacts::auto_accept_directory(&app_handle)?;
+        let dest = downloads::unique_path(&directory, &record.file_name, &[]);
+        return accept(&app_handle, record.id, dest);
+    }
+
+    Ok(record)
+}
+
+fn accept(
+    app_handle: &AppHandle,
+    transfer_id: String,
+    dest: PathBuf,
+) -> Result<TransferRecord, String> {
+    let record = get_record(app_handle, &transfer_id)
+        .ok_or_else(|| format!("Unknown transfer: {}", transfer_id))?;
+    let offer = match (&record.offer, record.status) {
+        (Some(offer), TransferStatus::Pending) => offer.clone(),
+        _ => return Err("This transfer can no longer be accepted".to_string()),
+    };
+
+    start_receiving(app_handle, transfer_id, offer, dest, false)
+}
+
+fn start_receiving(
+    app_handle: &AppHandle,
+    transfer_id: String,
+    offer: TransferOffer,
+    dest: PathBuf,
+    resume: bool,
+) -> Result<TransferRecord, String> {
+    let active = register_active(app_handle, &transfer_id);
+    let record = update_record(app_handle, &transfer_id, |record| {
+        record.path = Some(dest.clone());
+        record.partial_path = Some(partial_path(&dest));
+        record.status = TransferStatus::InProgress;
+        record.error = None;
+    })
+    .ok_or_else(|| format!("Unknown transfer: {}", transfer_id))?;
+
+    let app = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = receive_file(&app, &transfer_id, &offer, &dest, resume, &active).await;
+        finish(&app, &transfer_id, result, &active);
+    });
+
+    Ok(record)
+}
+
+fn resume_receive(app_handle: &AppHandle, transfer_id: String) -> Result<TransferRecord, String> {
+    let record = get_record(app_handle, &transfer_id)
+        .ok_or_else(|| format!("Unknown transfer: {}", transfer_id))?;
+    match (record.offer, record.path, record.status) {
+        (Some(offer), Some(dest), TransferStatus::Interrupted) => {
+            // Only bytes this transfer wrote itself are continued
+            let resume = record.partial_path.as_deref() == Some(partial_path(&dest).as_path());
+            start_receiving(app_handle, transfer_id, offer, dest, resume)
+        }
+        _ => Err("This transfer can't be resumed".to_string()),
+    }
+}
+
+// `dest` may be a directory, in which case the sender's file name is used,
+// numbered if a file by that name is already there
+#[tauri::command]
+pub async fn accept_file_receive(
+    app_handle: AppHandle,
+    transfer_id: String,
+    dest: String,
+) -> Result<TransferRecord, String> {
+    let record = get_record(&app_handle, &transfer_id)
+        .ok_or_else(|| format!("Unknown transfer: {}", transfer_id))?;
+
+    let mut dest = PathBuf::from(dest);
+    if dest.is_dir() {
+        dest = downloads::unique_path(&dest, &record.file_name, &[]);
+    }
+
+    accept(&app_handle, transfer_id, dest)
+}
+
+// Continues an interrupted transfer from where it stopped. For sends this
+// listens again and returns a new offer to pass to the peer over chat; the
+// receiver resumes automatically when that offer arrives. Receives reconnect
+// with the last offer, which only works while the sender is still listening.
+#[tauri::command]
+pub async fn resume_file_transfer(
+    app_handle: AppHandle,
+    transfer_id: String,
+) -> Result<Option<TransferOffer>, String> {
+    let record = get_record(&app_handle, &transfer_id)
+        .ok_or_else(|| format!("Unknown transfer: {}", transfer_id))?;
+    if get_active(&app_handle, &transfer_id).is_some() {
+        return Err("This transfer is already running".to_string());
+    }
+
+    if record.direction == TransferDirection::Receive {
+        resume_receive(&app_handle, transfer_id)?;
+        return Ok(None);
+    }
+
+    let path = match (record.path, record.status) {
+        (Some(path), TransferStatus::Interrupted | TransferStatus::Failed) => path,
+        _ => return Err("This transfer can't be resumed".to_string()),
+    };
+    // The receiver checks the hash, so a changed file can't be continued
+    if hash_source(&path).await.ok().as_ref() != Some(&record.sha256) {
+        return Err("The file changed since it was first sent".to_string());
+    }
+
+    update_record(&app_handle, &transfer_id, |record| {
+        record.status = TransferStatus::Pending;
+        record.error = None;
+    });
+    let offer = TransferOffer {
+        transfer_id: transfer_id.clone(),
+        file_name: record.file_name,
+        size: record.size,
+        addresses: Vec::new(),
+        port: 0,
+        token: String::new(),
+        sha256: record.sha256,
+    };
+    start_serving(&app_handle, offer, path)
+        .await
+        .inspect_err(|error| {
+            update_record(&app_handle, &transfer_id, |record| {
+                record.status = TransferStatus::Interrupted;
+                record.error = Some(error.clone());
+            });
+        })
+        .map(Some)
+}
+
+// Stops a running transfer, or declines an offer that was never accepted
+#[tauri::command]
+pub async fn cancel_file_transfer(
+    app_handle: AppHandle,
+    transfer_id: String,
+) -> Result<(), String> {
+    match get_active(&app_handle, &transfer_id) {
+        Some(active) => {
+            active.cancel.store(true, Ordering::Relaxed);
+            // Stored as a permit if nothing is waiting right now
+            active.cancelled.notify_one();
+        }
+        None => {
+            let record = update_record(&app_handle, &transfer_id, |record| {
+                if !record.status.is_finished() {
+                    record.status = TransferStatus::Cancelled;
+                }
+            })
+            .ok_or_else(|| format!("Unknown transfer: {}", transfer_id))?;
+            // Interrupted receives leave their partial file behind
+            if let Some(partial) = record.partial_path {
+                let _ = tokio::fs::remove_file(partial).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_transfer_speed_limits(
+    app_handle: AppHandle,
+) -> Result<TransferSpeedLimits, String> {
+    let state = app_handle.state::<TransferState>();
+    Ok(TransferSpeedLimits {
+        upload_kbps: state.upload_limit.kbps(),
+        download_kbps: state.download_limit.kbps(),
+    })
+}
+
+// Global caps in KiB/s across all transfers; `None` means unlimited
+#[tauri::command]
+pub async fn set_transfer_speed_limits(
+    app_handle: AppHandle,
+    upload_kbps: Option<u64>,
+    download_kbps: Option<u64>,
+) -> Result<TransferSpeedLimits, String> {
+    let mut settings = crate::settings::load(&app_handle)?;
+    settings.transfer_upload_limit_kbps = upload_kbps;
+    settings.transfer_download_limit_kbps = download_kbps;
+    crate::settings::save(&app_handle, &settings)?;
+
+    let state = app_handle.state::<TransferState>();
+    state.upload_limit.set_kbps(upload_kbps);
+    state.download_limit.set_kbps(download_kbps);
+
+    get_transfer_speed_limits(app_handle.clone()).await
+}
+
+// Caps a single running transfer in KiB/s, on top of the global limit
+#[tauri::command]
+pub async fn set_transfer_speed_limit(
+    app_handle: AppHandle,
+    transfer_id: String,
+    kbps: Option<u64>,
+) -> Result<(), String> {
+    let active = get_active(&app_handle, &transfer_id)
+        .ok_or_else(|| format!("Transfer is not running: {}", transfer_id))?;
+    active.limit.set_kbps(kbps);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_file_transfers(
+    app_handle: AppHandle,
+    chat_id: Option<String>,
+) -> Result<Vec<TransferRecord>, String> {
+    let records = app_handle
+        .state::<TransferState>()
+        .records
+        .lock()
+        .unwrap()
+        .clone();
+
+    Ok(records
+        .into_iter()
+        .filter(|record| {
+            chat_id
+                .as_ref()
+                .is_none_or(|chat_id| &record.chat_id == chat_id)
+        })
+        .collect())
+}
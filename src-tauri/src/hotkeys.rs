@@ -0,0 +1,228 @@
+// Global hotkeys with persisted, user-configurable bindings
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::store_recovery;
+
+const HOTKEYS_STORE: &str = "hotkeys.json";
+const VALID_STATUSES: [&str; 5] = ["online", "away", "busy", "invisible", "offline"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HotkeyAction {
+    OpenMainWindow,
+    NewMessage,
+    ToggleMute,
+    // Microphone mute while in a call
+    ToggleCallMute,
+    SetStatus { status: String },
+}
+
+impl HotkeyAction {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            HotkeyAction::SetStatus { status } if !VALID_STATUSES.contains(&status.as_str()) => {
+                Err(format!("Unknown status: {}", status))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotkeyBinding {
+    action: HotkeyAction,
+    accelerator: String,
+}
+
+fn parse_accelerator(accelerator: &str) -> Result<Shortcut, String> {
+    Shortcut::from_str(accelerator)
+        .map_err(|e| format!("Invalid shortcut '{}': {}", accelerator, e))
+}
+
+pub fn load_bindings(app_handle: &AppHandle) -> Result<Vec<HotkeyBinding>, String> {
+    let bindings = store_recovery::read(app_handle, HOTKEYS_STORE, "bindings")?;
+    Ok(bindings.unwrap_or_default())
+}
+
+fn save_bindings(app_handle: &AppHandle, bindings: &[HotkeyBinding]) -> Result<(), String> {
+    let store = store_recovery::open(app_handle, HOTKEYS_STORE)?;
+
+    store.set("bindings", serde_json::to_value(bindings).unwrap());
+    store_recovery::save(app_handle, HOTKEYS_STORE, &store)?;
+
+    Ok(())
+}
+
+fn handle_hotkey(app_handle: &AppHandle, action: &HotkeyAction) {
+    match action {
+        HotkeyAction::OpenMainWindow | HotkeyAction::NewMessage => {
+            if let Some(window) = app_handle.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+        }
+        HotkeyAction::ToggleCallMute => crate::call_audio::toggle_call_mute(app_handle),
+        HotkeyAction::ToggleMute | HotkeyAction::SetStatus { .. } => {}
+    }
+
+    // The frontend owns status, mute and compose state, so it acts on the event
+    let _ = app_handle.emit("hotkey-triggered", action);
+}
+
+fn register_with_os(
+    app_handle: &AppHandle,
+    shortcut: Shortcut,
+    action: HotkeyAction,
+) -> Result<(), String> {
+    app_handle
+        .global_shortcut()
+        .on_shortcut(shortcut, move |app, _shortcut, event| {
+            if event.state == ShortcutState::Pressed {
+                handle_hotkey(app, &action);
+            }
+        })
+        .map_err(|e| format!("Shortcut is already in use by another application: {}", e))
+}
+
+// Media keys work on their own too, e.g. "MediaPlayPause" for a headset's
+// button or "AudioVolumeMute"
+#[tauri::command]
+pub async fn register_hotkey(
+    app_handle: AppHandle,
+    action: HotkeyAction,
+    accelerator: String,
+) -> Result<HotkeyBinding, String> {
+    action.validate()?;
+    let shortcut = parse_accelerator(&accelerator)?;
+    let mut bindings = load_bindings(&app_handle)?;
+
+    // Conflict detection: one shortcut can only drive one action
+    if let Some(existing) = bindings.iter().find(|binding| {
+        binding.action != action && parse_accelerator(&binding.accelerator).ok() == Some(shortcut)
+    }) {
+        return Err(format!(
+            "'{}' is already assigned to {:?}",
+            accelerator, existing.action
+        ));
+    }
+
+    // Rebinding an action replaces its previous shortcut, which is put back
+    // if the new one can't be registered
+    let previous = bindings
+        .iter()
+        .position(|binding| binding.action == action)
+        .and_then(|index| parse_accelerator(&bindings.remove(index).accelerator).ok());
+    if let Some(previous) = previous {
+        let _ = app_handle.global_shortcut().unregister(previous);
+    }
+
+    if let Err(error) = register_with_os(&app_handle, shortcut, action.clone()) {
+        if let Some(previous) = previous {
+            let _ = register_with_os(&app_handle, previous, action);
+        }
+        return Err(error);
+    }
+
+    let binding = HotkeyBinding {
+        action,
+        accelerator: shortcut.into_string(),
+    };
+    bindings.push(binding.clone());
+    save_bindings(&app_handle, &bindings)?;
+
+    Ok(binding)
+}
+
+#[tauri::command]
+pub async fn unregister_hotkey(app_handle: AppHandle, action: HotkeyAction) -> Result<(), String> {
+    let mut bindings = load_bindings(&app_handle)?;
+
+    if let Some(index) = bindings.iter().position(|binding| binding.action == action) {
+        let binding = bindings.remove(index);
+        if let Ok(shortcut) = parse_accelerator(&binding.accelerator) {
+            let _ = app_handle.global_shortcut().unregister(shortcut);
+        }
+        save_bindings(&app_handle, &bindings)?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_hotkeys(app_handle: AppHandle) -> Result<Vec<HotkeyBinding>, String> {
+    load_bindings(&app_handle)
+}
+
+// Settings import. Merging keeps current bindings for actions the import
+// doesn't mention, unless their shortcut is now taken.
+pub fn import_bindings(
+    app_handle: &AppHandle,
+    imported: Vec<HotkeyBinding>,
+    merge: bool,
+) -> Result<(), String> {
+    let mut shortcuts = Vec::new();
+    for (index, binding) in imported.iter().enumerate() {
+        binding.action.validate()?;
+        if imported[..index]
+            .iter()
+            .any(|earlier| earlier.action == binding.action)
+        {
+            return Err(format!("{:?} has more than one shortcut", binding.action));
+        }
+        let shortcut = parse_accelerator(&binding.accelerator)?;
+        if shortcuts.contains(&shortcut) {
+            return Err(format!(
+                "'{}' is assigned more than once",
+                binding.accelerator
+            ));
+        }
+        shortcuts.push(shortcut);
+    }
+
+    let current = load_bindings(app_handle)?;
+    let mut bindings = Vec::new();
+    if merge {
+        bindings.extend(
+            current
+                .iter()
+                .filter(|binding| {
+                    !imported
+                        .iter()
+                        .any(|imported| imported.action == binding.action)
+                        && parse_accelerator(&binding.accelerator)
+                            .is_ok_and(|shortcut| !shortcuts.contains(&shortcut))
+                })
+                .cloned(),
+        );
+    }
+    bindings.extend(imported);
+
+    for binding in &current {
+        if let Ok(shortcut) = parse_accelerator(&binding.accelerator) {
+            let _ = app_handle.global_shortcut().unregister(shortcut);
+        }
+    }
+    save_bindings(app_handle, &bindings)?;
+    restore_hotkeys(app_handle)
+}
+
+// Re-registers persisted bindings at startup. Shortcuts that another
+// application grabbed in the meantime are reported instead of dropped.
+pub fn restore_hotkeys(app_handle: &AppHandle) -> Result<(), String> {
+    for binding in load_bindings(app_handle)? {
+        let result = parse_accelerator(&binding.accelerator)
+            .and_then(|shortcut| register_with_os(app_handle, shortcut, binding.action.clone()));
+
+        if let Err(error) = result {
+            let _ = app_handle.emit(
+                "hotkey-registration-failed",
+                serde_json::json!({ "binding": binding, "error": error }),
+            );
+        }
+    }
+
+    Ok(())
+}
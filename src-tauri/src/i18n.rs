@@ -0,0 +1,140 @@
+// Localization for native (Rust-side) user-visible strings
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use tauri::{AppHandle, Emitter, Manager};
+
+const DEFAULT_LANGUAGE: &str = "en";
+
+// Bundled translation tables, keyed by primary language subtag
+const TRANSLATIONS: [(&str, &str); 8] = [
+    ("en", include_str!("../locales/en.json")),
+    ("de", include_str!("../locales/de.json")),
+    ("es", include_str!("../locales/es.json")),
+    ("fr", include_str!("../locales/fr.json")),
+    ("it", include_str!("../locales/it.json")),
+    ("ja", include_str!("../locales/ja.json")),
+    ("nl", include_str!("../locales/nl.json")),
+    ("pt", include_str!("../locales/pt.json")),
+];
+
+type Table = HashMap<String, String>;
+
+fn tables() -> &'static HashMap<&'static str, Table> {
+    static TABLES: OnceLock<HashMap<&'static str, Table>> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        TRANSLATIONS
+            .iter()
+            .map(|(language, json)| {
+                let table: Table =
+                    serde_json::from_str(json).expect("invalid bundled translation table");
+                (*language, table)
+            })
+            .collect()
+    })
+}
+
+pub struct LanguageState(RwLock<String>);
+
+impl Default for LanguageState {
+    fn default() -> Self {
+        Self(RwLock::new(DEFAULT_LANGUAGE.to_string()))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppLanguage {
+    language: String,
+    preferred: Option<String>,
+    supported: Vec<String>,
+}
+
+// "pt-BR" / "pt_BR.UTF-8" -> "pt", when we have a table for it
+fn normalize(language: &str) -> Option<String> {
+    let primary = language.split(['-', '_', '.']).next()?.to_lowercase();
+    tables().contains_key(primary.as_str()).then_some(primary)
+}
+
+fn resolve(preferred: Option<&str>) -> String {
+    preferred
+        .and_then(normalize)
+        .or_else(|| sys_locale::get_locale().as_deref().and_then(normalize))
+        .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string())
+}
+
+fn current_language(app: &AppHandle) -> String {
+    app.state::<LanguageState>().0.read().unwrap().clone()
+}
+
+// Translate `key` into the active language, falling back to English
+pub fn t(app: &AppHandle, key: &str) -> String {
+    let language = current_language(app);
+    let tables = tables();
+
+    tables
+        .get(language.as_str())
+        .and_then(|table| table.get(key))
+        .or_else(|| {
+            tables
+                .get(DEFAULT_LANGUAGE)
+                .and_then(|table| table.get(key))
+        })
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+// Translate `key` and substitute `{name}`-style placeholders
+pub fn t_with(app: &AppHandle, key: &str, args: &[(&str, &str)]) -> String {
+    args.iter().fold(t(app, key), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+// Picks the saved language (or the OS locale) before any native UI is built
+pub fn init(app: &AppHandle) {
+    let preferred = crate::settings::load(app)
+        .ok()
+        .and_then(|settings| settings.language);
+    *app.state::<LanguageState>().0.write().unwrap() = resolve(preferred.as_deref());
+}
+
+#[tauri::command]
+pub async fn get_app_language(app_handle: AppHandle) -> Result<AppLanguage, String> {
+    let preferred = crate::settings::load(&app_handle)?.language;
+    let mut supported: Vec<String> = tables()
+        .keys()
+        .map(|language| language.to_string())
+        .collect();
+    supported.sort();
+
+    Ok(AppLanguage {
+        language: current_language(&app_handle),
+        preferred,
+        supported,
+    })
+}
+
+// `None` follows the OS locale
+#[tauri::command]
+pub async fn set_app_language(
+    app_handle: AppHandle,
+    language: Option<String>,
+) -> Result<AppLanguage, String> {
+    if let Some(language) = &language {
+        normalize(language).ok_or_else(|| format!("Unsupported language: {}", language))?;
+    }
+
+    let mut settings = crate::settings::load(&app_handle)?;
+    settings.language = language.clone();
+    crate::settings::save(&app_handle, &settings)?;
+
+    *app_handle.state::<LanguageState>().0.write().unwrap() = resolve(language.as_deref());
+
+    // Rebuild the native surfaces that carry text
+    crate::refresh_tray(&app_handle).map_err(|e| e.to_string())?;
+
+    let current = get_app_language(app_handle.clone()).await?;
+    let _ = app_handle.emit("app-language-changed", &current.language);
+
+    Ok(current)
+}
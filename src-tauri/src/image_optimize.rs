@@ -0,0 +1,183 @@
+// Shrinks photos before they're uploaded
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::{DynamicImage, ImageDecoder, ImageEncoder, ImageReader};
+use serde::Serialize;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OptimizedImage {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    size: u64,
+    original_size: u64,
+}
+
+fn is_heic(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| extension == "heic" || extension == "heif")
+}
+
+// The image crate can't decode HEIC, so let the OS convert it to JPEG first
+fn convert_heic(source: &Path, out: &Path) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let status = std::process::Command::new("sips")
+        .args(["-s", "format", "jpeg"])
+        .arg(source)
+        .arg("--out")
+        .arg(out)
+        .output();
+    #[cfg(not(target_os = "macos"))]
+    let status = std::process::Command::new("heif-convert")
+        .arg(source)
+        .arg(out)
+        .output();
+
+    match status {
+        Ok(output) if output.status.success() && out.exists() => Ok(()),
+        Ok(_) => Err("Could not convert this HEIC image".to_string()),
+        Err(_) => Err("HEIC images need heif-convert (libheif) installed".to_string()),
+    }
+}
+
+fn has_transparency(image: &DynamicImage) -> bool {
+    image.color().has_alpha() && image.to_rgba8().pixels().any(|pixel| pixel[3] < u8::MAX)
+}
+
+fn optimize(
+    source: &Path,
+    out_dir: &Path,
+    quality: u8,
+    max_dimension: u32,
+    strip_metadata: bool,
+) -> Result<OptimizedImage, String> {
+    let original_size = std::fs::metadata(source).map_err(|e| e.to_string())?.len();
+
+    let stem = source
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "image".to_string());
+
+    let converted = out_dir.join(format!("{}.heic.jpg", stem));
+    let decode_from = if is_heic(source) {
+        convert_heic(source, &converted)?;
+        converted.as_path()
+    } else {
+        source
+    };
+
+    let mut decoder = ImageReader::open(decode_from)
+        .map_err(|e| e.to_string())?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+    let orientation = decoder.orientation().map_err(|e| e.to_string())?;
+    let exif = decoder.exif_metadata().ok().flatten();
+    // Whether the original could be sent as it is, if re-encoding doesn't help
+    let mut usable_as_is = decode_from == source && !(strip_metadata && exif.is_some());
+    let icc_profile = decoder.icc_profile().ok().flatten();
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    let _ = std::fs::remove_file(&converted);
+
+    // Stripping EXIF also drops the orientation tag, so bake the rotation into
+    // the pixels. When EXIF is kept, viewers keep rotating it themselves.
+    if strip_metadata {
+        image.apply_orientation(orientation);
+    }
+
+    if image.width() > max_dimension || image.height() > max_dimension {
+        usable_as_is = false;
+        image = image.resize(
+            max_dimension,
+            max_dimension,
+            image::imageops::FilterType::Lanczos3,
+        );
+    }
+
+    // Photos become JPEG; only images that really use transparency stay PNG
+    let transparent = has_transparency(&image);
+    let out = out_dir.join(format!(
+        "{}-{}.{}",
+        stem,
+        chrono::Utc::now().timestamp_millis(),
+        if transparent { "png" } else { "jpg" }
+    ));
+    let writer = BufWriter::new(std::fs::File::create(&out).map_err(|e| e.to_string())?);
+
+    let result = if transparent {
+        let mut encoder = PngEncoder::new(writer);
+        if let Some(icc_profile) = icc_profile {
+            let _ = encoder.set_icc_profile(icc_profile);
+        }
+        image.write_with_encoder(encoder)
+    } else {
+        let mut encoder = JpegEncoder::new_with_quality(writer, quality);
+        if let Some(icc_profile) = icc_profile {
+            let _ = encoder.set_icc_profile(icc_profile);
+        }
+        if let (false, Some(exif)) = (strip_metadata, exif) {
+            let _ = encoder.set_exif_metadata(exif);
+        }
+        DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)
+    };
+    result.map_err(|e| e.to_string())?;
+
+    let size = std::fs::metadata(&out).map_err(|e| e.to_string())?.len();
+    if usable_as_is && size >= original_size {
+        let _ = std::fs::remove_file(&out);
+        return Ok(OptimizedImage {
+            path: source.to_path_buf(),
+            width: image.width(),
+            height: image.height(),
+            size: original_size,
+            original_size,
+        });
+    }
+
+    Ok(OptimizedImage {
+        path: out,
+        width: image.width(),
+        height: image.height(),
+        size,
+        original_size,
+    })
+}
+
+// Re-encodes `path` into the cache directory and returns the new copy, or
+// `path` itself when that's smaller and needs no resizing or stripping.
+// `strip_metadata` (default on) removes EXIF such as GPS location.
+#[tauri::command]
+pub async fn optimize_image(
+    app_handle: AppHandle,
+    path: String,
+    quality: u8,
+    max_dimension: u32,
+    strip_metadata: Option<bool>,
+) -> Result<OptimizedImage, String> {
+    let source = PathBuf::from(path);
+    if !source.is_file() {
+        return Err(format!("Not a file: {}", source.display()));
+    }
+
+    let out_dir = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join("optimized");
+    std::fs::create_dir_all(&out_dir).map_err(|e| e.to_string())?;
+
+    let quality = quality.clamp(1, 100);
+    let max_dimension = max_dimension.max(1);
+    let strip_metadata = strip_metadata.unwrap_or(true);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        optimize(&source, &out_dir, quality, max_dimension, strip_metadata)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -1,17 +1,77 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod accounts;
+mod audio_devices;
+mod autostart;
+mod av_permissions;
+mod avatars;
+mod backup;
+mod call_audio;
+mod cameras;
+mod capture_sources;
+mod checksum;
+mod clipboard;
+mod clipboard_watch;
+mod clock;
+mod close_behavior;
+mod conversation_export;
+mod downloads;
+mod drafts;
+mod drag_drop;
+mod drag_out;
+mod echo_test;
+mod emoticon_packs;
+mod emotes;
+mod file_checks;
+mod file_transfer;
+mod hotkeys;
+mod i18n;
+mod image_optimize;
+mod media_capabilities;
+mod message_cache;
+mod message_log;
+mod mic_level;
+mod migrations;
+mod net;
+mod nudge;
+mod power;
+mod preview;
+mod proxy;
+mod rate_limit;
+mod screenshot;
+mod secrets;
+mod session;
+mod settings;
+mod settings_export;
+mod shared_files;
+mod sound;
+mod sound_schemes;
+mod storage;
+mod storage_usage;
+mod store_recovery;
+mod theme;
+mod thumbnails;
+mod transcription;
+mod trusted_contacts;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod updates;
+mod uploads;
+#[cfg(target_os = "windows")]
+mod win_events;
+mod winks;
+
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
 use tauri::{
-    menu::{Menu, MenuItem, PredefinedMenuItem},
+    menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem},
     tray::{TrayIconBuilder, TrayIconEvent},
     webview::WebviewWindowBuilder,
     AppHandle, Emitter, Manager, WebviewUrl, WebviewWindow,
 };
 use tauri_plugin_notification::{NotificationExt, PermissionState};
 use tauri_plugin_opener::OpenerExt;
-use tauri_plugin_store::StoreBuilder;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct WindowConfig {
@@ -66,6 +126,10 @@ impl Default for NotificationSettings {
     }
 }
 
+// Last unread count pushed by the frontend, so the tray tooltip can be rebuilt
+#[derive(Default)]
+struct UnreadCount(AtomicU32);
+
 impl Default for AppState {
     fn default() -> Self {
         Self {
@@ -83,6 +147,39 @@ impl Default for AppState {
     }
 }
 
+// Whether `now` falls inside the configured quiet hours. Ranges that wrap past
+// midnight (e.g. 22:00 - 08:00) are treated as overnight.
+fn is_within_quiet_hours(settings: &NotificationSettings, now: chrono::NaiveTime) -> bool {
+    if !settings.quiet_hours_enabled {
+        return false;
+    }
+
+    let (Some(start), Some(end)) = (&settings.quiet_hours_start, &settings.quiet_hours_end) else {
+        return false;
+    };
+    let (Ok(start), Ok(end)) = (
+        chrono::NaiveTime::parse_from_str(start, "%H:%M"),
+        chrono::NaiveTime::parse_from_str(end, "%H:%M"),
+    ) else {
+        return false;
+    };
+
+    if start <= end {
+        now >= start && now < end
+    } else {
+        now >= start || now < end
+    }
+}
+
+// Normalize label to safe chars for Tauri window labels
+fn chat_window_label(chat_id: &str) -> String {
+    let normalized_id: String = chat_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '-' })
+        .collect();
+    format!("chat-{}", normalized_id)
+}
+
 // Tauri commands for window management
 #[tauri::command]
 async fn create_chat_window(
@@ -90,12 +187,7 @@ async fn create_chat_window(
     chat_id: String,
     contact_name: String,
 ) -> Result<(), String> {
-    // Normalize label to safe chars for Tauri window labels
-    let normalized_id: String = chat_id
-        .chars()
-        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '-' })
-        .collect();
-    let window_label = format!("chat-{}", normalized_id);
+    let window_label = chat_window_label(&chat_id);
 
     // Check if window already exists
     if app_handle.get_webview_window(&window_label).is_some() {
@@ -107,7 +199,7 @@ async fn create_chat_window(
     }
 
     // Create new chat window
-    let window_title = format!("Chat with {}", contact_name);
+    let window_title = i18n::t_with(&app_handle, "window.chat_title", &[("name", &contact_name)]);
     // Open chat-only window mode; renderer reads ?chat=... and window=chat
     let window_url = format!("/?chat={}&window=chat", chat_id);
 
@@ -129,12 +221,7 @@ async fn create_chat_window(
 
 #[tauri::command]
 async fn close_chat_window(app_handle: AppHandle, chat_id: String) -> Result<(), String> {
-    // Use same normalization as create_chat_window to find the label
-    let normalized_id: String = chat_id
-        .chars()
-        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '-' })
-        .collect();
-    let window_label = format!("chat-{}", normalized_id);
+    let window_label = chat_window_label(&chat_id);
 
     if let Some(window) = app_handle.get_webview_window(&window_label) {
         window.close().map_err(|e| e.to_string())?;
@@ -161,13 +248,12 @@ async fn restore_from_tray(app_handle: AppHandle) -> Result<(), String> {
 #[tauri::command]
 async fn update_unread_count(app_handle: AppHandle, count: u32) -> Result<(), String> {
     // Update system tray tooltip with unread count
+    app_handle
+        .state::<UnreadCount>()
+        .0
+        .store(count, Ordering::Relaxed);
     if let Some(tray) = app_handle.tray_by_id("main-tray") {
-        let tooltip = if count > 0 {
-            format!("MSN Messenger - {} unread messages", count)
-        } else {
-            "MSN Messenger".to_string()
-        };
-        tray.set_tooltip(Some(&tooltip))
+        tray.set_tooltip(Some(&tray_tooltip(&app_handle)))
             .map_err(|e| e.to_string())?;
     }
 
@@ -180,12 +266,11 @@ async fn save_window_state(
     window_label: String,
     config: WindowConfig,
 ) -> Result<(), String> {
-    let store = StoreBuilder::new(&app_handle, std::path::PathBuf::from("window-state.json"))
-        .build()
-        .map_err(|e| e.to_string())?;
+    let path = accounts::store_path(&app_handle, "window-state.json");
+    let store = store_recovery::open(&app_handle, &path)?;
 
     store.set(window_label, serde_json::to_value(config).unwrap());
-    store.save().map_err(|e| e.to_string())?;
+    store_recovery::save(&app_handle, path, &store)?;
 
     Ok(())
 }
@@ -195,17 +280,11 @@ async fn load_window_state(
     app_handle: AppHandle,
     window_label: String,
 ) -> Result<Option<WindowConfig>, String> {
-    let store = StoreBuilder::new(&app_handle, std::path::PathBuf::from("window-state.json"))
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    if let Some(value) = store.get(&window_label) {
-        let config: WindowConfig =
-            serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
-        Ok(Some(config))
-    } else {
-        Ok(None)
-    }
+    store_recovery::read(
+        &app_handle,
+        accounts::store_path(&app_handle, "window-state.json"),
+        &window_label,
+    )
 }
 
 // Notification management commands
@@ -256,24 +335,21 @@ async fn show_notification(
         }
     }
 
-    // Check quiet hours
-    if settings.quiet_hours_enabled {
-        if let (Some(start), Some(end)) = (&settings.quiet_hours_start, &settings.quiet_hours_end) {
-            let now = chrono::Local::now();
-            let current_time = now.format("%H:%M").to_string();
+    // Hold notifications while another user has the machine
+    if !session::is_active(&app_handle) {
+        return Ok(());
+    }
 
-            // Simple time comparison (doesn't handle overnight ranges perfectly)
-            if current_time >= *start && current_time <= *end {
-                return Ok(());
-            }
-        }
+    // Check quiet hours
+    if is_within_quiet_hours(&settings, chrono::Local::now().time()) {
+        return Ok(());
     }
 
     // Prepare notification body
     let body = if settings.show_preview {
         notification_data.body.clone()
     } else {
-        "New message".to_string()
+        i18n::t(&app_handle, "notification.new_message")
     };
 
     // Create and show notification
@@ -300,15 +376,14 @@ async fn show_notification(
     }
 
     // Store notification data for click handling
-    let store = StoreBuilder::new(&app_handle, std::path::PathBuf::from("notifications.json"))
-        .build()
-        .map_err(|e| e.to_string())?;
+    let path = accounts::store_path(&app_handle, "notifications.json");
+    let store = store_recovery::open(&app_handle, &path)?;
 
     store.set(
         &notification_data.id,
         serde_json::to_value(&action_data).unwrap(),
     );
-    store.save().map_err(|e| e.to_string())?;
+    store_recovery::save(&app_handle, path, &store)?;
 
     notification.show().map_err(|e| e.to_string())?;
 
@@ -321,24 +396,22 @@ async fn handle_notification_click(
     notification_id: String,
 ) -> Result<(), String> {
     // Load notification data
-    let store = StoreBuilder::new(&app_handle, std::path::PathBuf::from("notifications.json"))
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    if let Some(data_value) = store.get(&notification_id) {
-        let data: HashMap<String, String> =
-            serde_json::from_value(data_value.clone()).map_err(|e| e.to_string())?;
+    let path = accounts::store_path(&app_handle, "notifications.json");
+    let store = store_recovery::open(&app_handle, &path)?;
 
+    if let Some(data) =
+        store_recovery::read::<HashMap<String, String>>(&app_handle, &path, &notification_id)?
+    {
         match data.get("type").map(|s| s.as_str()) {
             Some("message") => {
                 if let Some(chat_id) = data.get("chat_id") {
                     // Open chat window
-                    create_chat_window(app_handle.clone(), chat_id.clone(), "Contact".to_string())
-                        .await?;
+                    let contact_name = i18n::t(&app_handle, "contact.fallback_name");
+                    create_chat_window(app_handle.clone(), chat_id.clone(), contact_name).await?;
                 }
 
                 // Show main window
-                restore_from_tray(app_handle).await?;
+                restore_from_tray(app_handle.clone()).await?;
             }
             Some("contact_request") => {
                 // Show main window and navigate to contact requests
@@ -364,13 +437,13 @@ async fn handle_notification_click(
             }
             _ => {
                 // Default: just show main window
-                restore_from_tray(app_handle).await?;
+                restore_from_tray(app_handle.clone()).await?;
             }
         }
 
         // Clean up notification data
         store.delete(&notification_id);
-        let _ = store.save();
+        let _ = store_recovery::save(&app_handle, path, &store);
     }
 
     Ok(())
@@ -381,46 +454,36 @@ async fn save_notification_settings(
     app_handle: AppHandle,
     settings: NotificationSettings,
 ) -> Result<(), String> {
-    let store = StoreBuilder::new(
-        &app_handle,
-        std::path::PathBuf::from("notification-settings.json"),
-    )
-    .build()
-    .map_err(|e| e.to_string())?;
+    let path = accounts::store_path(&app_handle, "notification-settings.json");
+    let store = store_recovery::open(&app_handle, &path)?;
 
     store.set("settings", serde_json::to_value(settings).unwrap());
-    store.save().map_err(|e| e.to_string())?;
+    store_recovery::save(&app_handle, path, &store)?;
+
+    // Quiet hours may have been toggled or moved
+    clock::refresh_quiet_hours(&app_handle, false).await?;
 
     Ok(())
 }
 
 #[tauri::command]
 async fn load_notification_settings(app_handle: AppHandle) -> Result<NotificationSettings, String> {
-    let store = StoreBuilder::new(
+    let settings = store_recovery::read(
         &app_handle,
-        std::path::PathBuf::from("notification-settings.json"),
-    )
-    .build()
-    .map_err(|e| e.to_string())?;
-
-    if let Some(value) = store.get("settings") {
-        let settings: NotificationSettings =
-            serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
-        Ok(settings)
-    } else {
-        Ok(NotificationSettings::default())
-    }
+        accounts::store_path(&app_handle, "notification-settings.json"),
+        "settings",
+    )?;
+    Ok(settings.unwrap_or_default())
 }
 
 #[tauri::command]
 async fn clear_all_notifications(app_handle: AppHandle) -> Result<(), String> {
     // Clear notification store
-    let store = StoreBuilder::new(&app_handle, std::path::PathBuf::from("notifications.json"))
-        .build()
-        .map_err(|e| e.to_string())?;
+    let path = accounts::store_path(&app_handle, "notifications.json");
+    let store = store_recovery::open(&app_handle, &path)?;
 
     store.clear();
-    store.save().map_err(|e| e.to_string())?;
+    store_recovery::save(&app_handle, path, &store)?;
 
     Ok(())
 }
@@ -442,10 +505,27 @@ async fn open_url(app_handle: AppHandle, url: String) -> Result<(), String> {
     Ok(())
 }
 
+fn tray_tooltip(app: &AppHandle) -> String {
+    let count = app.state::<UnreadCount>().0.load(Ordering::Relaxed);
+    if count > 0 {
+        i18n::t_with(app, "tray.tooltip_unread", &[("count", &count.to_string())])
+    } else {
+        i18n::t(app, "tray.tooltip")
+    }
+}
+
 fn create_tray_menu(app: &AppHandle) -> Result<Menu<tauri::Wry>, tauri::Error> {
-    let show = MenuItem::with_id(app, "show", "Show MSN Messenger", true, None::<&str>)?;
-    let hide = MenuItem::with_id(app, "hide", "Hide to Tray", true, None::<&str>)?;
-    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    let show = MenuItem::with_id(app, "show", i18n::t(app, "tray.show"), true, None::<&str>)?;
+    let hide = MenuItem::with_id(app, "hide", i18n::t(app, "tray.hide"), true, None::<&str>)?;
+    let mute_sounds = CheckMenuItem::with_id(
+        app,
+        "mute_sounds",
+        i18n::t(app, "tray.mute_sounds"),
+        true,
+        sound::is_muted(app),
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(app, "quit", i18n::t(app, "tray.quit"), true, None::<&str>)?;
 
     let menu = Menu::with_items(
         app,
@@ -454,6 +534,8 @@ fn create_tray_menu(app: &AppHandle) -> Result<Menu<tauri::Wry>, tauri::Error> {
             &PredefinedMenuItem::separator(app)?,
             &hide,
             &PredefinedMenuItem::separator(app)?,
+            &mute_sounds,
+            &PredefinedMenuItem::separator(app)?,
             &quit,
         ],
     )?;
@@ -461,24 +543,81 @@ fn create_tray_menu(app: &AppHandle) -> Result<Menu<tauri::Wry>, tauri::Error> {
     Ok(menu)
 }
 
+// Rebuilds the tray menu and tooltip, e.g. after the language changes
+fn refresh_tray(app: &AppHandle) -> Result<(), tauri::Error> {
+    if let Some(tray) = app.tray_by_id("main-tray") {
+        tray.set_menu(Some(create_tray_menu(app)?))?;
+        tray.set_tooltip(Some(&tray_tooltip(app)))?;
+    }
+    Ok(())
+}
+
 fn main() {
     // Initialize Tauri application with modern v2.7 plugin architecture
     let mut builder = tauri::Builder::default()
-        .plugin(tauri_plugin_store::Builder::default().build())
+        .plugin(
+            tauri_plugin_store::Builder::default()
+                .default_serialize_fn(storage::serialize_store)
+                .default_deserialize_fn(storage::deserialize_store)
+                .build(),
+        )
+        .register_asynchronous_uri_scheme_protocol(
+            avatars::SCHEME,
+            |context, request, responder| {
+                avatars::handle_request(context.app_handle(), request, responder)
+            },
+        )
+        .register_uri_scheme_protocol(emoticon_packs::SCHEME, |context, request| {
+            emoticon_packs::handle_request(context.app_handle(), request)
+        })
+        .register_asynchronous_uri_scheme_protocol(winks::SCHEME, |context, request, responder| {
+            winks::handle_request(context.app_handle(), request, responder)
+        })
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_deep_link::init());
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(
+            tauri_plugin_autostart::Builder::new()
+                .arg(autostart::AUTOSTART_ARG)
+                .build(),
+        )
+        .plugin(tauri_plugin_clipboard_manager::init());
 
     // Add updater plugin only on desktop platforms (not mobile)
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     {
-        builder = builder.plugin(tauri_plugin_updater::Builder::new().build());
+        builder = builder
+            .plugin(tauri_plugin_updater::Builder::new().build())
+            .manage(updates::UpdateState::default());
     }
 
     builder
+        .manage(clock::QuietHoursState::default())
+        .manage(i18n::LanguageState::default())
+        .manage(UnreadCount::default())
+        .manage(net::HttpClientState::default())
+        .manage(session::SessionState::default())
+        .manage(file_transfer::TransferState::default())
+        .manage(downloads::DownloadState::default())
+        .manage(drag_drop::DropState::default())
+        .manage(shared_files::SharedFilesState::default())
+        .manage(uploads::UploadState::default())
+        .manage(sound::SoundState::default())
+        .manage(mic_level::MicMonitorState::default())
+        .manage(nudge::NudgeState::default())
+        .manage(call_audio::RingtoneState::default())
+        .manage(call_audio::CallMuteState::default())
+        .manage(capture_sources::CaptureState::default())
+        .manage(media_capabilities::MediaCapabilitiesState::default())
+        .manage(message_cache::MessageCacheState::default())
+        .manage(accounts::AccountState::default())
+        .manage(message_log::MessageLogState::default())
+        .manage(avatars::AvatarCacheState::default())
+        .manage(emotes::EmotesState::default())
         .invoke_handler(tauri::generate_handler![
             create_chat_window,
             close_chat_window,
@@ -494,31 +633,184 @@ fn main() {
             save_notification_settings,
             load_notification_settings,
             clear_all_notifications,
-            open_url
+            open_url,
+            clock::get_quiet_hours_state,
+            settings::save_app_settings,
+            settings::load_app_settings,
+            settings::save_account_settings,
+            settings::load_account_settings,
+            hotkeys::register_hotkey,
+            hotkeys::unregister_hotkey,
+            hotkeys::list_hotkeys,
+            autostart::set_autostart,
+            autostart::get_autostart,
+            theme::get_system_theme,
+            i18n::get_app_language,
+            i18n::set_app_language,
+            proxy::get_proxy_settings,
+            proxy::save_proxy_settings,
+            proxy::test_proxy,
+            clipboard_watch::set_clipboard_link_detection,
+            clipboard_watch::get_clipboard_link_detection,
+            file_transfer::start_file_send,
+            file_transfer::receive_file_offer,
+            file_transfer::accept_file_receive,
+            file_transfer::cancel_file_transfer,
+            file_transfer::resume_file_transfer,
+            file_transfer::list_file_transfers,
+            file_transfer::get_transfer_speed_limits,
+            file_transfer::set_transfer_speed_limits,
+            file_transfer::set_transfer_speed_limit,
+            downloads::start_download,
+            downloads::pause_download,
+            downloads::resume_download,
+            downloads::cancel_download,
+            downloads::list_downloads,
+            downloads::open_file,
+            downloads::show_in_folder,
+            downloads::get_download_directory,
+            downloads::set_download_directory,
+            downloads::save_file_as,
+            drag_drop::read_dropped_file,
+            clipboard::read_clipboard_image,
+            screenshot::capture_screenshot,
+            thumbnails::get_thumbnail,
+            image_optimize::optimize_image,
+            file_checks::check_incoming_file,
+            file_checks::scan_file,
+            trusted_contacts::list_trusted_contacts,
+            trusted_contacts::add_trusted_contact,
+            trusted_contacts::remove_trusted_contact,
+            checksum::hash_file,
+            shared_files::get_shared_files,
+            shared_files::remove_shared_file_record,
+            conversation_export::export_conversation_bundle,
+            drag_out::start_file_drag,
+            uploads::start_upload,
+            uploads::retry_upload,
+            uploads::cancel_upload,
+            uploads::list_uploads,
+            preview::preview_file,
+            sound::play_sound,
+            sound::set_sound_volume,
+            sound::stop_all_sounds,
+            audio_devices::list_audio_devices,
+            audio_devices::set_audio_devices,
+            mic_level::start_mic_level_monitor,
+            mic_level::stop_mic_level_monitor,
+            cameras::list_cameras,
+            cameras::capture_camera_snapshot,
+            nudge::send_nudge,
+            nudge::receive_nudge,
+            nudge::get_nudge_cooldown,
+            sound_schemes::list_sound_schemes,
+            sound_schemes::import_sound_scheme,
+            sound_schemes::export_sound_scheme,
+            sound_schemes::set_sound_scheme,
+            sound_schemes::remove_sound_scheme,
+            call_audio::begin_call_audio_session,
+            call_audio::end_call_audio_session,
+            call_audio::start_ringtone,
+            call_audio::stop_ringtone,
+            transcription::transcription_available,
+            transcription::transcribe_audio,
+            echo_test::run_echo_test,
+            capture_sources::list_capture_sources,
+            capture_sources::grant_capture_source,
+            capture_sources::revoke_capture_source,
+            media_capabilities::get_media_capabilities,
+            av_permissions::check_av_permissions,
+            av_permissions::request_av_permissions,
+            call_audio::set_call_muted,
+            sound::set_sounds_muted,
+            message_cache::cache_messages,
+            message_cache::get_cached_messages,
+            message_cache::purge_chat_cache,
+            backup::backup_app_data,
+            backup::restore_app_data,
+            settings_export::export_settings,
+            settings_export::import_settings,
+            accounts::set_active_account,
+            drafts::save_draft,
+            drafts::get_draft,
+            drafts::clear_draft,
+            conversation_export::export_conversation,
+            message_log::enable_message_logging,
+            message_log::disable_message_logging,
+            storage_usage::get_storage_usage,
+            storage_usage::clear_cache,
+            emotes::record_emote_use,
+            emotes::get_frequent_emotes,
+            emoticon_packs::list_emoticon_packs,
+            emoticon_packs::import_emoticon_pack,
+            emoticon_packs::remove_emoticon_pack,
+            winks::list_winks,
+            winks::get_wink,
+            winks::install_wink,
+            winks::uninstall_wink,
+            #[cfg(not(any(target_os = "android", target_os = "ios")))]
+            updates::check_for_update,
+            #[cfg(not(any(target_os = "android", target_os = "ios")))]
+            updates::install_update
         ])
         .on_window_event(|window, event| {
             match event {
                 tauri::WindowEvent::CloseRequested { api, .. } => {
-                    // Prevent closing main window, minimize to tray instead
+                    // Keep the main window alive; what happens next is user-configurable
                     if window.label() == "main" {
-                        let _ = window.hide();
                         api.prevent_close();
+                        close_behavior::handle_main_window_close(window.app_handle());
                     }
                 }
+                tauri::WindowEvent::DragDrop(drop_event) => {
+                    // Files dropped onto any window become a send for that conversation
+                    drag_drop::handle_drag_drop(window, drop_event);
+                }
+                tauri::WindowEvent::ThemeChanged(_) => {
+                    theme::check_system_theme(window.app_handle());
+                }
                 _ => {}
             }
         })
         .setup(|app| {
+            // Encrypt stores left over from before encryption at rest
+            storage::encrypt_plaintext_stores(app.handle())?;
+
+            // Pick the signed-in account before opening any of its stores
+            accounts::init(app.handle())?;
+
+            // Put back any store that was damaged, e.g. by a crash mid-write
+            store_recovery::recover_stores(app.handle())?;
+
+            // Bring stores saved by older versions up to the current layout
+            migrations::migrate_stores(app.handle())?;
+
             // Initialize store for window state persistence
-            let _store =
-                StoreBuilder::new(app.handle(), std::path::PathBuf::from("window-state.json"))
-                    .build()?;
+            store_recovery::open(
+                app.handle(),
+                accounts::store_path(app.handle(), "window-state.json"),
+            )?;
+
+            // Pick the UI language before building any native menus
+            i18n::init(app.handle());
+
+            // Load the file transfer and download history
+            file_transfer::init(app.handle())?;
+            downloads::init(app.handle())?;
+            uploads::init(app.handle())?;
+            shared_files::init(app.handle())?;
+            sound::init(app.handle());
 
             // Create system tray
             let tray_menu = create_tray_menu(app.handle())?;
             let _tray = TrayIconBuilder::with_id("main-tray")
                 .menu(&tray_menu)
-                .tooltip("MSN Messenger")
+                .tooltip(tray_tooltip(app.handle()))
+                .on_menu_event(|app, event| {
+                    if event.id() == "mute_sounds" {
+                        let _ = sound::set_muted(app, !sound::is_muted(app));
+                    }
+                })
                 .on_tray_icon_event(|_tray, event| {
                     match event {
                         TrayIconEvent::Click {
@@ -537,8 +829,42 @@ fn main() {
                 })
                 .build(app)?;
 
+            // Honor "start hidden in tray" for login launches
+            autostart::show_main_window_on_launch(app.handle())?;
+
+            // Restore user-configured global shortcuts
+            hotkeys::restore_hotkeys(app.handle())?;
+
+            // Watch for timezone / clock changes so quiet hours stay accurate
+            tauri::async_runtime::spawn(clock::watch_system_clock(app.handle().clone()));
+
+            // Watch for displays sleeping / lid closing
+            tauri::async_runtime::spawn(power::watch_display_state(app.handle().clone()));
+
+            // Follow OS dark/light mode and accent color
+            theme::watch_system_theme(app.handle());
+
+            // Fast user switching
+            tauri::async_runtime::spawn(session::watch_session(app.handle().clone()));
+
+            // Opt-in invite link detection on the clipboard
+            tauri::async_runtime::spawn(clipboard_watch::watch_clipboard(app.handle().clone()));
+
+            // Microphone and speaker hot-plug
+            tauri::async_runtime::spawn(audio_devices::watch_devices(app.handle().clone()));
+
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app_handle, _event| {
+            #[cfg(target_os = "macos")]
+            if let tauri::RunEvent::Reopen {
+                has_visible_windows,
+                ..
+            } = _event
+            {
+                close_behavior::handle_reopen(_app_handle, has_visible_windows);
+            }
+        });
 }
@@ -0,0 +1,186 @@
+// What this machine's hardware can do for calls, so the call layer can pick
+// codecs and resolutions that won't overwhelm it
+use serde::Serialize;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::cameras::{self, Camera};
+
+// Codecs worth offering in a call
+const CALL_CODECS: [&str; 5] = ["h264", "hevc", "av1", "vp8", "vp9"];
+// ffmpeg's suffixes for encoders backed by a GPU or media engine
+const HARDWARE_SUFFIXES: [&str; 7] = [
+    "_nvenc",
+    "_qsv",
+    "_amf",
+    "_vaapi",
+    "_videotoolbox",
+    "_mf",
+    "_v4l2m2m",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HardwareEncoder {
+    // ffmpeg's name for it, e.g. "h264_videotoolbox"
+    name: String,
+    codec: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaCapabilities {
+    // Empty when ffmpeg isn't installed to probe with
+    hardware_encoders: Vec<HardwareEncoder>,
+    // Hardware decoding APIs that initialise, e.g. "videotoolbox" or "vaapi"
+    hardware_decoders: Vec<String>,
+    cameras: Vec<Camera>,
+    // Whether the OS can cancel echo itself, on top of WebRTC's own
+    system_echo_cancellation: bool,
+}
+
+// Probing the GPU takes a few seconds and the answer doesn't change while
+// the app runs, so it's done once
+#[derive(Default)]
+pub struct MediaCapabilitiesState(Mutex<Option<(Vec<HardwareEncoder>, Vec<String>)>>);
+
+fn ffmpeg_output(args: &[&str]) -> Option<String> {
+    let output = Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .args(args)
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// A one-frame encode is the only reliable test; ffmpeg lists every encoder
+// it was built with, whether or not the hardware is there
+fn ffmpeg_succeeds(args: &[&str]) -> bool {
+    Command::new("ffmpeg")
+        .args(["-v", "error"])
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn probe_encoders() -> Vec<HardwareEncoder> {
+    let Some(listing) = ffmpeg_output(&["-encoders"]) else {
+        return Vec::new();
+    };
+
+    // " V....D h264_nvenc    NVIDIA NVENC H.264 encoder (codec h264)"
+    listing
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            if !fields.next()?.starts_with('V') {
+                return None;
+            }
+            let name = fields.next()?;
+            if !HARDWARE_SUFFIXES
+                .iter()
+                .any(|suffix| name.ends_with(suffix))
+            {
+                return None;
+            }
+            let codec = line
+                .rsplit_once("(codec ")
+                .and_then(|(_, codec)| codec.strip_suffix(')'))
+                .unwrap_or_else(|| name.split('_').next().unwrap_or(name));
+            CALL_CODECS.contains(&codec).then(|| HardwareEncoder {
+                name: name.to_string(),
+                codec: codec.to_string(),
+            })
+        })
+        .filter(|encoder| {
+            ffmpeg_succeeds(&[
+                "-f",
+                "lavfi",
+                "-i",
+                "nullsrc=s=256x256",
+                "-frames:v",
+                "1",
+                "-c:v",
+                &encoder.name,
+                "-f",
+                "null",
+                "-",
+            ])
+        })
+        .collect()
+}
+
+fn probe_decoders() -> Vec<String> {
+    let Some(listing) = ffmpeg_output(&["-hwaccels"]) else {
+        return Vec::new();
+    };
+
+    // A heading line, then one API per line
+    listing
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .filter(|api| !api.is_empty())
+        .filter(|api| {
+            ffmpeg_succeeds(&[
+                "-init_hw_device",
+                api,
+                "-f",
+                "lavfi",
+                "-i",
+                "nullsrc=s=64x64",
+                "-frames:v",
+                "1",
+                "-f",
+                "null",
+                "-",
+            ])
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+// Voice processing I/O on macOS and the communications DSP on Windows ship
+// with the OS
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn system_echo_cancellation() -> bool {
+    true
+}
+
+// PulseAudio and PipeWire only cancel echo with their module loaded
+#[cfg(target_os = "linux")]
+fn system_echo_cancellation() -> bool {
+    Command::new("pactl")
+        .args(["list", "short", "modules"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("module-echo-cancel"))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn system_echo_cancellation() -> bool {
+    false
+}
+
+#[tauri::command]
+pub async fn get_media_capabilities(app_handle: AppHandle) -> Result<MediaCapabilities, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<MediaCapabilitiesState>();
+        let (hardware_encoders, hardware_decoders) = state
+            .0
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| (probe_encoders(), probe_decoders()))
+            .clone();
+
+        MediaCapabilities {
+            hardware_encoders,
+            hardware_decoders,
+            // Cameras come and go, so they're listed fresh every time
+            cameras: cameras::enumerate().unwrap_or_default(),
+            system_echo_cancellation: system_echo_cancellation(),
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
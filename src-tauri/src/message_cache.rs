@@ -0,0 +1,296 @@
+// Local copy of recent messages, so conversations open instantly and can be
+// read offline. The server stays the source of truth; this only ever holds
+// the latest messages per chat. Payloads are encrypted; ids, chat ids and
+// times stay readable so they can be indexed.
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::storage;
+
+const DATABASE_FILE: &str = "message-cache.sqlite3";
+// Older messages are dropped once a chat holds more than this
+const MAX_PER_CHAT: u32 = 1000;
+const DEFAULT_PAGE: u32 = 50;
+const MAX_PAGE: u32 = 200;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS messages (
+        id TEXT PRIMARY KEY,
+        chat_id TEXT NOT NULL,
+        sent_at INTEGER NOT NULL,
+        payload BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS messages_by_chat ON messages (chat_id, sent_at);
+";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedMessage {
+    pub id: String,
+    pub chat_id: String,
+    pub sent_at: i64, // ms since the epoch
+    // The message as the frontend knows it; stored as-is
+    pub payload: serde_json::Value,
+}
+
+// Where a page ends. Messages sent in the same millisecond are told apart by
+// id, so none are skipped at a page boundary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageCursor {
+    sent_at: i64,
+    id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MessagePage {
+    messages: Vec<CachedMessage>,
+    // Pass back as `before` for the page before this one; None at the start
+    next: Option<MessageCursor>,
+}
+
+// Opened on first use
+#[derive(Default)]
+pub struct MessageCacheState(Mutex<Option<Connection>>);
+
+fn open(app: &AppHandle) -> Result<Connection, String> {
+    let dir = crate::accounts::data_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let mut connection = Connection::open(dir.join(DATABASE_FILE)).map_err(|e| e.to_string())?;
+    // WAL keeps reads from waiting on a big batch being written
+    connection
+        .pragma_update(None, "journal_mode", "WAL")
+        .map_err(|e| e.to_string())?;
+    // Deleted messages are overwritten rather than left in free pages
+    connection
+        .pragma_update(None, "secure_delete", true)
+        .map_err(|e| e.to_string())?;
+    connection
+        .execute_batch(SCHEMA)
+        .map_err(|e| e.to_string())?;
+    encrypt_plaintext_rows(&mut connection).map_err(|e| e.to_string())?;
+    Ok(connection)
+}
+
+fn encrypted_payload(payload: &[u8]) -> rusqlite::Result<Vec<u8>> {
+    storage::encrypt(payload).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))
+}
+
+// Rows cached before encryption, or while the key was unavailable
+fn encrypt_plaintext_rows(connection: &mut Connection) -> rusqlite::Result<()> {
+    if !storage::is_available() {
+        return Ok(());
+    }
+
+    let plaintext: Vec<(String, Vec<u8>)> = connection
+        .prepare("SELECT id, payload FROM messages WHERE substr(payload, 1, ?1) != ?2")?
+        .query_map(
+            params![storage::MAGIC.len() as i64, storage::MAGIC],
+            |row| Ok((row.get(0)?, row.get_ref(1)?.as_bytes()?.to_vec())),
+        )?
+        .collect::<rusqlite::Result<_>>()?;
+    if plaintext.is_empty() {
+        return Ok(());
+    }
+
+    let transaction = connection.transaction()?;
+    {
+        let mut update = transaction.prepare("UPDATE messages SET payload = ?2 WHERE id = ?1")?;
+        for (id, payload) in &plaintext {
+            update.execute(params![id, encrypted_payload(payload)?])?;
+        }
+    }
+    transaction.commit()?;
+    // Drops the old plaintext pages from the file
+    connection.execute_batch("VACUUM")
+}
+
+// Lets go of the database, e.g. when switching accounts
+pub fn close(app: &AppHandle) {
+    app.state::<MessageCacheState>().0.lock().unwrap().take();
+}
+
+fn with_database<T>(
+    app: &AppHandle,
+    f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>,
+) -> Result<T, String> {
+    let state = app.state::<MessageCacheState>();
+    let mut connection = state.0.lock().unwrap();
+    if connection.is_none() {
+        *connection = Some(open(app)?);
+    }
+    f(connection.as_mut().unwrap()).map_err(|e| e.to_string())
+}
+
+fn store(connection: &mut Connection, batch: &[CachedMessage]) -> rusqlite::Result<()> {
+    let transaction = connection.transaction()?;
+    {
+        let mut insert = transaction.prepare(
+            "INSERT INTO messages (id, chat_id, sent_at, payload) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (id) DO UPDATE SET
+                 chat_id = excluded.chat_id,
+                 sent_at = excluded.sent_at,
+                 payload = excluded.payload",
+        )?;
+        for message in batch {
+            insert.execute(params![
+                message.id,
+                message.chat_id,
+                message.sent_at,
+                encrypted_payload(message.payload.to_string().as_bytes())?
+            ])?;
+        }
+
+        let mut trim = transaction.prepare(
+            "DELETE FROM messages WHERE chat_id = ?1 AND id NOT IN (
+                 SELECT id FROM messages WHERE chat_id = ?1
+                 ORDER BY sent_at DESC, id DESC LIMIT ?2
+             )",
+        )?;
+        let chats: HashSet<&str> = batch
+            .iter()
+            .map(|message| message.chat_id.as_str())
+            .collect();
+        for chat_id in chats {
+            trim.execute(params![chat_id, MAX_PER_CHAT])?;
+        }
+    }
+    transaction.commit()
+}
+
+fn page(
+    connection: &Connection,
+    chat_id: &str,
+    before: Option<&MessageCursor>,
+    limit: u32,
+) -> rusqlite::Result<Vec<CachedMessage>> {
+    // With no cursor, (i64::MAX, "") sorts after every message
+    let (before_sent_at, before_id) = before.map_or((i64::MAX, ""), |cursor| {
+        (cursor.sent_at, cursor.id.as_str())
+    });
+    let mut query = connection.prepare(
+        "SELECT id, chat_id, sent_at, payload FROM messages
+         WHERE chat_id = ?1 AND (sent_at, id) < (?2, ?3)
+         ORDER BY sent_at DESC, id DESC LIMIT ?4",
+    )?;
+    let mut messages = query
+        .query_map(params![chat_id, before_sent_at, before_id, limit], |row| {
+            Ok(CachedMessage {
+                id: row.get(0)?,
+                chat_id: row.get(1)?,
+                sent_at: row.get(2)?,
+                // Anything unreadable comes back as null rather than
+                // failing the whole page
+                payload: storage::decrypt(row.get_ref(3)?.as_bytes()?)
+                    .ok()
+                    .and_then(|payload| serde_json::from_slice(&payload).ok())
+                    .unwrap_or_default(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // Fetched newest first to page backwards; returned oldest first to display
+    messages.reverse();
+    Ok(messages)
+}
+
+// Everything cached for a chat, oldest first
+pub fn chat_history(app: &AppHandle, chat_id: &str) -> Result<Vec<CachedMessage>, String> {
+    with_database(app, |connection| {
+        page(connection, chat_id, None, MAX_PER_CHAT)
+    })
+}
+
+// Chat id, time and id of each chat's newest message; several for a chat
+// whose newest messages share a time
+pub fn newest_messages(app: &AppHandle) -> Result<Vec<(String, i64, String)>, String> {
+    with_database(app, |connection| {
+        let mut query = connection.prepare(
+            "SELECT chat_id, sent_at, id FROM messages AS message
+             WHERE sent_at = (SELECT MAX(sent_at) FROM messages WHERE chat_id = message.chat_id)",
+        )?;
+        let rows = query.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    })
+}
+
+// Size on disk, counting the write-ahead log
+pub fn size(app: &AppHandle) -> Result<u64, String> {
+    let dir = crate::accounts::data_dir(app)?;
+    Ok(["", "-wal", "-shm"]
+        .iter()
+        .filter_map(|suffix| {
+            std::fs::metadata(dir.join(format!("{}{}", DATABASE_FILE, suffix))).ok()
+        })
+        .map(|metadata| metadata.len())
+        .sum())
+}
+
+// Empties every chat; they fill again from the server as they're opened
+pub fn clear(app: &AppHandle) -> Result<(), String> {
+    with_database(app, |connection| {
+        connection.execute_batch("DELETE FROM messages; VACUUM; PRAGMA wal_checkpoint(TRUNCATE)")
+    })
+}
+
+// Adds or updates messages, e.g. each batch the server sends
+#[tauri::command]
+pub async fn cache_messages(
+    app_handle: AppHandle,
+    batch: Vec<CachedMessage>,
+) -> Result<(), String> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        with_database(&app_handle, |connection| store(connection, &batch))?;
+        crate::message_log::append(&app_handle, &batch);
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Up to `limit` messages from before the `before` cursor, oldest first.
+// Leave `before` out for the latest messages.
+#[tauri::command]
+pub async fn get_cached_messages(
+    app_handle: AppHandle,
+    chat_id: String,
+    before: Option<MessageCursor>,
+    limit: Option<u32>,
+) -> Result<MessagePage, String> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE).clamp(1, MAX_PAGE);
+    let messages = tauri::async_runtime::spawn_blocking(move || {
+        with_database(&app_handle, |connection| {
+            page(connection, &chat_id, before.as_ref(), limit)
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    // A short page means there's nothing older
+    let next = messages
+        .first()
+        .filter(|_| messages.len() == limit as usize)
+        .map(|oldest| MessageCursor {
+            sent_at: oldest.sent_at,
+            id: oldest.id.clone(),
+        });
+    Ok(MessagePage { messages, next })
+}
+
+#[tauri::command]
+pub async fn purge_chat_cache(app_handle: AppHandle, chat_id: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        with_database(&app_handle, |connection| {
+            connection
+                .execute("DELETE FROM messages WHERE chat_id = ?1", params![chat_id])
+                .map(|_| ())
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,285 @@
+// Opt-in "keep message history on this computer": every cached message is
+// also appended to a plain-text log per contact or group, under the active
+// account's `message-logs/<chat id>/`. Unlike the cache these are meant to
+// be read outside the app, so they aren't encrypted.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::conversation_export;
+use crate::message_cache::CachedMessage;
+
+const LOGS_DIR: &str = "message-logs";
+const CURRENT_LOG: &str = "current.log";
+// Remembers how far each chat has been logged, since the same messages are
+// cached again whenever a chat is reopened
+const LOG_STORE: &str = "message-log.json";
+// Chats with nothing logged yet start from when logging was turned on
+const LOGGED_KEY: &str = "logged";
+const SINCE_KEY: &str = "logged_since";
+// The current log is set aside once it passes this size, or at the start of
+// a new month
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+// Older logs past this count are deleted, per chat
+const MAX_ROTATED_LOGS: usize = 24;
+
+// Held while writing so batches don't interleave
+#[derive(Default)]
+pub struct MessageLogState(Mutex<()>);
+
+// The newest logged time in a chat, with the ids logged at that time, so a
+// message sharing the time with ones already logged isn't dropped
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LogPosition {
+    sent_at: i64,
+    ids: Vec<String>,
+}
+
+impl LogPosition {
+    fn covers(&self, message: &CachedMessage) -> bool {
+        message.sent_at < self.sent_at
+            || (message.sent_at == self.sent_at && self.ids.contains(&message.id))
+    }
+
+    fn advance(&mut self, message: &CachedMessage) {
+        if message.sent_at > self.sent_at {
+            self.sent_at = message.sent_at;
+            self.ids.clear();
+        }
+        if message.sent_at == self.sent_at {
+            self.ids.push(message.id.clone());
+        }
+    }
+}
+
+pub fn logs_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::accounts::data_dir(app)?.join(LOGS_DIR))
+}
+
+fn chat_dir(logs: &Path, chat_id: &str) -> PathBuf {
+    let name: String = chat_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    logs.join(name)
+}
+
+fn needs_rotation(log: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(log) else {
+        return false;
+    };
+    let last_written = metadata.modified().map(|time| {
+        chrono::DateTime::<chrono::Local>::from(time)
+            .format("%Y-%m")
+            .to_string()
+    });
+    metadata.len() >= MAX_LOG_BYTES
+        || last_written.is_ok_and(|month| month != chrono::Local::now().format("%Y-%m").to_string())
+}
+
+// Rotated logs are named after when they were last written, so they sort in
+// order. Logs set aside within the same second get a counter after the time.
+fn rotate(dir: &Path) -> Result<(), String> {
+    let current = dir.join(CURRENT_LOG);
+    let last_written = std::fs::metadata(&current)
+        .and_then(|metadata| metadata.modified())
+        .map(chrono::DateTime::<chrono::Local>::from)
+        .unwrap_or_else(|_| chrono::Local::now());
+    let stamp = last_written.format("%Y-%m-%d_%H%M%S").to_string();
+    let mut rotated = dir.join(format!("{}.log", stamp));
+    let mut counter = 1;
+    while rotated.exists() {
+        rotated = dir.join(format!("{}_{:03}.log", stamp, counter));
+        counter += 1;
+    }
+    std::fs::rename(&current, rotated).map_err(|e| e.to_string())?;
+
+    let mut logs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension().is_some_and(|extension| extension == "log")
+                && path.file_name().is_some_and(|name| name != CURRENT_LOG)
+        })
+        .collect();
+    logs.sort();
+    let excess = logs.len().saturating_sub(MAX_ROTATED_LOGS);
+    for old in &logs[..excess] {
+        let _ = std::fs::remove_file(old);
+    }
+    Ok(())
+}
+
+fn append_chat(dir: &Path, messages: &[&CachedMessage]) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let current = dir.join(CURRENT_LOG);
+    if needs_rotation(&current) {
+        rotate(dir)?;
+    }
+
+    let mut lines = String::new();
+    for message in messages {
+        lines.push_str(&conversation_export::log_line(message));
+        lines.push('\n');
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&current)
+        .and_then(|mut file| file.write_all(lines.as_bytes()))
+        .map_err(|e| e.to_string())
+}
+
+// Where each chat's logging left off, and where chats not logged yet start
+fn positions(
+    app: &AppHandle,
+    path: &Path,
+) -> Result<(HashMap<String, LogPosition>, Option<i64>), String> {
+    let logged = crate::store_recovery::read(app, path, LOGGED_KEY)?.unwrap_or_default();
+    let since = crate::store_recovery::read(app, path, SINCE_KEY)?;
+    Ok((logged, since))
+}
+
+fn append_batch(app: &AppHandle, batch: &[CachedMessage]) -> Result<(), String> {
+    let state = app.state::<MessageLogState>();
+    let _writing = state.0.lock().unwrap();
+
+    let path = crate::accounts::store_path(app, LOG_STORE);
+    let store = crate::store_recovery::open(app, &path)?;
+    let (mut logged, since) = positions(app, &path)?;
+    let start = LogPosition {
+        sent_at: since.unwrap_or(i64::MIN),
+        ids: Vec::new(),
+    };
+
+    let mut chats: HashMap<&str, Vec<&CachedMessage>> = HashMap::new();
+    for message in batch {
+        if !logged
+            .get(&message.chat_id)
+            .unwrap_or(&start)
+            .covers(message)
+        {
+            chats.entry(&message.chat_id).or_default().push(message);
+        }
+    }
+    if chats.is_empty() {
+        return Ok(());
+    }
+
+    let logs = logs_dir(app)?;
+    for (chat_id, mut messages) in chats {
+        messages.sort_by(|a, b| (a.sent_at, &a.id).cmp(&(b.sent_at, &b.id)));
+        append_chat(&chat_dir(&logs, chat_id), &messages)?;
+        let position = logged
+            .entry(chat_id.to_string())
+            .or_insert_with(|| start.clone());
+        for message in messages {
+            position.advance(message);
+        }
+    }
+
+    store.set(LOGGED_KEY, serde_json::to_value(&logged).unwrap());
+    crate::store_recovery::save(app, path, &store)
+}
+
+// Called with each batch the message cache takes in. Does nothing unless
+// logging is on; failures are reported with "message-log-failed" rather
+// than failing the cache.
+pub fn append(app: &AppHandle, batch: &[CachedMessage]) {
+    if !crate::settings::load_account(app).is_ok_and(|settings| settings.keep_message_history) {
+        return;
+    }
+    if let Err(error) = append_batch(app, batch) {
+        let _ = app.emit("message-log-failed", error);
+    }
+}
+
+fn remove_logs(logs: &Path) -> Result<(), String> {
+    match std::fs::remove_dir_all(logs) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.to_string()),
+        _ => Ok(()),
+    }
+}
+
+// Frees the space the logs take. Logging carries on from where it was, so
+// messages already logged aren't written again.
+pub fn delete_logs(app: &AppHandle) -> Result<(), String> {
+    let logs = logs_dir(app)?;
+    let state = app.state::<MessageLogState>();
+    let _writing = state.0.lock().unwrap();
+    remove_logs(&logs)
+}
+
+fn set_enabled(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    let mut settings = crate::settings::load_account(app)?;
+    if settings.keep_message_history == enabled {
+        return Ok(());
+    }
+    if enabled {
+        start_logging(app)?;
+    }
+    settings.keep_message_history = enabled;
+    crate::settings::save_account(app, &settings)
+}
+
+// Marks everything already cached, and anything sent before now, as logged.
+// Otherwise reopening a chat would log the history it caches again.
+fn start_logging(app: &AppHandle) -> Result<(), String> {
+    let mut logged: HashMap<String, LogPosition> = HashMap::new();
+    for (chat_id, sent_at, id) in crate::message_cache::newest_messages(app)? {
+        let position = logged.entry(chat_id).or_default();
+        position.sent_at = sent_at;
+        position.ids.push(id);
+    }
+
+    let path = crate::accounts::store_path(app, LOG_STORE);
+    let store = crate::store_recovery::open(app, &path)?;
+    let state = app.state::<MessageLogState>();
+    let _writing = state.0.lock().unwrap();
+    store.set(LOGGED_KEY, serde_json::to_value(&logged).unwrap());
+    store.set(SINCE_KEY, chrono::Utc::now().timestamp_millis());
+    crate::store_recovery::save(app, path, &store)
+}
+
+// Only messages that arrive from now on are logged. Returns the folder the
+// logs go to.
+#[tauri::command]
+pub async fn enable_message_logging(app_handle: AppHandle) -> Result<PathBuf, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        set_enabled(&app_handle, true)?;
+        logs_dir(&app_handle)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Existing logs are kept unless `delete_logs` is set
+#[tauri::command]
+pub async fn disable_message_logging(
+    app_handle: AppHandle,
+    delete_logs: bool,
+) -> Result<(), String> {
+    set_enabled(&app_handle, false)?;
+    if !delete_logs {
+        return Ok(());
+    }
+
+    let logs = logs_dir(&app_handle)?;
+    let path = crate::accounts::store_path(&app_handle, LOG_STORE);
+    let store = crate::store_recovery::open(&app_handle, &path)?;
+    let state = app_handle.state::<MessageLogState>();
+    let _writing = state.0.lock().unwrap();
+    store.clear();
+    crate::store_recovery::save(&app_handle, path, &store)?;
+    remove_logs(&logs)
+}
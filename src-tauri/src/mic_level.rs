@@ -0,0 +1,159 @@
+// Live microphone level, so users can check their mic before joining a call
+use rodio::cpal::traits::{DeviceTrait, StreamTrait};
+use rodio::cpal::{Device, FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+use serde::Serialize;
+use std::sync::{mpsc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+
+// Roughly 20 "mic-level" events a second
+const UPDATES_PER_SECOND: u32 = 20;
+
+#[derive(Debug, Clone, Serialize)]
+struct MicLevel {
+    rms: f32,
+    peak: f32,
+}
+
+// Dropping the sender stops the stream on the monitor's thread
+#[derive(Default)]
+pub struct MicMonitorState(Mutex<Option<mpsc::Sender<()>>>);
+
+struct LevelMeter {
+    app: AppHandle,
+    window: usize,
+    count: usize,
+    sum_squares: f32,
+    peak: f32,
+}
+
+impl LevelMeter {
+    fn push(&mut self, sample: f32) {
+        self.sum_squares += sample * sample;
+        self.peak = self.peak.max(sample.abs());
+        self.count += 1;
+
+        if self.count >= self.window {
+            let rms = (self.sum_squares / self.count as f32).sqrt();
+            let _ = self.app.emit(
+                "mic-level",
+                MicLevel {
+                    rms,
+                    peak: self.peak,
+                },
+            );
+            self.count = 0;
+            self.sum_squares = 0.0;
+            self.peak = 0.0;
+        }
+    }
+}
+
+fn build_stream<T>(
+    app: &AppHandle,
+    device: &Device,
+    config: &StreamConfig,
+    mut on_sample: impl FnMut(f32) + Send + 'static,
+) -> Result<Stream, String>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let error_app = app.clone();
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _| {
+                for &sample in data {
+                    on_sample(sample.to_sample::<f32>());
+                }
+            },
+            // Usually the mic being unplugged mid-monitor
+            move |error| {
+                let _ = error_app.emit("mic-monitor-stopped", error.to_string());
+            },
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
+
+// Starts capturing from the selected microphone. `callback` gets the stream's
+// config and returns what receives every sample, as f32; capture stops when
+// the stream is dropped.
+pub fn open_input<F>(
+    app: &AppHandle,
+    callback: impl FnOnce(&StreamConfig) -> F,
+) -> Result<Stream, String>
+where
+    F: FnMut(f32) + Send + 'static,
+{
+    let device = crate::audio_devices::input_device(app).ok_or("No microphone found")?;
+    let supported = device.default_input_config().map_err(|e| e.to_string())?;
+    let format = supported.sample_format();
+    let config: StreamConfig = supported.into();
+    let on_sample = callback(&config);
+
+    let stream = match format {
+        SampleFormat::F32 => build_stream::<f32>(app, &device, &config, on_sample),
+        SampleFormat::I16 => build_stream::<i16>(app, &device, &config, on_sample),
+        SampleFormat::U16 => build_stream::<u16>(app, &device, &config, on_sample),
+        SampleFormat::I32 => build_stream::<i32>(app, &device, &config, on_sample),
+        format => Err(format!("Unsupported microphone format: {}", format)),
+    }?;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok(stream)
+}
+
+fn open_stream(app: &AppHandle) -> Result<Stream, String> {
+    let app = app.clone();
+    open_input(&app.clone(), move |config| {
+        let mut meter = LevelMeter {
+            app,
+            window: (config.sample_rate.0 * config.channels as u32 / UPDATES_PER_SECOND) as usize,
+            count: 0,
+            sum_squares: 0.0,
+            peak: 0.0,
+        };
+        move |sample| meter.push(sample)
+    })
+}
+
+// Restarts the monitor if it's already running, e.g. after switching mics
+#[tauri::command]
+pub async fn start_mic_level_monitor(app_handle: AppHandle) -> Result<(), String> {
+    stop_mic_level_monitor(app_handle.clone()).await?;
+
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let (stop_tx, stop_rx) = mpsc::channel::<()>();
+    let app = app_handle.clone();
+
+    // Streams aren't Send on every platform, so one thread owns it throughout
+    std::thread::spawn(move || match open_stream(&app) {
+        Ok(stream) => {
+            let _ = ready_tx.send(Ok(()));
+            let _ = stop_rx.recv();
+            drop(stream);
+        }
+        Err(error) => {
+            let _ = ready_tx.send(Err(error));
+        }
+    });
+
+    tauri::async_runtime::spawn_blocking(move || ready_rx.recv())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())??;
+
+    *app_handle.state::<MicMonitorState>().0.lock().unwrap() = Some(stop_tx);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_mic_level_monitor(app_handle: AppHandle) -> Result<(), String> {
+    app_handle
+        .state::<MicMonitorState>()
+        .0
+        .lock()
+        .unwrap()
+        .take();
+    Ok(())
+}
@@ -0,0 +1,131 @@
+// Versioned JSON stores. Each registered store records the version of its
+// layout under VERSION_KEY and is upgraded one step at a time on startup,
+// after the file as it was is backed up next to it.
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+
+use crate::storage;
+
+const VERSION_KEY: &str = "__schema_version";
+
+pub type StoreContents = HashMap<String, JsonValue>;
+// Upgrades a store from the version at its index in the list to the next
+type Migration = fn(&mut StoreContents) -> Result<(), String>;
+
+struct VersionedStore {
+    file: &'static str,
+    migrations: &'static [Migration],
+}
+
+// Append a step whenever a store's layout changes; never edit or reorder
+// existing ones, since files out there are at every version
+const STORES: &[VersionedStore] = &[
+    VersionedStore {
+        file: "window-state.json",
+        migrations: &[window_state_v1],
+    },
+    VersionedStore {
+        file: "notification-settings.json",
+        migrations: &[notification_settings_v1],
+    },
+];
+
+// Adds fields missing from a saved object, leaving the rest alone
+fn fill_missing(object: &mut JsonValue, defaults: JsonValue) {
+    if let (Some(object), JsonValue::Object(defaults)) = (object.as_object_mut(), defaults) {
+        for (key, value) in defaults {
+            object.entry(key).or_insert(value);
+        }
+    }
+}
+
+// Files from before versioning may be missing fields added over time
+fn window_state_v1(store: &mut StoreContents) -> Result<(), String> {
+    for config in store.values_mut() {
+        fill_missing(
+            config,
+            json!({ "x": null, "y": null, "maximized": false, "minimized": false }),
+        );
+    }
+    Ok(())
+}
+
+fn notification_settings_v1(store: &mut StoreContents) -> Result<(), String> {
+    if let Some(settings) = store.get_mut("settings") {
+        let defaults = serde_json::to_value(crate::NotificationSettings::default())
+            .map_err(|e| e.to_string())?;
+        fill_missing(settings, defaults);
+    }
+    Ok(())
+}
+
+fn version(store: &StoreContents) -> usize {
+    store
+        .get(VERSION_KEY)
+        .and_then(|version| version.as_u64())
+        .unwrap_or(0) as usize
+}
+
+// False for a store saved by a newer build, whose layout this one can't know
+pub fn is_supported(file: &str, store: &StoreContents) -> bool {
+    let latest = STORES
+        .iter()
+        .find(|versioned| versioned.file == file)
+        .map_or(0, |versioned| versioned.migrations.len());
+    version(store) <= latest
+}
+
+fn write(path: &Path, mut store: StoreContents, version: usize) -> Result<(), String> {
+    store.insert(VERSION_KEY.to_string(), version.into());
+    let bytes = storage::serialize_store(&store).map_err(|e| e.to_string())?;
+
+    let partial = path.with_extension("json.part");
+    std::fs::write(&partial, bytes).map_err(|e| e.to_string())?;
+    std::fs::rename(&partial, path).map_err(|e| e.to_string())
+}
+
+fn migrate(dir: &Path, versioned: &VersionedStore) -> Result<(), String> {
+    let path = dir.join(versioned.file);
+    let latest = versioned.migrations.len();
+
+    // New stores start out at the latest version
+    let Ok(bytes) = std::fs::read(&path) else {
+        return write(&path, StoreContents::new(), latest);
+    };
+
+    let mut store = storage::deserialize_store(&bytes).map_err(|e| e.to_string())?;
+    let version = version(&store);
+    store.remove(VERSION_KEY);
+    // Files from a newer build are left for that build
+    if version >= latest {
+        return Ok(());
+    }
+
+    let backup = dir.join(format!("{}.v{}.bak", versioned.file, version));
+    std::fs::write(&backup, &bytes).map_err(|e| e.to_string())?;
+
+    for migration in &versioned.migrations[version..] {
+        migration(&mut store)?;
+    }
+    write(&path, store, latest)
+}
+
+// Runs before any store is opened. A store that fails to migrate is left as
+// it was and reported with "store-migration-failed".
+pub fn migrate_stores(app: &AppHandle) -> Result<(), String> {
+    let dir = crate::accounts::data_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    for versioned in STORES {
+        if let Err(error) = migrate(&dir, versioned) {
+            let _ = app.emit(
+                "store-migration-failed",
+                json!({ "file": versioned.file, "error": error }),
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+// Shared HTTP client for all native networking. Anything in the Rust backend
+// that talks to the network must go through `client()` so proxy settings apply.
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::proxy::{self, ProxyMode, ProxySettings};
+
+#[derive(Default)]
+pub struct HttpClientState(Mutex<Option<reqwest::Client>>);
+
+pub fn build_client(
+    settings: &ProxySettings,
+    password: Option<&str>,
+) -> Result<reqwest::Client, String> {
+    let builder = reqwest::Client::builder()
+        .user_agent(concat!("BootlegMSN/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(15));
+
+    let builder = match settings.mode {
+        ProxyMode::System => match desktop_proxy() {
+            Some((url, ignore_hosts)) => builder.proxy(
+                reqwest::Proxy::all(url.as_str())
+                    .map_err(|e| e.to_string())?
+                    .no_proxy(reqwest::NoProxy::from_string(&ignore_hosts)),
+            ),
+            // reqwest picks up HTTP(S)_PROXY and the OS proxy configuration itself
+            None => builder,
+        },
+        ProxyMode::Direct => builder.no_proxy(),
+        ProxyMode::Manual => {
+            let url = proxy::proxy_url(settings, password)?;
+            builder.proxy(reqwest::Proxy::all(url.as_str()).map_err(|e| e.to_string())?)
+        }
+    };
+
+    builder.build().map_err(|e| e.to_string())
+}
+
+// reqwest reads the macOS and Windows proxy settings but only the environment
+// on Linux, so GNOME's manual proxy is read here when the environment has
+// none. Automatic (PAC) configuration isn't supported there. Returns the
+// proxy and a comma-separated list of hosts that bypass it.
+#[cfg(target_os = "linux")]
+fn desktop_proxy() -> Option<(url::Url, String)> {
+    let from_environment = ["HTTPS_PROXY", "HTTP_PROXY", "ALL_PROXY"]
+        .iter()
+        .any(|name| {
+            std::env::var_os(name).is_some() || std::env::var_os(name.to_lowercase()).is_some()
+        });
+    if from_environment {
+        return None;
+    }
+
+    let gsettings = |schema: &str, key: &str| {
+        let output = std::process::Command::new("gsettings")
+            .args(["get", schema, key])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    if gsettings("org.gnome.system.proxy", "mode")?.trim_matches('\'') != "manual" {
+        return None;
+    }
+
+    let url = [
+        ("org.gnome.system.proxy.https", "http"),
+        ("org.gnome.system.proxy.http", "http"),
+        ("org.gnome.system.proxy.socks", "socks5h"),
+    ]
+    .iter()
+    .find_map(|(schema, scheme)| {
+        let host = gsettings(schema, "host")?.trim_matches('\'').to_string();
+        let port: u16 = gsettings(schema, "port")?.parse().ok()?;
+        if host.is_empty() || port == 0 {
+            return None;
+        }
+        url::Url::parse(&format!("{}://{}:{}", scheme, host, port)).ok()
+    })?;
+
+    // A list like ['localhost', '127.0.0.0/8']
+    let ignore_hosts = gsettings("org.gnome.system.proxy", "ignore-hosts")
+        .unwrap_or_default()
+        .trim_matches(|c| c == '[' || c == ']')
+        .split(',')
+        .map(|host| host.trim().trim_matches('\''))
+        .filter(|host| !host.is_empty())
+        .collect::<Vec<_>>()
+        .join(",");
+    Some((url, ignore_hosts))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn desktop_proxy() -> Option<(url::Url, String)> {
+    None
+}
+
+// Cached client; rebuilt lazily after `invalidate`
+pub fn client(app_handle: &AppHandle) -> Result<reqwest::Client, String> {
+    let state = app_handle.state::<HttpClientState>();
+    let mut cached = state.0.lock().unwrap();
+
+    if let Some(client) = cached.as_ref() {
+        return Ok(client.clone());
+    }
+
+    let settings = proxy::load(app_handle)?;
+    let password = proxy::load_password()?;
+    let client = build_client(&settings, password.as_deref())?;
+    *cached = Some(client.clone());
+
+    Ok(client)
+}
+
+pub fn invalidate(app_handle: &AppHandle) {
+    *app_handle.state::<HttpClientState>().0.lock().unwrap() = None;
+}
+
+// The updater makes its own requests, so it's handed the same proxy
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub fn updater(app_handle: &AppHandle) -> Result<tauri_plugin_updater::Updater, String> {
+    use tauri_plugin_updater::UpdaterExt;
+
+    let settings = proxy::load(app_handle)?;
+    let builder = app_handle.updater_builder();
+    let builder = match settings.mode {
+        ProxyMode::System => match desktop_proxy() {
+            Some((url, _)) => builder.proxy(url),
+            None => builder,
+        },
+        ProxyMode::Direct => builder.no_proxy(),
+        ProxyMode::Manual => {
+            let password = proxy::load_password()?;
+            builder.proxy(proxy::proxy_url(&settings, password.as_deref())?)
+        }
+    };
+
+    builder.build().map_err(|e| e.to_string())
+}
@@ -0,0 +1,165 @@
+// Nudges: the window shake and sound, rate limited per chat
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, UserAttentionType, WebviewWindow};
+
+use crate::sound::{self, SoundId};
+
+// Matches the server's limit on sending
+const COOLDOWN: Duration = Duration::from_secs(30);
+const SHAKE_STEP: Duration = Duration::from_millis(40);
+// Offsets from the window's resting position, dying down towards the end
+const SHAKE_OFFSETS: [(i32, i32); 12] = [
+    (-12, 0),
+    (12, -6),
+    (-10, 6),
+    (10, 0),
+    (-8, -4),
+    (8, 4),
+    (-6, 0),
+    (6, -2),
+    (-3, 2),
+    (3, 0),
+    (-1, 0),
+    (0, 0),
+];
+
+#[derive(Default)]
+pub struct NudgeState {
+    last_sent: Mutex<HashMap<String, Instant>>,
+    // Incoming nudges past the first only raise an event until the cooldown
+    // passes, so a busy group chat can't keep the window shaking
+    last_received: Mutex<HashMap<String, Instant>>,
+    shaking: Mutex<HashSet<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct NudgeEvent<'a> {
+    chat_id: &'a str,
+}
+
+fn remaining(times: &HashMap<String, Instant>, chat_id: &str) -> Duration {
+    times
+        .get(chat_id)
+        .map(|at| COOLDOWN.saturating_sub(at.elapsed()))
+        .unwrap_or_default()
+}
+
+// Checked and taken under one lock, so two calls at once can't both get
+// through. The time left otherwise.
+fn take_turn(times: &Mutex<HashMap<String, Instant>>, chat_id: &str) -> Result<(), Duration> {
+    let mut times = times.lock().unwrap();
+    let wait = remaining(&times, chat_id);
+    if !wait.is_zero() {
+        return Err(wait);
+    }
+    times.insert(chat_id.to_string(), Instant::now());
+    Ok(())
+}
+
+// The chat's own window if it's popped out, otherwise the main window
+fn window_for_chat(app: &AppHandle, chat_id: &str) -> Option<WebviewWindow> {
+    app.get_webview_window(&crate::chat_window_label(chat_id))
+        .or_else(|| app.get_webview_window("main"))
+}
+
+async fn shake(app: &AppHandle, window: &WebviewWindow) -> Result<(), String> {
+    if window.is_maximized().unwrap_or(false) || window.is_fullscreen().unwrap_or(false) {
+        return Ok(());
+    }
+    let state = app.state::<NudgeState>();
+    if !state
+        .shaking
+        .lock()
+        .unwrap()
+        .insert(window.label().to_string())
+    {
+        return Ok(());
+    }
+
+    let result = async {
+        let origin = window.outer_position().map_err(|e| e.to_string())?;
+        for (x, y) in SHAKE_OFFSETS {
+            window
+                .set_position(PhysicalPosition::new(origin.x + x, origin.y + y))
+                .map_err(|e| e.to_string())?;
+            tokio::time::sleep(SHAKE_STEP).await;
+        }
+        Ok(())
+    }
+    .await;
+
+    state.shaking.lock().unwrap().remove(window.label());
+    result
+}
+
+async fn play_nudge_sound(app: &AppHandle) -> Result<(), String> {
+    let settings = crate::load_notification_settings(app.clone()).await?;
+    if !settings.sound_enabled
+        || crate::is_within_quiet_hours(&settings, chrono::Local::now().time())
+    {
+        return Ok(());
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || sound::play(&app, SoundId::Nudge))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// Seconds until another nudge can be sent to this chat
+#[tauri::command]
+pub async fn get_nudge_cooldown(app_handle: AppHandle, chat_id: String) -> Result<u64, String> {
+    let state = app_handle.state::<NudgeState>();
+    let wait = remaining(&state.last_sent.lock().unwrap(), &chat_id);
+    Ok(wait.as_secs_f64().ceil() as u64)
+}
+
+// Call before sending the nudge to the server; fails while the chat is
+// still cooling down
+#[tauri::command]
+pub async fn send_nudge(app_handle: AppHandle, chat_id: String) -> Result<(), String> {
+    let state = app_handle.state::<NudgeState>();
+    take_turn(&state.last_sent, &chat_id).map_err(|wait| {
+        format!(
+            "Wait {}s before sending another nudge",
+            wait.as_secs_f64().ceil() as u64
+        )
+    })?;
+
+    let _ = app_handle.emit("nudge-sent", NudgeEvent { chat_id: &chat_id });
+
+    // Like the original, the sender's own window shakes too. A missing
+    // speaker shouldn't stop the nudge itself.
+    let _ = play_nudge_sound(&app_handle).await;
+    if let Some(window) = window_for_chat(&app_handle, &chat_id) {
+        shake(&app_handle, &window).await?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn receive_nudge(app_handle: AppHandle, chat_id: String) -> Result<(), String> {
+    let _ = app_handle.emit("nudge-received", NudgeEvent { chat_id: &chat_id });
+
+    let state = app_handle.state::<NudgeState>();
+    if take_turn(&state.last_received, &chat_id).is_err() {
+        return Ok(());
+    }
+
+    // Nobody's there to see it while another user has the machine
+    if !crate::session::is_active(&app_handle) {
+        return Ok(());
+    }
+
+    let _ = play_nudge_sound(&app_handle).await;
+    let Some(window) = window_for_chat(&app_handle, &chat_id) else {
+        return Ok(());
+    };
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.request_user_attention(Some(UserAttentionType::Informational));
+    shake(&app_handle, &window).await
+}
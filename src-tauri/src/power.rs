@@ -0,0 +1,163 @@
+// Display sleep and laptop lid detection
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// What to do when every display turns off or the lid is closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayOffAction {
+    #[default]
+    KeepRunning,
+    AppearOffline,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DisplayState {
+    pub lid_closed: bool,
+    pub displays_off: bool,
+}
+
+impl DisplayState {
+    fn is_asleep(&self) -> bool {
+        self.lid_closed || self.displays_off
+    }
+
+    fn reason(&self) -> &'static str {
+        if self.lid_closed {
+            "lid_closed"
+        } else if self.displays_off {
+            "display_off"
+        } else {
+            "display_on"
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_display_state() -> DisplayState {
+    use std::fs;
+
+    // Any lid reporting "closed" counts; most laptops expose a single LID0
+    let lid_closed = fs::read_dir("/proc/acpi/button/lid")
+        .map(|entries| {
+            entries.flatten().any(|entry| {
+                fs::read_to_string(entry.path().join("state"))
+                    .map(|state| state.contains("closed"))
+                    .unwrap_or(false)
+            })
+        })
+        .unwrap_or(false);
+
+    // Displays are "off" only when every connected DRM connector reports DPMS off
+    let mut connected = 0;
+    let mut off = 0;
+    if let Ok(entries) = fs::read_dir("/sys/class/drm") {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let status = fs::read_to_string(path.join("status")).unwrap_or_default();
+            if status.trim() != "connected" {
+                continue;
+            }
+            connected += 1;
+            if fs::read_to_string(path.join("dpms"))
+                .map(|dpms| dpms.trim() == "Off")
+                .unwrap_or(false)
+            {
+                off += 1;
+            }
+        }
+    }
+
+    DisplayState {
+        lid_closed,
+        displays_off: connected > 0 && off == connected,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn read_display_state() -> DisplayState {
+    fn ioreg(args: &[&str]) -> String {
+        std::process::Command::new("ioreg")
+            .args(args)
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default()
+    }
+
+    let lid_closed = ioreg(&["-r", "-k", "AppleClamshellState", "-d", "1"])
+        .lines()
+        .any(|line| line.contains("\"AppleClamshellState\" = Yes"));
+
+    // IODisplayWrangler reports 4 when on, 3 when dimmed and lower when asleep
+    let displays_off = ioreg(&["-r", "-n", "IODisplayWrangler", "-d", "1"])
+        .lines()
+        .find_map(|line| {
+            let (_, value) = line.split_once("\"CurrentPowerState\"=")?;
+            value.trim().parse::<u32>().ok()
+        })
+        .map(|state| state < 3)
+        .unwrap_or(false);
+
+    DisplayState {
+        lid_closed,
+        displays_off,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn read_display_state() -> DisplayState {
+    use std::sync::atomic::Ordering;
+
+    DisplayState {
+        lid_closed: crate::win_events::LID_CLOSED.load(Ordering::Relaxed),
+        displays_off: crate::win_events::DISPLAYS_OFF.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn read_display_state() -> DisplayState {
+    DisplayState::default()
+}
+
+// Emits `display-state-changed` whenever the lid or displays change state and,
+// when the user opted into it, `power-presence-changed` so the frontend can
+// switch to Appear Offline and back.
+pub async fn watch_display_state(app: AppHandle) {
+    #[cfg(target_os = "windows")]
+    crate::win_events::start();
+
+    let mut last_state = DisplayState::default();
+    let mut appearing_offline = false;
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        let state = tauri::async_runtime::spawn_blocking(read_display_state)
+            .await
+            .unwrap_or_default();
+
+        if state != last_state {
+            let _ = app.emit("display-state-changed", state);
+            last_state = state;
+        }
+
+        let action = crate::settings::load(&app)
+            .map(|settings| settings.display_off_action)
+            .unwrap_or_default();
+        let should_appear_offline = action == DisplayOffAction::AppearOffline && state.is_asleep();
+
+        if should_appear_offline != appearing_offline {
+            appearing_offline = should_appear_offline;
+            let _ = app.emit(
+                "power-presence-changed",
+                serde_json::json!({
+                    "appear_offline": appearing_offline,
+                    "reason": state.reason(),
+                }),
+            );
+        }
+    }
+}
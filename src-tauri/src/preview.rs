@@ -0,0 +1,80 @@
+// Quick look at a file before opening it in its full application
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+#[cfg(not(target_os = "macos"))]
+const PREVIEW_WINDOW: &str = "file-preview";
+
+// Quick Look handles almost anything; our own window sticks to what a
+// webview can render without running anything from the file, so no HTML,
+// scripts or SVG
+#[cfg(not(target_os = "macos"))]
+fn is_previewable(path: &Path) -> bool {
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    match mime.essence_str() {
+        "text/plain" | "application/pdf" => true,
+        "image/svg+xml" => false,
+        _ => mime.type_() == "image",
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn show_preview(_app: &AppHandle, path: &Path) -> Result<(), String> {
+    use std::process::Stdio;
+
+    // `qlmanage -p` opens the same panel as pressing space in Finder
+    std::process::Command::new("qlmanage")
+        .arg("-p")
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+// A single plain webview window, reused for each preview. It isn't covered
+// by any capability, so the file it shows can't reach the app's commands.
+#[cfg(not(target_os = "macos"))]
+fn show_preview(app: &AppHandle, path: &Path) -> Result<(), String> {
+    use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
+
+    if !is_previewable(path) {
+        return Err("Only images, PDFs and text files can be previewed".to_string());
+    }
+
+    let url = tauri::Url::from_file_path(path)
+        .map_err(|_| format!("Invalid path: {}", path.display()))?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let title = crate::i18n::t_with(app, "window.preview_title", &[("name", &name)]);
+
+    if let Some(window) = app.get_webview_window(PREVIEW_WINDOW) {
+        window.navigate(url).map_err(|e| e.to_string())?;
+        window.set_title(&title).map_err(|e| e.to_string())?;
+        window.show().map_err(|e| e.to_string())?;
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
+    WebviewWindowBuilder::new(app, PREVIEW_WINDOW, WebviewUrl::External(url))
+        .title(&title)
+        .inner_size(800.0, 600.0)
+        .min_inner_size(300.0, 200.0)
+        .resizable(true)
+        .center()
+        .build()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn preview_file(app_handle: AppHandle, path: String) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    if !path.is_absolute() || !path.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+
+    show_preview(&app_handle, &path)
+}
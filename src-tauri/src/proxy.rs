@@ -0,0 +1,169 @@
+// Proxy configuration for native networking
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+use crate::{net, secrets, store_recovery};
+
+const PROXY_STORE: &str = "proxy-settings.json";
+const PASSWORD_SECRET: &str = "proxy-password";
+const DEFAULT_TEST_URL: &str = "https://www.gstatic.com/generate_204";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    #[default]
+    System,
+    Direct,
+    Manual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyKind {
+    #[default]
+    Http,
+    Socks5,
+}
+
+// The password never lives here; it's kept in the OS credential store
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    pub mode: ProxyMode,
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProxyConfig {
+    settings: ProxySettings,
+    has_password: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProxyTestResult {
+    ok: bool,
+    status: Option<u16>,
+    latency_ms: u64,
+    error: Option<String>,
+}
+
+pub fn proxy_url(settings: &ProxySettings, password: Option<&str>) -> Result<url::Url, String> {
+    let host = settings.host.trim();
+    if host.is_empty() || settings.port == 0 {
+        return Err("Proxy host and port are required".to_string());
+    }
+
+    // socks5h resolves hostnames through the proxy, which is what users expect
+    let scheme = match settings.kind {
+        ProxyKind::Http => "http",
+        ProxyKind::Socks5 => "socks5h",
+    };
+    let mut url = url::Url::parse(&format!("{}://{}:{}", scheme, host, settings.port))
+        .map_err(|e| format!("Invalid proxy address: {}", e))?;
+
+    if let Some(username) = settings.username.as_deref().filter(|u| !u.is_empty()) {
+        url.set_username(username)
+            .map_err(|_| "Invalid proxy username".to_string())?;
+        url.set_password(password)
+            .map_err(|_| "Invalid proxy password".to_string())?;
+    }
+
+    Ok(url)
+}
+
+pub fn load(app_handle: &AppHandle) -> Result<ProxySettings, String> {
+    let settings = store_recovery::read(app_handle, PROXY_STORE, "settings")?;
+    Ok(settings.unwrap_or_default())
+}
+
+pub fn load_password() -> Result<Option<String>, String> {
+    secrets::get(PASSWORD_SECRET)
+}
+
+#[tauri::command]
+pub async fn get_proxy_settings(app_handle: AppHandle) -> Result<ProxyConfig, String> {
+    Ok(ProxyConfig {
+        settings: load(&app_handle)?,
+        has_password: load_password()?.is_some(),
+    })
+}
+
+// `password`: None keeps the stored one, Some("") removes it
+#[tauri::command]
+pub async fn save_proxy_settings(
+    app_handle: AppHandle,
+    settings: ProxySettings,
+    password: Option<String>,
+) -> Result<(), String> {
+    if settings.mode == ProxyMode::Manual {
+        proxy_url(&settings, None)?;
+    }
+
+    let has_username = settings.username.as_deref().is_some_and(|u| !u.is_empty());
+    match password.as_deref() {
+        Some(password) if has_username && !password.is_empty() => {
+            secrets::set(PASSWORD_SECRET, password)?
+        }
+        Some(_) => secrets::delete(PASSWORD_SECRET)?,
+        None if !has_username => secrets::delete(PASSWORD_SECRET)?,
+        None => {}
+    }
+
+    let store = store_recovery::open(&app_handle, PROXY_STORE)?;
+
+    store.set("settings", serde_json::to_value(settings).unwrap());
+    store_recovery::save(&app_handle, PROXY_STORE, &store)?;
+
+    // Next request picks up the new proxy
+    net::invalidate(&app_handle);
+
+    Ok(())
+}
+
+// Tests the given (possibly unsaved) settings, or the saved ones
+#[tauri::command]
+pub async fn test_proxy(
+    app_handle: AppHandle,
+    settings: Option<ProxySettings>,
+    password: Option<String>,
+    url: Option<String>,
+) -> Result<ProxyTestResult, String> {
+    // Saved settings go through the shared client, exactly as real requests do
+    let client = match settings {
+        Some(settings) => {
+            let password = match password {
+                Some(password) => Some(password),
+                None => load_password()?,
+            };
+            net::build_client(&settings, password.as_deref())?
+        }
+        None => net::client(&app_handle)?,
+    };
+
+    let started = Instant::now();
+    let result = client
+        .get(url.as_deref().unwrap_or(DEFAULT_TEST_URL))
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    Ok(match result {
+        Ok(response) => ProxyTestResult {
+            ok: response.status().is_success(),
+            status: Some(response.status().as_u16()),
+            latency_ms,
+            error: None,
+        },
+        Err(e) => ProxyTestResult {
+            ok: false,
+            status: None,
+            latency_ms,
+            error: Some(e.to_string()),
+        },
+    })
+}
@@ -0,0 +1,64 @@
+// Token-bucket rate limiting for streamed transfers
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Bucket {
+    bytes_per_second: Option<u64>,
+    // Goes negative when a chunk overdraws it; that debt is slept off
+    available: f64,
+    last_refill: Instant,
+}
+
+pub struct RateLimiter(Mutex<Bucket>);
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self(Mutex::new(Bucket {
+            bytes_per_second: None,
+            available: 0.0,
+            last_refill: Instant::now(),
+        }))
+    }
+}
+
+impl RateLimiter {
+    // `None` removes the cap
+    pub fn set_kbps(&self, kbps: Option<u64>) {
+        let mut bucket = self.0.lock().unwrap();
+        bucket.bytes_per_second = kbps.filter(|kbps| *kbps > 0).map(|kbps| kbps * 1024);
+        bucket.available = 0.0;
+        bucket.last_refill = Instant::now();
+    }
+
+    pub fn kbps(&self) -> Option<u64> {
+        self.0
+            .lock()
+            .unwrap()
+            .bytes_per_second
+            .map(|rate| rate / 1024)
+    }
+
+    // Accounts for `bytes` just moved and waits long enough to stay under the cap
+    pub async fn consume(&self, bytes: usize) {
+        let wait = {
+            let mut bucket = self.0.lock().unwrap();
+            let Some(rate) = bucket.bytes_per_second else {
+                return;
+            };
+            let rate = rate as f64;
+
+            // Allow at most a second's worth of burst after idling
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.last_refill).as_secs_f64() * rate;
+            bucket.available = (bucket.available + refill).min(rate);
+            bucket.last_refill = now;
+            bucket.available -= bytes as f64;
+
+            (bucket.available < 0.0).then(|| Duration::from_secs_f64(-bucket.available / rate))
+        };
+
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
@@ -0,0 +1,330 @@
+// Screen capture for sharing in a conversation
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+// Gives the compositor time to actually remove our window before capturing
+const HIDE_DELAY: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    FullScreen,
+    ActiveWindow,
+    Region,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Screenshot {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+}
+
+#[cfg(target_os = "macos")]
+fn capture(_app: &AppHandle, mode: CaptureMode, out: &Path) -> Result<(), String> {
+    // There's no "frontmost window" flag, so window mode lets the user click one
+    let args: &[&str] = match mode {
+        CaptureMode::FullScreen => &["-x"],
+        CaptureMode::ActiveWindow => &["-x", "-o", "-i", "-W"],
+        CaptureMode::Region => &["-x", "-i", "-s"],
+    };
+
+    let output = std::process::Command::new("screencapture")
+        .args(args)
+        .arg(out)
+        .output()
+        .map_err(|e| e.to_string())?;
+    // Cancelling an interactive capture can exit non-zero too, but silently
+    let cancelled = mode != CaptureMode::FullScreen && output.stderr.is_empty();
+    if output.status.success() || cancelled {
+        Ok(())
+    } else {
+        Err(tool_error("screencapture", &output))
+    }
+}
+
+// A tool that ran but failed, with whatever it said about it
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn tool_error(program: &str, output: &std::process::Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match stderr.trim() {
+        "" => format!("{} failed ({})", program, output.status),
+        message => format!("{} failed: {}", program, message),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn capture(_app: &AppHandle, mode: CaptureMode, out: &Path) -> Result<(), String> {
+    use std::process::Command;
+
+    let out = out.to_string_lossy().into_owned();
+
+    // grim has no selection UI of its own; slurp provides the geometry
+    if mode == CaptureMode::Region {
+        if let Ok(output) = Command::new("slurp").output() {
+            if !output.status.success() {
+                return Ok(()); // selection cancelled
+            }
+            let geometry = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            match Command::new("grim").args(["-g", &geometry, &out]).output() {
+                Ok(output) if output.status.success() => return Ok(()),
+                Ok(output) => return Err(tool_error("grim", &output)),
+                Err(_) => {}
+            }
+        }
+    }
+
+    // Whichever screenshot tool the desktop ships with
+    let candidates: &[(&str, &[&str])] = match mode {
+        CaptureMode::FullScreen => &[
+            ("gnome-screenshot", &["-f"]),
+            ("spectacle", &["-b", "-n", "-f", "-o"]),
+            ("grim", &[]),
+            ("scrot", &["-o"]),
+            ("maim", &[]),
+        ],
+        CaptureMode::ActiveWindow => &[
+            ("gnome-screenshot", &["-w", "-f"]),
+            ("spectacle", &["-b", "-n", "-a", "-o"]),
+            ("scrot", &["-u", "-o"]),
+        ],
+        CaptureMode::Region => &[
+            ("gnome-screenshot", &["-a", "-f"]),
+            ("spectacle", &["-b", "-n", "-r", "-o"]),
+            ("scrot", &["-s", "-o"]),
+            ("maim", &["-s"]),
+        ],
+    };
+
+    for (program, args) in candidates {
+        match Command::new(program).args(*args).arg(&out).output() {
+            Ok(output) if output.status.success() => return Ok(()),
+            Ok(output) => return Err(tool_error(program, &output)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    Err(
+        "No supported screenshot tool found (gnome-screenshot, spectacle, grim, scrot or maim)"
+            .to_string(),
+    )
+}
+
+#[cfg(target_os = "windows")]
+fn capture(app: &AppHandle, mode: CaptureMode, out: &Path) -> Result<(), String> {
+    use windows_sys::Win32::Foundation::RECT;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetForegroundWindow, GetSystemMetrics, GetWindowRect, SM_CXVIRTUALSCREEN,
+        SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
+    };
+
+    let (x, y, width, height) = match mode {
+        CaptureMode::FullScreen => unsafe {
+            (
+                GetSystemMetrics(SM_XVIRTUALSCREEN),
+                GetSystemMetrics(SM_YVIRTUALSCREEN),
+                GetSystemMetrics(SM_CXVIRTUALSCREEN),
+                GetSystemMetrics(SM_CYVIRTUALSCREEN),
+            )
+        },
+        CaptureMode::ActiveWindow => unsafe {
+            let mut rect: RECT = std::mem::zeroed();
+            if GetWindowRect(GetForegroundWindow(), &mut rect) == 0 {
+                return Err("No active window to capture".to_string());
+            }
+            (
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+            )
+        },
+        CaptureMode::Region => return capture_region_with_snipping_tool(app, out),
+    };
+
+    let rgba = unsafe { capture_rect(x, y, width, height) }
+        .ok_or_else(|| "Screen capture failed".to_string())?;
+    image::RgbaImage::from_raw(width as u32, height as u32, rgba)
+        .ok_or_else(|| "Screen capture failed".to_string())?
+        .save_with_format(out, image::ImageFormat::Png)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "windows")]
+pub unsafe fn capture_rect(x: i32, y: i32, width: i32, height: i32) -> Option<Vec<u8>> {
+    use windows_sys::Win32::Graphics::Gdi::{BitBlt, CAPTUREBLT, SRCCOPY};
+
+    render_to_pixels(width, height, |memory, screen| {
+        BitBlt(
+            memory,
+            0,
+            0,
+            width,
+            height,
+            screen,
+            x,
+            y,
+            SRCCOPY | CAPTUREBLT,
+        ) != 0
+    })
+}
+
+// Hands `draw` a memory DC of the given size (plus the screen's DC) and
+// returns whatever it drew as RGBA
+#[cfg(target_os = "windows")]
+pub unsafe fn render_to_pixels(
+    width: i32,
+    height: i32,
+    draw: impl FnOnce(
+        windows_sys::Win32::Graphics::Gdi::HDC,
+        windows_sys::Win32::Graphics::Gdi::HDC,
+    ) -> bool,
+) -> Option<Vec<u8>> {
+    use windows_sys::Win32::Graphics::Gdi::{
+        CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC, GetDIBits,
+        ReleaseDC, SelectObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
+    };
+
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+
+    let screen = GetDC(std::ptr::null_mut());
+    let memory = CreateCompatibleDC(screen);
+    let bitmap = CreateCompatibleBitmap(screen, width, height);
+    let previous = SelectObject(memory, bitmap);
+
+    let copied = draw(memory, screen);
+
+    let mut info: BITMAPINFO = std::mem::zeroed();
+    info.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+    info.bmiHeader.biWidth = width;
+    info.bmiHeader.biHeight = -height; // top-down rows
+    info.bmiHeader.biPlanes = 1;
+    info.bmiHeader.biBitCount = 32;
+    info.bmiHeader.biCompression = BI_RGB;
+
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    let lines = GetDIBits(
+        memory,
+        bitmap,
+        0,
+        height as u32,
+        pixels.as_mut_ptr() as *mut _,
+        &mut info,
+        DIB_RGB_COLORS,
+    );
+
+    SelectObject(memory, previous);
+    DeleteObject(bitmap);
+    DeleteDC(memory);
+    ReleaseDC(std::ptr::null_mut(), screen);
+
+    if !copied || lines == 0 {
+        return None;
+    }
+
+    // GDI hands back BGRA with an undefined alpha channel
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+        pixel[3] = 0xff;
+    }
+    Some(pixels)
+}
+
+// The system snipping overlay only copies its result to the clipboard, so
+// wait for a new image to show up there.
+#[cfg(target_os = "windows")]
+fn capture_region_with_snipping_tool(app: &AppHandle, out: &Path) -> Result<(), String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    const SNIP_TIMEOUT: Duration = Duration::from_secs(60);
+    const SNIP_POLL: Duration = Duration::from_millis(250);
+
+    let before = app
+        .clipboard()
+        .read_image()
+        .ok()
+        .map(|image| image.rgba().to_vec());
+
+    std::process::Command::new("explorer.exe")
+        .arg("ms-screenclip:")
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    let started = std::time::Instant::now();
+    while started.elapsed() < SNIP_TIMEOUT {
+        std::thread::sleep(SNIP_POLL);
+
+        let Ok(image) = app.clipboard().read_image() else {
+            continue;
+        };
+        if before.as_deref() == Some(image.rgba()) {
+            continue;
+        }
+
+        return image::RgbaImage::from_raw(image.width(), image.height(), image.rgba().to_vec())
+            .ok_or_else(|| "Screen capture failed".to_string())?
+            .save_with_format(out, image::ImageFormat::Png)
+            .map_err(|e| e.to_string());
+    }
+
+    Ok(()) // nothing snipped in time; treated as cancelled
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn capture(_app: &AppHandle, _mode: CaptureMode, _out: &Path) -> Result<(), String> {
+    Err("Screenshots are not supported on this platform".to_string())
+}
+
+// Hides the calling window while capturing and returns the saved PNG, or
+// `None` if the user cancelled an interactive selection.
+#[tauri::command]
+pub async fn capture_screenshot(
+    app_handle: AppHandle,
+    window: WebviewWindow,
+    mode: CaptureMode,
+) -> Result<Option<Screenshot>, String> {
+    let directory = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join("screenshots");
+    std::fs::create_dir_all(&directory).map_err(|e| e.to_string())?;
+    let path = directory.join(format!(
+        "screenshot-{}.png",
+        chrono::Local::now().format("%Y%m%d-%H%M%S-%3f")
+    ));
+
+    let was_visible = window.is_visible().unwrap_or(false);
+    if was_visible {
+        let _ = window.hide();
+        tokio::time::sleep(HIDE_DELAY).await;
+    }
+
+    let app = app_handle.clone();
+    let out = path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || capture(&app, mode, &out))
+        .await
+        .map_err(|e| e.to_string());
+
+    if was_visible {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    result??;
+
+    if !path.exists() {
+        return Ok(None);
+    }
+    let (width, height) = image::image_dimensions(&path).map_err(|e| e.to_string())?;
+
+    Ok(Some(Screenshot {
+        path,
+        width,
+        height,
+    }))
+}
@@ -0,0 +1,28 @@
+// Secrets kept in the OS credential store (Keychain, Credential Manager,
+// Secret Service) instead of the plain JSON stores
+use keyring::Entry;
+
+const SERVICE: &str = "com.msnmessenger.bootleg";
+
+pub fn get(key: &str) -> Result<Option<String>, String> {
+    let entry = Entry::new(SERVICE, key).map_err(|e| e.to_string())?;
+    match entry.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub fn set(key: &str, value: &str) -> Result<(), String> {
+    Entry::new(SERVICE, key)
+        .and_then(|entry| entry.set_password(value))
+        .map_err(|e| e.to_string())
+}
+
+pub fn delete(key: &str) -> Result<(), String> {
+    let entry = Entry::new(SERVICE, key).map_err(|e| e.to_string())?;
+    match entry.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
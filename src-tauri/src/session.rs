@@ -0,0 +1,87 @@
+// Fast user switching: notice when our OS session is switched away from
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+// Whether our session owns the console; notifications are held while it doesn't
+pub struct SessionState(AtomicBool);
+
+impl Default for SessionState {
+    fn default() -> Self {
+        Self(AtomicBool::new(true))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+struct SessionChange {
+    active: bool,
+}
+
+pub fn is_active(app: &AppHandle) -> bool {
+    app.state::<SessionState>().0.load(Ordering::Relaxed)
+}
+
+#[cfg(target_os = "linux")]
+fn read_session_active() -> bool {
+    // logind marks the session on the active VT/seat as Active=yes
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "self".to_string());
+    std::process::Command::new("loginctl")
+        .args(["show-session", &session, "--property=Active", "--value"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim() != "no")
+        .unwrap_or(true)
+}
+
+#[cfg(target_os = "macos")]
+fn read_session_active() -> bool {
+    // The console device belongs to whichever user is switched in
+    let console_user = std::process::Command::new("stat")
+        .args(["-f", "%Su", "/dev/console"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+
+    match (console_user, std::env::var("USER")) {
+        (Some(console_user), Ok(user)) => console_user == user,
+        _ => true,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn read_session_active() -> bool {
+    crate::win_events::SESSION_ACTIVE.load(Ordering::Relaxed)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn read_session_active() -> bool {
+    true
+}
+
+// Emits `session-changed` when another user takes over the machine and again
+// when ours comes back, so the frontend can appear offline in between.
+pub async fn watch_session(app: AppHandle) {
+    #[cfg(target_os = "windows")]
+    crate::win_events::start();
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        let active = tauri::async_runtime::spawn_blocking(read_session_active)
+            .await
+            .unwrap_or(true);
+
+        let previous = app
+            .state::<SessionState>()
+            .0
+            .swap(active, Ordering::Relaxed);
+        if previous != active {
+            let _ = app.emit("session-changed", SessionChange { active });
+        }
+    }
+}
@@ -0,0 +1,96 @@
+// General application preferences, persisted alongside the other stores.
+// Machine-wide ones are in AppSettings; ones that belong to whoever is signed
+// in are in AccountSettings, kept in the account's folder.
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::close_behavior::CloseBehavior;
+use crate::file_checks::IncomingFilePolicy;
+use crate::power::DisplayOffAction;
+use crate::store_recovery;
+
+const SETTINGS_STORE: &str = "app-settings.json";
+const ACCOUNT_SETTINGS_STORE: &str = "account-settings.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub display_off_action: DisplayOffAction,
+    pub start_minimized: bool,
+    pub language: Option<String>, // None follows the OS locale
+    pub close_behavior: CloseBehavior,
+    pub clipboard_link_detection: bool,
+    pub incoming_files: IncomingFilePolicy,
+    pub transfer_upload_limit_kbps: Option<u64>, // None is unlimited
+    pub transfer_download_limit_kbps: Option<u64>,
+    pub sounds_muted: bool,
+    pub sound_volume: Option<f32>,          // None is full volume
+    pub audio_input_device: Option<String>, // None follows the system default
+    pub audio_output_device: Option<String>,
+    pub sound_scheme: Option<String>, // None uses the built-in sounds
+    pub transcription_model: Option<PathBuf>, // None uses <app data>/models/ggml-base.bin
+    pub active_account: Option<String>, // None while signed out
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountSettings {
+    pub download_directory: Option<PathBuf>, // None uses the OS Downloads folder
+    pub trusted_contacts: Vec<String>,       // contact user ids
+    pub auto_accept_trusted_transfers: bool,
+    pub keep_message_history: bool,
+}
+
+pub fn load(app_handle: &AppHandle) -> Result<AppSettings, String> {
+    let settings = store_recovery::read(app_handle, SETTINGS_STORE, "settings")?;
+    Ok(settings.unwrap_or_default())
+}
+
+pub fn save(app_handle: &AppHandle, settings: &AppSettings) -> Result<(), String> {
+    let store = store_recovery::open(app_handle, SETTINGS_STORE)?;
+
+    store.set("settings", serde_json::to_value(settings).unwrap());
+    store_recovery::save(app_handle, SETTINGS_STORE, &store)?;
+
+    Ok(())
+}
+
+pub fn load_account(app_handle: &AppHandle) -> Result<AccountSettings, String> {
+    let path = crate::accounts::store_path(app_handle, ACCOUNT_SETTINGS_STORE);
+    let settings = store_recovery::read(app_handle, path, "settings")?;
+    Ok(settings.unwrap_or_default())
+}
+
+pub fn save_account(app_handle: &AppHandle, settings: &AccountSettings) -> Result<(), String> {
+    let path = crate::accounts::store_path(app_handle, ACCOUNT_SETTINGS_STORE);
+    let store = store_recovery::open(app_handle, &path)?;
+
+    store.set("settings", serde_json::to_value(settings).unwrap());
+    store_recovery::save(app_handle, &path, &store)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn save_app_settings(app_handle: AppHandle, settings: AppSettings) -> Result<(), String> {
+    save(&app_handle, &settings)
+}
+
+#[tauri::command]
+pub async fn load_app_settings(app_handle: AppHandle) -> Result<AppSettings, String> {
+    load(&app_handle)
+}
+
+#[tauri::command]
+pub async fn save_account_settings(
+    app_handle: AppHandle,
+    settings: AccountSettings,
+) -> Result<(), String> {
+    save_account(&app_handle, &settings)
+}
+
+#[tauri::command]
+pub async fn load_account_settings(app_handle: AppHandle) -> Result<AccountSettings, String> {
+    load_account(&app_handle)
+}
@@ -0,0 +1,179 @@
+// Moving chosen settings between machines, as a plain JSON file. Unlike a
+// backup it isn't tied to this machine's keychain, so it leaves out anything
+// machine-specific like devices, folders and installed sound schemes.
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter};
+
+use crate::hotkeys::{self, HotkeyBinding};
+use crate::settings::AppSettings;
+use crate::sound;
+
+// Bumped when the file layout changes
+const FORMAT_VERSION: u32 = 1;
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+// The AppSettings fields that make up the sounds category
+const SOUND_FIELDS: [&str; 2] = ["sound_volume", "sounds_muted"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingsCategory {
+    Notifications,
+    Hotkeys,
+    Sounds,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    // Each imported category replaces the current one outright
+    Replace,
+    // Imported values win, but anything the file doesn't mention is kept
+    Merge,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SettingsFile {
+    format_version: u32,
+    app_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notifications: Option<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hotkeys: Option<Vec<HotkeyBinding>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sounds: Option<JsonValue>,
+}
+
+fn to_json(value: impl Serialize) -> Result<JsonValue, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+// Top-level fields of `imported` written over `base`
+fn overlay(mut base: JsonValue, imported: JsonValue) -> JsonValue {
+    if let (Some(base), JsonValue::Object(imported)) = (base.as_object_mut(), imported) {
+        base.extend(imported);
+    }
+    base
+}
+
+fn sound_fields(settings: &AppSettings) -> Result<JsonValue, String> {
+    let mut settings = to_json(settings)?;
+    if let Some(fields) = settings.as_object_mut() {
+        fields.retain(|field, _| SOUND_FIELDS.contains(&field.as_str()));
+    }
+    Ok(settings)
+}
+
+async fn import_notifications(
+    app: &AppHandle,
+    imported: JsonValue,
+    strategy: MergeStrategy,
+) -> Result<(), String> {
+    let base = match strategy {
+        MergeStrategy::Replace => crate::NotificationSettings::default(),
+        MergeStrategy::Merge => crate::load_notification_settings(app.clone()).await?,
+    };
+    let settings = serde_json::from_value(overlay(to_json(base)?, imported))
+        .map_err(|e| format!("The notification settings are invalid: {}", e))?;
+    crate::save_notification_settings(app.clone(), settings).await
+}
+
+async fn import_sounds(
+    app: &AppHandle,
+    imported: JsonValue,
+    strategy: MergeStrategy,
+) -> Result<(), String> {
+    let current = crate::settings::load(app)?;
+    let base = match strategy {
+        MergeStrategy::Replace => sound_fields(&AppSettings::default())?,
+        MergeStrategy::Merge => sound_fields(&current)?,
+    };
+    let sounds = overlay(base, imported);
+    // Only the sound fields are taken from the file
+    let settings: AppSettings = serde_json::from_value(overlay(to_json(&current)?, sounds))
+        .map_err(|e| format!("The sound settings are invalid: {}", e))?;
+
+    sound::set_sound_volume(app.clone(), settings.sound_volume.unwrap_or(1.0)).await?;
+    sound::set_muted(app, settings.sounds_muted)
+}
+
+#[tauri::command]
+pub async fn export_settings(
+    app_handle: AppHandle,
+    categories: Vec<SettingsCategory>,
+    path: String,
+) -> Result<(), String> {
+    if categories.is_empty() {
+        return Err("Choose at least one kind of setting to export".to_string());
+    }
+
+    let mut file = SettingsFile {
+        format_version: FORMAT_VERSION,
+        app_version: app_handle.package_info().version.to_string(),
+        notifications: None,
+        hotkeys: None,
+        sounds: None,
+    };
+    for category in categories {
+        match category {
+            SettingsCategory::Notifications => {
+                let settings = crate::load_notification_settings(app_handle.clone()).await?;
+                file.notifications = Some(to_json(settings)?);
+            }
+            SettingsCategory::Hotkeys => {
+                file.hotkeys = Some(hotkeys::load_bindings(&app_handle)?);
+            }
+            SettingsCategory::Sounds => {
+                file.sounds = Some(sound_fields(&crate::settings::load(&app_handle)?)?);
+            }
+        }
+    }
+
+    let contents = serde_json::to_vec_pretty(&file).map_err(|e| e.to_string())?;
+    std::fs::write(PathBuf::from(path), contents).map_err(|e| e.to_string())
+}
+
+// Returns the categories the file held. Emits "settings-imported" with them
+// so open windows pick the changes up.
+#[tauri::command]
+pub async fn import_settings(
+    app_handle: AppHandle,
+    path: String,
+    merge_strategy: MergeStrategy,
+) -> Result<Vec<SettingsCategory>, String> {
+    let path = PathBuf::from(path);
+    if std::fs::metadata(&path).map_err(|e| e.to_string())?.len() > MAX_FILE_BYTES {
+        return Err("This file is too large to be a settings export".to_string());
+    }
+    let contents = std::fs::read(&path).map_err(|e| e.to_string())?;
+    let file: SettingsFile = serde_json::from_slice(&contents)
+        .map_err(|_| "This file isn't a settings export".to_string())?;
+    if file.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "These settings were exported by version {}; update the app to import them",
+            file.app_version
+        ));
+    }
+
+    let mut imported = Vec::new();
+    if let Some(notifications) = file.notifications {
+        import_notifications(&app_handle, notifications, merge_strategy).await?;
+        imported.push(SettingsCategory::Notifications);
+    }
+    if let Some(bindings) = file.hotkeys {
+        hotkeys::import_bindings(
+            &app_handle,
+            bindings,
+            merge_strategy == MergeStrategy::Merge,
+        )?;
+        imported.push(SettingsCategory::Hotkeys);
+    }
+    if let Some(sounds) = file.sounds {
+        import_sounds(&app_handle, sounds, merge_strategy).await?;
+        imported.push(SettingsCategory::Sounds);
+    }
+
+    let _ = app_handle.emit("settings-imported", &imported);
+    Ok(imported)
+}
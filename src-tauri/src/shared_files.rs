@@ -0,0 +1,134 @@
+// History of every file sent or received, for the "files shared" view
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+const SHARED_FILES_STORE: &str = "shared-files.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SharedDirection {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedFile {
+    // Same id as the transfer or download that produced it
+    pub id: String,
+    pub chat_id: String,
+    pub peer_id: Option<String>,
+    pub direction: SharedDirection,
+    pub file_name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub shared_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SharedFileFilter {
+    direction: Option<SharedDirection>,
+    query: Option<String>, // case-insensitive match on the file name
+}
+
+#[derive(Default)]
+pub struct SharedFilesState(Mutex<Vec<SharedFile>>);
+
+fn save_files(app: &AppHandle, files: &[SharedFile]) -> Result<(), String> {
+    let path = crate::accounts::store_path(app, SHARED_FILES_STORE);
+    let store = crate::store_recovery::open(app, &path)?;
+
+    store.set("files", serde_json::to_value(files).unwrap());
+    crate::store_recovery::save(app, path, &store)
+}
+
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let store =
+        crate::store_recovery::open(app, crate::accounts::store_path(app, SHARED_FILES_STORE))?;
+
+    let files: Vec<SharedFile> = store
+        .get("files")
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+
+    *app.state::<SharedFilesState>().0.lock().unwrap() = files;
+    Ok(())
+}
+
+// Called once a transfer or download has completed
+pub fn record(app: &AppHandle, file: SharedFile) {
+    let state = app.state::<SharedFilesState>();
+    let mut files = state.0.lock().unwrap();
+    if files.iter().any(|existing| existing.id == file.id) {
+        return;
+    }
+    files.push(file.clone());
+    let _ = save_files(app, &files);
+    drop(files);
+
+    let _ = app.emit("shared-file-added", &file);
+}
+
+pub fn contains_path(app: &AppHandle, path: &Path) -> bool {
+    app.state::<SharedFilesState>()
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|file| file.path == path)
+}
+
+// Oldest first, in the order they were shared
+pub fn list_for_chat(app: &AppHandle, chat_id: &str) -> Vec<SharedFile> {
+    app.state::<SharedFilesState>()
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|file| file.chat_id == chat_id)
+        .cloned()
+        .collect()
+}
+
+// Newest first
+#[tauri::command]
+pub async fn get_shared_files(
+    app_handle: AppHandle,
+    chat_id: String,
+    filter: Option<SharedFileFilter>,
+) -> Result<Vec<SharedFile>, String> {
+    let filter = filter.unwrap_or_default();
+    let query = filter.query.map(|query| query.to_lowercase());
+
+    let mut files: Vec<SharedFile> = list_for_chat(&app_handle, &chat_id)
+        .into_iter()
+        .filter(|file| {
+            filter
+                .direction
+                .is_none_or(|direction| file.direction == direction)
+        })
+        .filter(|file| {
+            query
+                .as_ref()
+                .is_none_or(|query| file.file_name.to_lowercase().contains(query))
+        })
+        .collect();
+
+    files.sort_by_key(|file| std::cmp::Reverse(file.shared_at));
+    Ok(files)
+}
+
+// Forgets the entry; the file itself stays on disk
+#[tauri::command]
+pub async fn remove_shared_file_record(app_handle: AppHandle, id: String) -> Result<(), String> {
+    let state = app_handle.state::<SharedFilesState>();
+    let mut files = state.0.lock().unwrap();
+    let before = files.len();
+    files.retain(|file| file.id != id);
+    if files.len() == before {
+        return Err(format!("Unknown shared file: {}", id));
+    }
+    save_files(&app_handle, &files)
+}
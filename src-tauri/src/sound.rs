@@ -0,0 +1,335 @@
+// Native playback for UI sounds, so they still play while every window is hidden
+use rodio::buffer::SamplesBuffer;
+use rodio::mixer::Mixer;
+use rodio::source::SineWave;
+use rodio::{Decoder, Device, OutputStreamBuilder, Sink, Source};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const MESSAGE_SOUND: &[u8] = include_bytes!("../../public/sounds/message.mp3");
+const NUDGE_SOUND: &[u8] = include_bytes!("../../public/sounds/nudge.mp3");
+const ONLINE_SOUND: &[u8] = include_bytes!("../../public/sounds/online.mp3");
+// Share of the normal volume left for sounds during a call
+const DUCKED_VOLUME: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundId {
+    NewMessage,
+    Nudge,
+    ContactOnline,
+    Typing,
+    Ringtone,
+}
+
+// The output stream lives on its own thread; dropping this closes it
+struct Output {
+    mixer: Mixer,
+    _close: mpsc::Sender<()>,
+}
+
+pub struct SoundState {
+    output: Mutex<Option<Output>>,
+    // Decoded on first use and kept for every later play
+    sounds: Mutex<HashMap<SoundId, SamplesBuffer>>,
+    playing: Mutex<Vec<Sink>>,
+    // Sounds repeating until stopped, by caller-chosen key
+    looping: Mutex<HashMap<String, Sink>>,
+    volume: Mutex<f32>,
+    // Silences everything short of the echo test, which is about the devices
+    muted: Mutex<bool>,
+    // Set while a call is running
+    ducked: Mutex<bool>,
+}
+
+impl Default for SoundState {
+    fn default() -> Self {
+        Self {
+            output: Mutex::new(None),
+            sounds: Mutex::new(HashMap::new()),
+            playing: Mutex::new(Vec::new()),
+            looping: Mutex::new(HashMap::new()),
+            volume: Mutex::new(1.0),
+            muted: Mutex::new(false),
+            ducked: Mutex::new(false),
+        }
+    }
+}
+
+pub fn init(app: &AppHandle) {
+    let settings = crate::settings::load(app).unwrap_or_default();
+    let state = app.state::<SoundState>();
+    *state.volume.lock().unwrap() = settings.sound_volume.unwrap_or(1.0);
+    *state.muted.lock().unwrap() = settings.sounds_muted;
+}
+
+pub fn decode(bytes: impl AsRef<[u8]> + Send + Sync + 'static) -> Result<SamplesBuffer, String> {
+    let decoder = Decoder::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+    Ok(SamplesBuffer::new(
+        channels,
+        sample_rate,
+        decoder.collect::<Vec<_>>(),
+    ))
+}
+
+fn load_sound(app: &AppHandle, id: SoundId) -> Result<SamplesBuffer, String> {
+    // A broken scheme file falls back to the built-in sound
+    if let Some(path) = crate::sound_schemes::active_sound(app, id) {
+        if let Ok(sound) = std::fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(decode)
+        {
+            return Ok(sound);
+        }
+    }
+
+    match id {
+        SoundId::NewMessage => decode(MESSAGE_SOUND),
+        SoundId::Nudge => decode(NUDGE_SOUND),
+        SoundId::ContactOnline => decode(ONLINE_SOUND),
+        // A short soft tick rather than a bundled file
+        SoundId::Typing => {
+            let tick = SineWave::new(1200.0)
+                .take_duration(Duration::from_millis(25))
+                .amplify(0.2);
+            let (channels, sample_rate) = (tick.channels(), tick.sample_rate());
+            Ok(SamplesBuffer::new(
+                channels,
+                sample_rate,
+                tick.collect::<Vec<_>>(),
+            ))
+        }
+        SoundId::Ringtone => Ok(ring()),
+    }
+}
+
+// Two short bursts of the classic 440 + 480 Hz ring, then a pause; meant to
+// be looped
+fn ring() -> SamplesBuffer {
+    const SAMPLE_RATE: u32 = 48_000;
+    const PATTERN: [(bool, u32); 4] = [(true, 400), (false, 200), (true, 400), (false, 2000)];
+
+    let mut samples = Vec::new();
+    for (on, millis) in PATTERN {
+        for _ in 0..SAMPLE_RATE * millis / 1000 {
+            let t = samples.len() as f32 / SAMPLE_RATE as f32;
+            samples.push(if on {
+                0.15 * ((std::f32::consts::TAU * 440.0 * t).sin()
+                    + (std::f32::consts::TAU * 480.0 * t).sin())
+            } else {
+                0.0
+            });
+        }
+    }
+    SamplesBuffer::new(1, SAMPLE_RATE, samples)
+}
+
+fn open_output(device: Option<Device>) -> Result<Output, String> {
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let (close_tx, close_rx) = mpsc::channel::<()>();
+
+    std::thread::spawn(move || {
+        let stream = match device {
+            Some(device) => {
+                OutputStreamBuilder::from_device(device).and_then(|builder| builder.open_stream())
+            }
+            None => OutputStreamBuilder::open_default_stream(),
+        };
+        match stream {
+            Ok(mut stream) => {
+                stream.log_on_drop(false);
+                let _ = ready_tx.send(Ok(stream.mixer().clone()));
+                // Blocks until the Output is dropped
+                let _ = close_rx.recv();
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e.to_string()));
+            }
+        }
+    });
+
+    let mixer = ready_rx.recv().map_err(|e| e.to_string())??;
+    Ok(Output {
+        mixer,
+        _close: close_tx,
+    })
+}
+
+// Opens the audio device on first use rather than at startup
+fn mixer(app: &AppHandle, state: &SoundState) -> Result<Mixer, String> {
+    let mut output = state.output.lock().unwrap();
+    if output.is_none() {
+        *output = Some(open_output(crate::audio_devices::output_device(app))?);
+    }
+    Ok(output.as_ref().unwrap().mixer.clone())
+}
+
+// Closes the current device; the next sound opens whichever is selected now
+pub fn reset_output(app: &AppHandle) {
+    let state = app.state::<SoundState>();
+    state.playing.lock().unwrap().clear();
+    state.looping.lock().unwrap().clear();
+    *state.output.lock().unwrap() = None;
+}
+
+fn effective_volume(state: &SoundState) -> f32 {
+    let volume = *state.volume.lock().unwrap();
+    if *state.muted.lock().unwrap() {
+        0.0
+    } else if *state.ducked.lock().unwrap() {
+        volume * DUCKED_VOLUME
+    } else {
+        volume
+    }
+}
+
+fn apply_volume(state: &SoundState) {
+    let volume = effective_volume(state);
+    for sink in state.playing.lock().unwrap().iter() {
+        sink.set_volume(volume);
+    }
+    for sink in state.looping.lock().unwrap().values() {
+        sink.set_volume(volume);
+    }
+}
+
+// Keeps sounds quiet enough not to talk over a call
+pub fn set_ducked(app: &AppHandle, ducked: bool) {
+    let state = app.state::<SoundState>();
+    *state.ducked.lock().unwrap() = ducked;
+    apply_volume(&state);
+}
+
+pub fn is_muted(app: &AppHandle) -> bool {
+    *app.state::<SoundState>().muted.lock().unwrap()
+}
+
+// Loops keep running silently, so a ringtone picks back up on unmute
+pub fn set_muted(app: &AppHandle, muted: bool) -> Result<(), String> {
+    let mut settings = crate::settings::load(app)?;
+    settings.sounds_muted = muted;
+    crate::settings::save(app, &settings)?;
+
+    let state = app.state::<SoundState>();
+    *state.muted.lock().unwrap() = muted;
+    apply_volume(&state);
+
+    let _ = app.emit("sounds-muted-changed", muted);
+    crate::refresh_tray(app).map_err(|e| e.to_string())
+}
+
+// Drops decoded sounds so the next play picks up a new scheme
+pub fn clear_cache(app: &AppHandle) {
+    app.state::<SoundState>().sounds.lock().unwrap().clear();
+}
+
+fn cached_sound(app: &AppHandle, state: &SoundState, id: SoundId) -> Result<SamplesBuffer, String> {
+    let mut sounds = state.sounds.lock().unwrap();
+    match sounds.get(&id) {
+        Some(sound) => Ok(sound.clone()),
+        None => {
+            let sound = load_sound(app, id)?;
+            sounds.insert(id, sound.clone());
+            Ok(sound)
+        }
+    }
+}
+
+fn new_sink(app: &AppHandle, state: &SoundState) -> Result<Sink, String> {
+    let sink = Sink::connect_new(&mixer(app, state)?);
+    sink.set_volume(effective_volume(state));
+    Ok(sink)
+}
+
+pub fn play(app: &AppHandle, id: SoundId) -> Result<(), String> {
+    let state = app.state::<SoundState>();
+    if *state.muted.lock().unwrap() {
+        return Ok(());
+    }
+    let sink = new_sink(app, &state)?;
+    sink.append(cached_sound(app, &state, id)?);
+
+    let mut playing = state.playing.lock().unwrap();
+    playing.retain(|sink| !sink.empty());
+    playing.push(sink);
+    Ok(())
+}
+
+// Plays recorded audio at full volume, since it's the devices being tested
+// rather than the sounds setting, and blocks until it's done
+pub fn play_to_end(app: &AppHandle, buffer: SamplesBuffer) -> Result<(), String> {
+    let state = app.state::<SoundState>();
+    let sink = Sink::connect_new(&mixer(app, &state)?);
+    sink.append(buffer);
+    sink.sleep_until_end();
+    Ok(())
+}
+
+// Repeats `id` until `stop_loop` is called with the same key, replacing
+// whatever was looping under it
+pub fn start_loop(app: &AppHandle, key: &str, id: SoundId) -> Result<(), String> {
+    let state = app.state::<SoundState>();
+    let sink = new_sink(app, &state)?;
+    sink.append(cached_sound(app, &state, id)?.repeat_infinite());
+
+    if let Some(previous) = state.looping.lock().unwrap().insert(key.to_string(), sink) {
+        previous.stop();
+    }
+    Ok(())
+}
+
+pub fn stop_loop(app: &AppHandle, key: &str) {
+    if let Some(sink) = app
+        .state::<SoundState>()
+        .looping
+        .lock()
+        .unwrap()
+        .remove(key)
+    {
+        sink.stop();
+    }
+}
+
+#[tauri::command]
+pub async fn play_sound(app_handle: AppHandle, id: SoundId) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || play(&app_handle, id))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// 0.0 (silent) to 1.0 (full); also applies to sounds already playing
+#[tauri::command]
+pub async fn set_sound_volume(app_handle: AppHandle, volume: f32) -> Result<(), String> {
+    let volume = volume.clamp(0.0, 1.0);
+
+    let mut settings = crate::settings::load(&app_handle)?;
+    settings.sound_volume = Some(volume);
+    crate::settings::save(&app_handle, &settings)?;
+
+    let state = app_handle.state::<SoundState>();
+    *state.volume.lock().unwrap() = volume;
+    apply_volume(&state);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_sounds_muted(app_handle: AppHandle, muted: bool) -> Result<(), String> {
+    set_muted(&app_handle, muted)
+}
+
+#[tauri::command]
+pub async fn stop_all_sounds(app_handle: AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<SoundState>();
+    for sink in state.playing.lock().unwrap().drain(..) {
+        sink.stop();
+    }
+    for (_, sink) in state.looping.lock().unwrap().drain() {
+        sink.stop();
+    }
+    Ok(())
+}
@@ -0,0 +1,279 @@
+// Installable sound schemes, e.g. the original MSN or WLM sets. A scheme is a
+// zip holding `scheme.json` plus the audio files it names:
+// { "name": "MSN 7.5", "sounds": { "new_message": "type.wav", "nudge": "nudge.wav" } }
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::sound::{self, SoundId};
+
+const MANIFEST: &str = "scheme.json";
+const MAX_MANIFEST_BYTES: u64 = 64 * 1024;
+const MAX_ARCHIVE_BYTES: u64 = 50 * 1024 * 1024;
+const MAX_SOUND_BYTES: u64 = 5 * 1024 * 1024;
+const MAX_NAME_LENGTH: usize = 64;
+// What the bundled decoders can play
+const SOUND_EXTENSIONS: [&str; 3] = ["wav", "mp3", "ogg"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Manifest {
+    name: String,
+    // Events without a file keep the built-in sound
+    sounds: HashMap<SoundId, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SoundScheme {
+    id: String,
+    name: String,
+    sounds: Vec<SoundId>,
+    active: bool,
+}
+
+pub fn schemes_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("sound-schemes"))
+}
+
+// Ids are generated on import, so anything else can't be a scheme of ours
+fn scheme_dir(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    uuid::Uuid::parse_str(id).map_err(|_| format!("Unknown sound scheme: {}", id))?;
+    Ok(schemes_dir(app)?.join(id))
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest, String> {
+    let file = std::fs::File::open(dir.join(MANIFEST)).map_err(|e| e.to_string())?;
+    serde_json::from_reader(file).map_err(|e| e.to_string())
+}
+
+fn active_id(app: &AppHandle) -> Option<String> {
+    crate::settings::load(app).ok()?.sound_scheme
+}
+
+// The active scheme's file for `id`, if it replaces the built-in sound
+pub fn active_sound(app: &AppHandle, id: SoundId) -> Option<PathBuf> {
+    let dir = scheme_dir(app, &active_id(app)?).ok()?;
+    let file = read_manifest(&dir).ok()?.sounds.remove(&id)?;
+    Some(dir.join(file))
+}
+
+// Only plain file names with a playable extension; no paths into or out of
+// the scheme's folder
+fn validate_file_name(name: &str) -> Result<(), String> {
+    let path = Path::new(name);
+    if path.file_name().and_then(|file| file.to_str()) != Some(name) {
+        return Err(format!("Invalid sound file name: {}", name));
+    }
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    if !extension.is_some_and(|extension| SOUND_EXTENSIONS.contains(&extension.as_str())) {
+        return Err(format!("{} must be a WAV, MP3 or Ogg Vorbis file", name));
+    }
+    Ok(())
+}
+
+// Reads one entry, trusting neither its declared size nor its contents
+fn read_entry(
+    archive: &mut ZipArchive<std::fs::File>,
+    name: &str,
+    limit: u64,
+) -> Result<Vec<u8>, String> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|_| format!("The scheme is missing {}", name))?;
+    if entry.size() > limit {
+        return Err(format!("{} is larger than {} KB", name, limit / 1024));
+    }
+
+    let mut bytes = Vec::new();
+    entry
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > limit {
+        return Err(format!("{} is larger than {} KB", name, limit / 1024));
+    }
+    Ok(bytes)
+}
+
+fn install(archive_path: &Path, schemes: &Path) -> Result<(String, Manifest), String> {
+    let file = std::fs::File::open(archive_path).map_err(|e| e.to_string())?;
+    if file.metadata().map_err(|e| e.to_string())?.len() > MAX_ARCHIVE_BYTES {
+        return Err(format!(
+            "Sound schemes can be at most {} MB",
+            MAX_ARCHIVE_BYTES / 1024 / 1024
+        ));
+    }
+    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+
+    let manifest = read_entry(&mut archive, MANIFEST, MAX_MANIFEST_BYTES)?;
+    let mut manifest: Manifest = serde_json::from_slice(&manifest).map_err(|e| e.to_string())?;
+    manifest.name = manifest.name.trim().chars().take(MAX_NAME_LENGTH).collect();
+    if manifest.name.is_empty() {
+        return Err("The scheme has no name".to_string());
+    }
+    if manifest.sounds.is_empty() {
+        return Err("The scheme has no sounds".to_string());
+    }
+
+    let mut sounds = Vec::new();
+    for name in manifest.sounds.values() {
+        validate_file_name(name)?;
+        let bytes = read_entry(&mut archive, name, MAX_SOUND_BYTES)?;
+        sound::decode(bytes.clone()).map_err(|_| format!("{} could not be played", name))?;
+        sounds.push((name.clone(), bytes));
+    }
+
+    // Unpacked next to the others and renamed into place once complete
+    let id = uuid::Uuid::new_v4().to_string();
+    let partial = schemes.join(format!("{}.part", id));
+    let result = (|| {
+        std::fs::create_dir_all(&partial).map_err(|e| e.to_string())?;
+        for (name, bytes) in &sounds {
+            std::fs::write(partial.join(name), bytes).map_err(|e| e.to_string())?;
+        }
+        let manifest_file =
+            std::fs::File::create(partial.join(MANIFEST)).map_err(|e| e.to_string())?;
+        serde_json::to_writer_pretty(manifest_file, &manifest).map_err(|e| e.to_string())?;
+        std::fs::rename(&partial, schemes.join(&id)).map_err(|e| e.to_string())
+    })();
+    if let Err(error) = result {
+        let _ = std::fs::remove_dir_all(&partial);
+        return Err(error);
+    }
+
+    Ok((id, manifest))
+}
+
+fn write_archive(dir: &Path, out: &Path) -> Result<(), String> {
+    let manifest = read_manifest(dir)?;
+
+    let mut zip = ZipWriter::new(std::fs::File::create(out).map_err(|e| e.to_string())?);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file(MANIFEST, deflated)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|e| e.to_string())?;
+
+    let mut names: Vec<&String> = manifest.sounds.values().collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let bytes = std::fs::read(dir.join(name)).map_err(|e| e.to_string())?;
+        zip.start_file(name.as_str(), deflated)
+            .map_err(|e| e.to_string())?;
+        zip.write_all(&bytes).map_err(|e| e.to_string())?;
+    }
+
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn describe(id: String, manifest: Manifest, active: &Option<String>) -> SoundScheme {
+    let mut sounds: Vec<SoundId> = manifest.sounds.into_keys().collect();
+    sounds.sort();
+    SoundScheme {
+        active: active.as_ref() == Some(&id),
+        id,
+        name: manifest.name,
+        sounds,
+    }
+}
+
+#[tauri::command]
+pub async fn list_sound_schemes(app_handle: AppHandle) -> Result<Vec<SoundScheme>, String> {
+    let active = active_id(&app_handle);
+    let Ok(entries) = std::fs::read_dir(schemes_dir(&app_handle)?) else {
+        return Ok(Vec::new());
+    };
+
+    let mut schemes: Vec<SoundScheme> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry.file_name().to_str()?.to_string();
+            uuid::Uuid::parse_str(&id).ok()?;
+            let manifest = read_manifest(&entry.path()).ok()?;
+            Some(describe(id, manifest, &active))
+        })
+        .collect();
+    schemes.sort_by_key(|scheme| scheme.name.to_lowercase());
+    Ok(schemes)
+}
+
+// Installs the scheme without switching to it
+#[tauri::command]
+pub async fn import_sound_scheme(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<SoundScheme, String> {
+    let schemes = schemes_dir(&app_handle)?;
+    std::fs::create_dir_all(&schemes).map_err(|e| e.to_string())?;
+
+    let (id, manifest) =
+        tauri::async_runtime::spawn_blocking(move || install(Path::new(&path), &schemes))
+            .await
+            .map_err(|e| e.to_string())??;
+    Ok(describe(id, manifest, &active_id(&app_handle)))
+}
+
+#[tauri::command]
+pub async fn export_sound_scheme(
+    app_handle: AppHandle,
+    scheme_id: String,
+    path: String,
+) -> Result<(), String> {
+    let dir = scheme_dir(&app_handle, &scheme_id)?;
+    let path = PathBuf::from(path);
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    let out = partial.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || write_archive(&dir, &out))
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Err(error) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(error);
+    }
+    std::fs::rename(&partial, &path).map_err(|e| e.to_string())
+}
+
+// `None` goes back to the built-in sounds
+#[tauri::command]
+pub async fn set_sound_scheme(
+    app_handle: AppHandle,
+    scheme_id: Option<String>,
+) -> Result<(), String> {
+    if let Some(id) = &scheme_id {
+        read_manifest(&scheme_dir(&app_handle, id)?)
+            .map_err(|_| format!("Unknown sound scheme: {}", id))?;
+    }
+
+    let mut settings = crate::settings::load(&app_handle)?;
+    settings.sound_scheme = scheme_id;
+    crate::settings::save(&app_handle, &settings)?;
+
+    sound::clear_cache(&app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn remove_sound_scheme(app_handle: AppHandle, scheme_id: String) -> Result<(), String> {
+    let dir = scheme_dir(&app_handle, &scheme_id)?;
+
+    if active_id(&app_handle).as_ref() == Some(&scheme_id) {
+        set_sound_scheme(app_handle.clone(), None).await?;
+    }
+    std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())
+}
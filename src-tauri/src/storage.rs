@@ -0,0 +1,148 @@
+// Encryption at rest for the JSON stores and the message cache. The key lives
+// in the OS credential store, so the files alone are unreadable.
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+
+use crate::secrets;
+
+const KEY_SECRET: &str = "storage-key";
+// Starts every encrypted file or value; anything without it predates encryption
+pub const MAGIC: &[u8] = b"BMSNENC1";
+const NONCE_LENGTH: usize = 12;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// Created on first run. `None` while the credential store can't be reached
+// (e.g. Linux with no Secret Service running, or a keychain prompt that was
+// denied), in which case data is written as plaintext rather than lost. Only
+// a key that loaded is kept, so a later call tries the credential store again.
+fn cipher() -> Option<&'static Aes256Gcm> {
+    static CIPHER: OnceLock<Aes256Gcm> = OnceLock::new();
+    // Keeps two first calls from each generating a key
+    static LOADING: Mutex<()> = Mutex::new(());
+    if let Some(cipher) = CIPHER.get() {
+        return Some(cipher);
+    }
+    let _loading = LOADING.lock().unwrap();
+    if let Some(cipher) = CIPHER.get() {
+        return Some(cipher);
+    }
+    let cipher = load_cipher()?;
+    Some(CIPHER.get_or_init(|| cipher))
+}
+
+fn load_cipher() -> Option<Aes256Gcm> {
+    let key = match secrets::get(KEY_SECRET) {
+        Ok(Some(encoded)) => base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .ok()?,
+        // Only when the credential store answered that there's no key yet;
+        // a new one would leave existing files unreadable
+        Ok(None) => {
+            let key = Aes256Gcm::generate_key(OsRng);
+            secrets::set(
+                KEY_SECRET,
+                &base64::engine::general_purpose::STANDARD.encode(key),
+            )
+            .ok()?;
+            key.to_vec()
+        }
+        Err(_) => return None,
+    };
+    Aes256Gcm::new_from_slice(&key).ok()
+}
+
+pub fn is_available() -> bool {
+    cipher().is_some()
+}
+
+fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn encrypt(plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let Some(cipher) = cipher() else {
+        return Ok(plaintext.to_vec());
+    };
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| "Could not encrypt data".to_string())?;
+    Ok([MAGIC, nonce.as_slice(), &ciphertext].concat())
+}
+
+// Plaintext passes through, so data written before encryption still loads
+pub fn decrypt(data: &[u8]) -> Result<Vec<u8>, String> {
+    let Some(sealed) = data.strip_prefix(MAGIC) else {
+        return Ok(data.to_vec());
+    };
+    let cipher = cipher().ok_or("The storage key is unavailable")?;
+    if sealed.len() < NONCE_LENGTH {
+        return Err("Encrypted data is truncated".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Could not decrypt stored data".to_string())
+}
+
+// Store plugin hooks, covering the frontend's stores (drafts and the like)
+// as well as ours
+pub fn serialize_store(cache: &HashMap<String, JsonValue>) -> Result<Vec<u8>, BoxError> {
+    Ok(encrypt(&serde_json::to_vec_pretty(cache)?)?)
+}
+
+pub fn deserialize_store(bytes: &[u8]) -> Result<HashMap<String, JsonValue>, BoxError> {
+    Ok(serde_json::from_slice(&decrypt(bytes)?)?)
+}
+
+// Rewrites stores saved before encryption, rather than waiting for each to
+// be saved again. Covers the app data folder and each account's folder
+// under it. Run before any store is opened.
+pub fn encrypt_plaintext_stores(app: &AppHandle) -> Result<(), String> {
+    if !is_available() {
+        return Ok(());
+    }
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    encrypt_plaintext_stores_in(&dir)?;
+
+    let accounts = dir.join(crate::accounts::ACCOUNTS_DIR);
+    for account in std::fs::read_dir(&accounts).into_iter().flatten().flatten() {
+        encrypt_plaintext_stores_in(&account.path())?;
+    }
+
+    Ok(())
+}
+
+fn encrypt_plaintext_stores_in(dir: &Path) -> Result<(), String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(());
+    };
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+            continue;
+        }
+        let Ok(bytes) = std::fs::read(&path) else {
+            continue;
+        };
+        // Only files the store plugin could have written
+        if is_encrypted(&bytes)
+            || serde_json::from_slice::<HashMap<String, JsonValue>>(&bytes).is_err()
+        {
+            continue;
+        }
+
+        let partial = path.with_extension("json.part");
+        std::fs::write(&partial, encrypt(&bytes)?).map_err(|e| e.to_string())?;
+        std::fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
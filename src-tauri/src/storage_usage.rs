@@ -0,0 +1,137 @@
+// How much disk space the app's caches take, for the settings page, and
+// clearing them
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::{avatars, downloads, file_transfer, message_cache, message_log};
+
+// Folders in the app cache holding thumbnails and capture source previews
+const THUMBNAIL_DIRS: [&str; 2] = ["thumbnails", "capture-sources"];
+// Folders in the app cache holding screenshots and resized images made for
+// sending. Transfers also counts the partial files of downloads and receives
+// that aren't running.
+const TRANSFER_DIRS: [&str; 2] = ["screenshots", "optimized"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheCategory {
+    MessageCache,
+    Avatars,
+    Thumbnails,
+    Transfers,
+    Logs,
+}
+
+// Sizes in bytes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageUsage {
+    message_cache: u64,
+    avatars: u64,
+    thumbnails: u64,
+    transfers: u64,
+    logs: u64,
+    total: u64,
+}
+
+fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(if metadata.is_dir() {
+                directory_size(&entry.path())
+            } else {
+                metadata.len()
+            })
+        })
+        .sum()
+}
+
+fn cache_dirs(app: &AppHandle, names: &[&str]) -> Result<Vec<PathBuf>, String> {
+    let cache = app.path().app_cache_dir().map_err(|e| e.to_string())?;
+    Ok(names.iter().map(|name| cache.join(name)).collect())
+}
+
+fn remove_dirs(dirs: &[PathBuf]) -> Result<(), String> {
+    for dir in dirs {
+        match std::fs::remove_dir_all(dir) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                return Err(error.to_string())
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn stale_partials_size(app: &AppHandle) -> u64 {
+    downloads::stale_partials(app)
+        .into_iter()
+        .chain(file_transfer::stale_partials(app))
+        .filter_map(|partial| std::fs::metadata(partial).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+fn usage(app: &AppHandle) -> Result<StorageUsage, String> {
+    let mut usage = StorageUsage {
+        message_cache: message_cache::size(app)?,
+        avatars: directory_size(&avatars::avatars_dir(app)?),
+        thumbnails: cache_dirs(app, &THUMBNAIL_DIRS)?
+            .iter()
+            .map(|dir| directory_size(dir))
+            .sum(),
+        transfers: cache_dirs(app, &TRANSFER_DIRS)?
+            .iter()
+            .map(|dir| directory_size(dir))
+            .sum::<u64>()
+            + stale_partials_size(app),
+        logs: directory_size(&message_log::logs_dir(app)?),
+        total: 0,
+    };
+    usage.total =
+        usage.message_cache + usage.avatars + usage.thumbnails + usage.transfers + usage.logs;
+    Ok(usage)
+}
+
+// Message cache and logs are the active account's
+#[tauri::command]
+pub async fn get_storage_usage(app_handle: AppHandle) -> Result<StorageUsage, String> {
+    tauri::async_runtime::spawn_blocking(move || usage(&app_handle))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// Returns the usage afterwards. Screenshots and resized images still waiting
+// to be sent have to be picked again, and paused or interrupted downloads and
+// receives are cancelled.
+#[tauri::command]
+pub async fn clear_cache(
+    app_handle: AppHandle,
+    categories: Vec<CacheCategory>,
+) -> Result<StorageUsage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        for category in categories {
+            match category {
+                CacheCategory::MessageCache => message_cache::clear(&app_handle)?,
+                CacheCategory::Avatars => avatars::clear(&app_handle)?,
+                CacheCategory::Thumbnails => {
+                    remove_dirs(&cache_dirs(&app_handle, &THUMBNAIL_DIRS)?)?
+                }
+                CacheCategory::Transfers => {
+                    remove_dirs(&cache_dirs(&app_handle, &TRANSFER_DIRS)?)?;
+                    downloads::discard_stale_partials(&app_handle);
+                    file_transfer::discard_stale_partials(&app_handle);
+                }
+                CacheCategory::Logs => message_log::delete_logs(&app_handle)?,
+            }
+        }
+        usage(&app_handle)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,191 @@
+// Keeps a damaged store from wedging the app. Saves first copy the file as it
+// was to `<file>.1.bak`, shifting older copies up to KEPT_BACKUPS, at most
+// once per BACKUP_INTERVAL. A store that no longer reads is put back from the
+// newest copy that does, and "store-recovered" is emitted with whether a copy
+// was found. Encrypted stores are left alone while the storage key can't be
+// fetched, and reported with "store-unreadable".
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_store::{resolve_store_path, Store, StoreBuilder, StoreExt};
+
+use crate::migrations::StoreContents;
+use crate::storage;
+
+const KEPT_BACKUPS: usize = 3;
+// Stores saved on every change would otherwise cycle out their older copies
+// within seconds
+const BACKUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+fn backup_path(file: &Path, index: usize) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(format!(".{}.bak", index));
+    PathBuf::from(path)
+}
+
+fn read_file(file: &Path) -> Option<StoreContents> {
+    let bytes = std::fs::read(file).ok()?;
+    storage::deserialize_store(&bytes).ok()
+}
+
+fn backed_up_recently(file: &Path) -> bool {
+    std::fs::metadata(backup_path(file, 1))
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| {
+            SystemTime::now()
+                .duration_since(modified)
+                .is_ok_and(|age| age < BACKUP_INTERVAL)
+        })
+}
+
+// Best effort; a failed copy shouldn't stop the save itself
+fn rotate_backups(file: &Path) {
+    if backed_up_recently(file) {
+        return;
+    }
+    let Ok(bytes) = std::fs::read(file) else {
+        return;
+    };
+    // A damaged file would push out a good copy
+    if storage::deserialize_store(&bytes).is_err() {
+        return;
+    }
+    for index in (1..KEPT_BACKUPS).rev() {
+        let _ = std::fs::rename(backup_path(file, index), backup_path(file, index + 1));
+    }
+    let _ = std::fs::write(backup_path(file, 1), bytes);
+}
+
+// Puts back the newest copy that reads. Without one, the damaged file is set
+// aside as `<file>.corrupt` so the store starts over.
+fn restore(app: &AppHandle, file: &Path) -> Result<bool, String> {
+    let backup = (1..=KEPT_BACKUPS)
+        .map(|index| backup_path(file, index))
+        .find(|backup| read_file(backup).is_some());
+
+    let restored = match &backup {
+        Some(backup) => {
+            std::fs::copy(backup, file).map_err(|e| e.to_string())?;
+            true
+        }
+        None => {
+            let mut corrupt = file.as_os_str().to_owned();
+            corrupt.push(".corrupt");
+            std::fs::rename(file, PathBuf::from(corrupt)).map_err(|e| e.to_string())?;
+            false
+        }
+    };
+
+    let _ = app.emit(
+        "store-recovered",
+        json!({ "file": display_name(app, file), "restored": restored }),
+    );
+    Ok(restored)
+}
+
+// Relative to the app data folder, for events
+fn display_name(app: &AppHandle, file: &Path) -> PathBuf {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .and_then(|dir| file.strip_prefix(dir).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| file.to_path_buf())
+}
+
+// Encrypted, but the key can't be fetched right now, e.g. on a launch at
+// login before the keychain is unlocked. That isn't damage, and neither the
+// file nor its backups would read, so nothing may be restored or set aside.
+fn is_locked(file: &Path) -> bool {
+    !storage::is_available()
+        && std::fs::read(file).is_ok_and(|bytes| bytes.starts_with(storage::MAGIC))
+}
+
+fn check_file(app: &AppHandle, file: &Path) -> Result<(), String> {
+    if !file.exists() || read_file(file).is_some() {
+        return Ok(());
+    }
+    if is_locked(file) {
+        let name = display_name(app, file);
+        let _ = app.emit("store-unreadable", json!({ "file": name }));
+        return Err(format!(
+            "{} can't be read until the storage key is available",
+            name.display()
+        ));
+    }
+    restore(app, file)?;
+    Ok(())
+}
+
+// Checks every store in the app data folder and the active account's folder.
+// Runs before migrations, which would otherwise give up on a damaged file.
+pub fn recover_stores(app: &AppHandle) -> Result<(), String> {
+    let mut dirs = vec![app.path().app_data_dir().map_err(|e| e.to_string())?];
+    let account = crate::accounts::data_dir(app)?;
+    if !dirs.contains(&account) {
+        dirs.push(account);
+    }
+
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|extension| extension.to_str()) == Some("json") {
+                // Locked stores were reported; the rest still get checked
+                if let Err(error) = check_file(app, &path) {
+                    if !is_locked(&path) {
+                        return Err(error);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// For stores opened with their own builder options; call before building
+pub fn check(app: &AppHandle, path: impl AsRef<Path>) -> Result<(), String> {
+    if app.get_store(path.as_ref()).is_some() {
+        return Ok(());
+    }
+    check_file(
+        app,
+        &resolve_store_path(app, path).map_err(|e| e.to_string())?,
+    )
+}
+
+// StoreBuilder::new(..).build(), but a damaged file is recovered first rather
+// than silently loading as an empty store
+pub fn open(app: &AppHandle, path: impl AsRef<Path>) -> Result<Arc<Store<Wry>>, String> {
+    check(app, path.as_ref())?;
+    StoreBuilder::new(app, path.as_ref())
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+// Saves after keeping a copy of what's on disk
+pub fn save(app: &AppHandle, path: impl AsRef<Path>, store: &Store<Wry>) -> Result<(), String> {
+    rotate_backups(&resolve_store_path(app, path).map_err(|e| e.to_string())?);
+    store.save().map_err(|e| e.to_string())
+}
+
+// Reads one value. A damaged file was already recovered when it was opened,
+// so a value that doesn't fit `T` is an error rather than a reason to go
+// back to an older copy.
+pub fn read<T: DeserializeOwned>(
+    app: &AppHandle,
+    path: impl AsRef<Path>,
+    key: &str,
+) -> Result<Option<T>, String> {
+    let path = path.as_ref();
+    let store = open(app, path)?;
+    store
+        .get(key)
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| format!("Can't read {} from {}: {}", key, path.display(), e))
+}
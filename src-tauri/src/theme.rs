@@ -0,0 +1,338 @@
+// OS theme (dark/light) and accent color detection. The OS is only asked
+// again when it reports a change.
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{image::Image, AppHandle, Emitter};
+
+// Last detected theme, compared against to tell real changes apart
+static LAST_THEME: Mutex<Option<SystemTheme>> = Mutex::new(None);
+// Whether the taskbar/panel holding the tray icon is dark, which on Windows
+// can differ from the apps' theme
+static DARK_PANEL: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SystemTheme {
+    theme: ThemeMode,
+    accent_color: Option<String>, // "#rrggbb"
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn detect_theme() -> SystemTheme {
+    // AppleInterfaceStyle is only set when dark mode is on
+    let dark = command_output("defaults", &["read", "-g", "AppleInterfaceStyle"])
+        .map(|style| style == "Dark")
+        .unwrap_or(false);
+
+    // AppleAccentColor is absent for the default (multicolor/blue) accent
+    let accent = command_output("defaults", &["read", "-g", "AppleAccentColor"])
+        .and_then(|value| value.parse::<i32>().ok());
+    let accent_color = match accent {
+        Some(-1) => "#8e8e93",
+        Some(0) => "#ff3b30",
+        Some(1) => "#ff9500",
+        Some(2) => "#ffcc00",
+        Some(3) => "#28cd41",
+        Some(5) => "#af52de",
+        Some(6) => "#ff2d55",
+        _ => "#007aff",
+    };
+
+    SystemTheme {
+        theme: if dark {
+            ThemeMode::Dark
+        } else {
+            ThemeMode::Light
+        },
+        accent_color: Some(accent_color.to_string()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn detect_theme() -> SystemTheme {
+    let gsettings = |key: &str| {
+        command_output("gsettings", &["get", "org.gnome.desktop.interface", key])
+            .map(|value| value.trim_matches('\'').to_string())
+    };
+
+    let dark = gsettings("color-scheme")
+        .map(|scheme| scheme == "prefer-dark")
+        .unwrap_or(false)
+        || gsettings("gtk-theme")
+            .or_else(|| std::env::var("GTK_THEME").ok())
+            .map(|theme| theme.to_lowercase().contains("dark"))
+            .unwrap_or(false);
+
+    // GNOME 47+ named accents, using the libadwaita palette
+    let accent_color = gsettings("accent-color").and_then(|name| {
+        let hex = match name.as_str() {
+            "blue" => "#3584e4",
+            "teal" => "#2190a4",
+            "green" => "#3a944a",
+            "yellow" => "#c88800",
+            "orange" => "#ed5b00",
+            "red" => "#e62d42",
+            "pink" => "#d56199",
+            "purple" => "#9141ac",
+            "slate" => "#6f8396",
+            _ => return None,
+        };
+        Some(hex.to_string())
+    });
+
+    SystemTheme {
+        theme: if dark {
+            ThemeMode::Dark
+        } else {
+            ThemeMode::Light
+        },
+        accent_color,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn detect_theme() -> SystemTheme {
+    use windows_sys::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+
+    fn read_dword(subkey: *const u16, value: *const u16) -> Option<u32> {
+        let mut data: u32 = 0;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_CURRENT_USER,
+                subkey,
+                value,
+                RRF_RT_REG_DWORD,
+                std::ptr::null_mut(),
+                &mut data as *mut u32 as *mut _,
+                &mut size,
+            )
+        };
+        (status == 0).then_some(data)
+    }
+
+    let apps_use_light_theme = read_dword(
+        windows_sys::w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"),
+        windows_sys::w!("AppsUseLightTheme"),
+    );
+    // The taskbar follows the system theme, not the apps' one
+    let system_uses_light_theme = read_dword(
+        windows_sys::w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"),
+        windows_sys::w!("SystemUsesLightTheme"),
+    );
+    DARK_PANEL.store(system_uses_light_theme == Some(0), Ordering::Relaxed);
+
+    // Stored as 0xAABBGGRR
+    let accent_color = read_dword(
+        windows_sys::w!("Software\\Microsoft\\Windows\\DWM"),
+        windows_sys::w!("AccentColor"),
+    )
+    .map(|abgr| {
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            abgr & 0xff,
+            (abgr >> 8) & 0xff,
+            (abgr >> 16) & 0xff
+        )
+    });
+
+    SystemTheme {
+        theme: if apps_use_light_theme == Some(0) {
+            ThemeMode::Dark
+        } else {
+            ThemeMode::Light
+        },
+        accent_color,
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn detect_theme() -> SystemTheme {
+    SystemTheme {
+        theme: ThemeMode::Light,
+        accent_color: None,
+    }
+}
+
+// A dot with a bar through it in the bottom-right corner, with a transparent
+// ring around it to set it apart from the icon
+fn mute_badge_alpha(x: u32, y: u32, width: u32, height: u32) -> Option<u8> {
+    let radius = width.min(height) as f32 * 0.28;
+    let dx = x as f32 - width as f32 * 0.72;
+    let dy = y as f32 - height as f32 * 0.72;
+    let distance = (dx * dx + dy * dy).sqrt();
+    if distance > radius * 1.2 {
+        return None;
+    }
+    let in_bar = dy.abs() < radius * 0.25 && dx.abs() < radius * 0.6;
+    Some(if distance <= radius && !in_bar {
+        0xff
+    } else {
+        0
+    })
+}
+
+// Tray icon for the current panel color, badged while muted in a call. On
+// macOS it's a monochrome template image that the menu bar recolors itself;
+// elsewhere the app icon keeps its colors and only the badge follows the
+// panel, like the system's own tray glyphs.
+pub fn refresh_tray_icon(app: &AppHandle) {
+    let (Some(tray), Some(icon)) = (app.tray_by_id("main-tray"), app.default_window_icon()) else {
+        return;
+    };
+
+    let template = cfg!(target_os = "macos");
+    let shade = if DARK_PANEL.load(Ordering::Relaxed) {
+        0xff
+    } else {
+        0x00
+    };
+    let muted = crate::call_audio::is_call_muted(app);
+    let (width, height) = (icon.width(), icon.height());
+    let rgba: Vec<u8> = icon
+        .rgba()
+        .chunks_exact(4)
+        .enumerate()
+        .flat_map(|(index, pixel)| {
+            let (x, y) = (index as u32 % width, index as u32 / width);
+            let badge = muted
+                .then(|| mute_badge_alpha(x, y, width, height))
+                .flatten();
+            match badge {
+                Some(alpha) => [shade, shade, shade, alpha],
+                None if template => [shade, shade, shade, pixel[3]],
+                None => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            }
+        })
+        .collect();
+
+    let _ = tray.set_icon(Some(Image::new_owned(rgba, width, height)));
+    let _ = tray.set_icon_as_template(template);
+}
+
+#[tauri::command]
+pub async fn get_system_theme() -> Result<SystemTheme, String> {
+    tauri::async_runtime::spawn_blocking(detect_theme)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Detects the theme again and, if it changed, redraws the tray icon and emits
+// `system-theme-changed`. Called whenever the OS or a window reports a
+// change, e.g. on `WindowEvent::ThemeChanged`.
+pub fn check_system_theme(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let Ok(theme) = tauri::async_runtime::spawn_blocking(detect_theme).await else {
+            return;
+        };
+        #[cfg(not(target_os = "windows"))]
+        DARK_PANEL.store(theme.theme == ThemeMode::Dark, Ordering::Relaxed);
+
+        let previous = LAST_THEME.lock().unwrap().replace(theme.clone());
+        // The Windows taskbar can change on its own, so the icon is always redrawn
+        refresh_tray_icon(&app);
+        if previous.is_some_and(|previous| previous != theme) {
+            let _ = app.emit("system-theme-changed", &theme);
+        }
+    });
+}
+
+// The window theme event misses accent color changes, so those come from
+// the OS's own change notifications
+#[cfg(target_os = "macos")]
+fn observe_os_changes(app: &AppHandle) {
+    use block2::RcBlock;
+    use objc2::runtime::AnyObject;
+    use objc2::{class, msg_send};
+    use objc2_foundation::NSString;
+    use std::ptr::NonNull;
+
+    for name in [
+        "AppleInterfaceThemeChangedNotification",
+        "AppleColorPreferencesChangedNotification",
+    ] {
+        let app = app.clone();
+        let block = RcBlock::new(move |_notification: NonNull<AnyObject>| {
+            check_system_theme(&app);
+        });
+        // The center keeps the observer, and so the block, for the app's lifetime
+        unsafe {
+            let center: *mut AnyObject =
+                msg_send![class!(NSDistributedNotificationCenter), defaultCenter];
+            let queue: *mut AnyObject = msg_send![class!(NSOperationQueue), mainQueue];
+            let _: *mut AnyObject = msg_send![
+                center,
+                addObserverForName: &*NSString::from_str(name),
+                object: std::ptr::null_mut::<AnyObject>(),
+                queue: queue,
+                usingBlock: &*block
+            ];
+        }
+    }
+}
+
+// `gsettings monitor` prints a line whenever a key changes
+#[cfg(target_os = "linux")]
+fn observe_os_changes(app: &AppHandle) {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command, Stdio};
+
+    let Ok(mut monitor) = Command::new("gsettings")
+        .args(["monitor", "org.gnome.desktop.interface"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return;
+    };
+    let Some(stdout) = monitor.stdout.take() else {
+        return;
+    };
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let key = line.split(':').next().unwrap_or_default();
+            if matches!(key, "color-scheme" | "gtk-theme" | "accent-color") {
+                check_system_theme(&app);
+            }
+        }
+        let _ = monitor.wait();
+    });
+}
+
+#[cfg(target_os = "windows")]
+fn observe_os_changes(app: &AppHandle) {
+    let app = app.clone();
+    crate::win_events::on_theme_change(move || check_system_theme(&app));
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+fn observe_os_changes(_app: &AppHandle) {}
+
+// Sets the tray icon for the current theme and follows OS dark/light mode
+// and accent color changes from then on
+pub fn watch_system_theme(app: &AppHandle) {
+    check_system_theme(app);
+    observe_os_changes(app);
+}
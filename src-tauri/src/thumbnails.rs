@@ -0,0 +1,170 @@
+// Cached thumbnails for image and video files
+use serde::Serialize;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+const MIN_DIMENSION: u32 = 16;
+const MAX_DIMENSION: u32 = 1024;
+// Oldest thumbnails are evicted once the cache grows past this
+const CACHE_LIMIT_BYTES: u64 = 100 * 1024 * 1024;
+
+const VIDEO_EXTENSIONS: [&str; 8] = ["mp4", "m4v", "mov", "webm", "mkv", "avi", "wmv", "3gp"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Thumbnail {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+}
+
+fn is_video(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| VIDEO_EXTENSIONS.contains(&extension.as_str()))
+}
+
+// Keyed on the file's identity and contents' last change, so edits re-render
+fn cache_key(path: &Path, modified: SystemTime, size: u64, max_dim: u32) -> String {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    modified.hash(&mut hasher);
+    size.hash(&mut hasher);
+    max_dim.hash(&mut hasher);
+    format!("{:016x}.png", hasher.finish())
+}
+
+fn thumbnail_image(source: &Path, out: &Path, max_dim: u32) -> Result<(), String> {
+    image::open(source)
+        .map_err(|e| e.to_string())?
+        .thumbnail(max_dim, max_dim)
+        .save_with_format(out, image::ImageFormat::Png)
+        .map_err(|e| e.to_string())
+}
+
+// Grabs a frame a second in (past black intros) with whatever the OS offers
+fn thumbnail_video(source: &Path, out: &Path, max_dim: u32) -> Result<(), String> {
+    use std::process::Command;
+
+    let frame = out.with_extension("frame.png");
+    let scale = format!(
+        "scale='min({0},iw)':'min({0},ih)':force_original_aspect_ratio=decrease",
+        max_dim
+    );
+    let ffmpeg = Command::new("ffmpeg")
+        .args(["-v", "error", "-y", "-ss", "1", "-i"])
+        .arg(source)
+        .args(["-frames:v", "1", "-vf", &scale])
+        .arg(&frame)
+        .status();
+
+    match ffmpeg {
+        Ok(status) if status.success() && frame.exists() => {
+            return std::fs::rename(&frame, out).map_err(|e| e.to_string());
+        }
+        Ok(_) => return Err("Could not read a frame from this video".to_string()),
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.to_string()),
+        Err(_) => {}
+    }
+
+    // macOS ships Quick Look, which renders video thumbnails without ffmpeg
+    #[cfg(target_os = "macos")]
+    {
+        let directory = out.parent().ok_or("Invalid thumbnail path")?;
+        let status = Command::new("qlmanage")
+            .args(["-t", "-s", &max_dim.to_string(), "-o"])
+            .arg(directory)
+            .arg(source)
+            .output()
+            .map_err(|e| e.to_string())?;
+        let rendered = directory.join(format!(
+            "{}.png",
+            source.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        if status.status.success() && rendered.exists() {
+            return std::fs::rename(&rendered, out).map_err(|e| e.to_string());
+        }
+    }
+
+    Err("Video thumbnails need ffmpeg installed".to_string())
+}
+
+// Drops least recently used thumbnails until the cache fits its budget
+fn enforce_cache_limit(directory: &Path) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return;
+    };
+
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let used = metadata.accessed().or_else(|_| metadata.modified()).ok()?;
+            Some((used, metadata.len(), entry.path()))
+        })
+        .collect();
+
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    if total <= CACHE_LIMIT_BYTES {
+        return;
+    }
+
+    files.sort_by_key(|(used, _, _)| *used);
+    for (_, size, path) in files {
+        if total <= CACHE_LIMIT_BYTES {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            total -= size;
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_thumbnail(
+    app_handle: AppHandle,
+    path: String,
+    max_dim: u32,
+) -> Result<Thumbnail, String> {
+    let source = PathBuf::from(path)
+        .canonicalize()
+        .map_err(|e| e.to_string())?;
+    let metadata = std::fs::metadata(&source).map_err(|e| e.to_string())?;
+    if !metadata.is_file() {
+        return Err(format!("Not a file: {}", source.display()));
+    }
+    let max_dim = max_dim.clamp(MIN_DIMENSION, MAX_DIMENSION);
+
+    let directory = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join("thumbnails");
+    std::fs::create_dir_all(&directory).map_err(|e| e.to_string())?;
+
+    let modified = metadata.modified().map_err(|e| e.to_string())?;
+    let out = directory.join(cache_key(&source, modified, metadata.len(), max_dim));
+
+    if !out.exists() {
+        let target = out.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            if is_video(&source) {
+                thumbnail_video(&source, &target, max_dim)
+            } else {
+                thumbnail_image(&source, &target, max_dim)
+            }?;
+            enforce_cache_limit(target.parent().unwrap_or(&target));
+            Ok::<(), String>(())
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+    }
+
+    let (width, height) = image::image_dimensions(&out).map_err(|e| e.to_string())?;
+    Ok(Thumbnail {
+        path: out,
+        width,
+        height,
+    })
+}
@@ -0,0 +1,182 @@
+// On-device transcripts for voice messages; the audio never leaves the machine.
+// Needs the `transcription` feature (whisper.cpp) and a ggml model on disk.
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+// Whisper only takes 16 kHz mono
+#[cfg(feature = "transcription")]
+const SAMPLE_RATE: u32 = 16_000;
+const MODEL_FILE: &str = "ggml-base.bin";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptSegment {
+    start_ms: i64,
+    end_ms: i64,
+    text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Transcript {
+    text: String,
+    segments: Vec<TranscriptSegment>,
+}
+
+// The configured model, or `<app data>/models/ggml-base.bin`
+fn model_path(app: &AppHandle) -> Result<PathBuf, String> {
+    if let Some(path) = crate::settings::load(app)?.transcription_model {
+        return Ok(path);
+    }
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("models")
+        .join(MODEL_FILE))
+}
+
+// rodio covers WAV, MP3 and Vorbis; anything else, like the WebM/Opus clips
+// browsers record, goes through ffmpeg
+#[cfg(feature = "transcription")]
+fn decode_audio(path: &std::path::Path) -> Result<Vec<f32>, String> {
+    use rodio::source::UniformSourceIterator;
+
+    if let Ok(decoder) = std::fs::File::open(path)
+        .map_err(|e| e.to_string())
+        .and_then(|file| rodio::Decoder::try_from(file).map_err(|e| e.to_string()))
+    {
+        return Ok(UniformSourceIterator::new(decoder, 1, SAMPLE_RATE).collect());
+    }
+
+    let output = std::process::Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args([
+            "-f",
+            "f32le",
+            "-ac",
+            "1",
+            "-ar",
+            &SAMPLE_RATE.to_string(),
+            "-",
+        ])
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                "This audio format needs ffmpeg installed to transcribe".to_string()
+            }
+            _ => e.to_string(),
+        })?;
+    if !output.status.success() {
+        return Err("Could not read this audio file".to_string());
+    }
+
+    Ok(output
+        .stdout
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect())
+}
+
+#[cfg(feature = "transcription")]
+fn transcribe(
+    model: &std::path::Path,
+    audio: &std::path::Path,
+    language: Option<&str>,
+) -> Result<Transcript, String> {
+    use std::sync::{Arc, Mutex};
+    use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+    // Loading a model takes seconds, so the last one stays in memory
+    static LOADED: Mutex<Option<(PathBuf, Arc<WhisperContext>)>> = Mutex::new(None);
+
+    let samples = decode_audio(audio)?;
+    if samples.is_empty() {
+        return Err("The audio file is empty".to_string());
+    }
+
+    let context = {
+        let mut loaded = LOADED.lock().unwrap();
+        match loaded.as_ref() {
+            Some((path, context)) if path == model => context.clone(),
+            _ => {
+                let context = Arc::new(
+                    WhisperContext::new_with_params(model, WhisperContextParameters::default())
+                        .map_err(|e| e.to_string())?,
+                );
+                *loaded = Some((model.to_path_buf(), context.clone()));
+                context
+            }
+        }
+    };
+
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_language(Some(language.unwrap_or("auto")));
+    params.set_print_special(false);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+
+    let mut state = context.create_state().map_err(|e| e.to_string())?;
+    state.full(params, &samples).map_err(|e| e.to_string())?;
+
+    // Whisper timestamps are in hundredths of a second
+    let segments: Vec<TranscriptSegment> = state
+        .as_iter()
+        .map(|segment| TranscriptSegment {
+            start_ms: segment.start_timestamp() * 10,
+            end_ms: segment.end_timestamp() * 10,
+            text: segment
+                .to_str_lossy()
+                .map(|text| text.trim().to_string())
+                .unwrap_or_default(),
+        })
+        .filter(|segment| !segment.text.is_empty())
+        .collect();
+
+    Ok(Transcript {
+        text: segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        segments,
+    })
+}
+
+#[cfg(not(feature = "transcription"))]
+fn transcribe(
+    _model: &std::path::Path,
+    _audio: &std::path::Path,
+    _language: Option<&str>,
+) -> Result<Transcript, String> {
+    Err("This build doesn't include transcription".to_string())
+}
+
+// Whether `transcribe_audio` can work: built with the feature and a model
+// in place
+#[tauri::command]
+pub async fn transcription_available(app_handle: AppHandle) -> Result<bool, String> {
+    Ok(cfg!(feature = "transcription") && model_path(&app_handle)?.is_file())
+}
+
+// `language` is an ISO 639-1 code like "en"; `None` detects it
+#[tauri::command]
+pub async fn transcribe_audio(
+    app_handle: AppHandle,
+    path: String,
+    language: Option<String>,
+) -> Result<Transcript, String> {
+    let audio = PathBuf::from(path);
+    if !audio.is_file() {
+        return Err(format!("Not a file: {}", audio.display()));
+    }
+    let model = model_path(&app_handle)?;
+    if !model.is_file() {
+        return Err(format!("No speech model found at {}", model.display()));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || transcribe(&model, &audio, language.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
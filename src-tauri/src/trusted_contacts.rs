@@ -0,0 +1,60 @@
+// Contacts whose file transfers are accepted without asking
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+// Auto-accepted files land here, apart from files the user saved themselves
+const AUTO_ACCEPT_FOLDER: &str = "From trusted contacts";
+
+// Direct chats are keyed "contact:<user id>"
+pub fn contact_id_for_chat(chat_id: &str) -> Option<&str> {
+    chat_id.strip_prefix("contact:")
+}
+
+pub fn should_auto_accept(app: &AppHandle, contact_id: &str) -> bool {
+    crate::settings::load_account(app)
+        .map(|settings| {
+            settings.auto_accept_trusted_transfers
+                && settings
+                    .trusted_contacts
+                    .iter()
+                    .any(|trusted| trusted == contact_id)
+        })
+        .unwrap_or(false)
+}
+
+pub fn auto_accept_directory(app: &AppHandle) -> Result<PathBuf, String> {
+    let directory = crate::downloads::download_directory(app)?.join(AUTO_ACCEPT_FOLDER);
+    std::fs::create_dir_all(&directory).map_err(|e| e.to_string())?;
+    Ok(directory)
+}
+
+#[tauri::command]
+pub async fn list_trusted_contacts(app_handle: AppHandle) -> Result<Vec<String>, String> {
+    Ok(crate::settings::load_account(&app_handle)?.trusted_contacts)
+}
+
+#[tauri::command]
+pub async fn add_trusted_contact(
+    app_handle: AppHandle,
+    contact_id: String,
+) -> Result<Vec<String>, String> {
+    let mut settings = crate::settings::load_account(&app_handle)?;
+    if !settings.trusted_contacts.contains(&contact_id) {
+        settings.trusted_contacts.push(contact_id);
+        crate::settings::save_account(&app_handle, &settings)?;
+    }
+    Ok(settings.trusted_contacts)
+}
+
+#[tauri::command]
+pub async fn remove_trusted_contact(
+    app_handle: AppHandle,
+    contact_id: String,
+) -> Result<Vec<String>, String> {
+    let mut settings = crate::settings::load_account(&app_handle)?;
+    settings
+        .trusted_contacts
+        .retain(|trusted| trusted != &contact_id);
+    crate::settings::save_account(&app_handle, &settings)?;
+    Ok(settings.trusted_contacts)
+}
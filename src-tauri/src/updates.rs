@@ -0,0 +1,74 @@
+// App updates, checked and installed from Rust so the requests go through
+// the proxy settings like all other native networking. The updater plugin's
+// own webview commands can't be given the proxy, so they aren't allowed.
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_updater::Update;
+
+use crate::net;
+
+// The update found by the last check, kept for `install_update`
+#[derive(Default)]
+pub struct UpdateState(Mutex<Option<Update>>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    version: String,
+    current_version: String,
+    notes: Option<String>,
+    date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct UpdateProgress {
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+#[tauri::command]
+pub async fn check_for_update(app_handle: AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let update = net::updater(&app_handle)?
+        .check()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let info = update.as_ref().map(|update| UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        notes: update.body.clone(),
+        date: update.date.map(|date| date.to_string()),
+    });
+    *app_handle.state::<UpdateState>().0.lock().unwrap() = update;
+    Ok(info)
+}
+
+// Downloads and installs the update found by `check_for_update`, emitting
+// "update-download-progress" on the way, then restarts into it
+#[tauri::command]
+pub async fn install_update(app_handle: AppHandle) -> Result<(), String> {
+    let update = app_handle
+        .state::<UpdateState>()
+        .0
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| "No update to install; check for updates first".to_string())?;
+
+    let mut downloaded = 0u64;
+    update
+        .download_and_install(
+            |chunk, total| {
+                downloaded += chunk as u64;
+                let _ = app_handle.emit(
+                    "update-download-progress",
+                    UpdateProgress { downloaded, total },
+                );
+            },
+            || {},
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    app_handle.restart()
+}
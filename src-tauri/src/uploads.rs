@@ -0,0 +1,456 @@
+// Background uploads of large files. The file is streamed from disk in chunks
+// as the body of a single POST, the way the app's upload URLs (Convex's
+// `generateUploadUrl`) expect it, so it never sits in memory whole. Uploads
+// keep running after the chat window that started them is closed, and failed
+// attempts are retried with backoff from the first byte, since the server
+// only takes whole files.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::AsyncReadExt;
+
+use crate::shared_files::{self, SharedDirection, SharedFile};
+
+const UPLOADS_STORE: &str = "uploads.json";
+const CHUNK_SIZE: usize = 256 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadStatus {
+    InProgress,
+    // Cut off by the app quitting; `retry_upload` starts it again
+    Interrupted,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadRecord {
+    id: String,
+    url: String,
+    chat_id: Option<String>,
+    file_name: String,
+    path: PathBuf,
+    uploaded: u64,
+    total: u64,
+    status: UploadStatus,
+    error: Option<String>,
+    // Body of the server's reply, e.g. `{"storageId": ...}`
+    response: Option<String>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct UploadProgress<'a> {
+    upload_id: &'a str,
+    uploaded: u64,
+    total: u64,
+}
+
+#[derive(Default)]
+pub struct UploadState {
+    records: Mutex<Vec<UploadRecord>>,
+    cancels: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+enum AttemptError {
+    Retry(String),
+    Fatal(String),
+}
+
+fn save_records(app: &AppHandle, records: &[UploadRecord]) -> Result<(), String> {
+    let path = crate::accounts::store_path(app, UPLOADS_STORE);
+    let store = crate::store_recovery::open(app, &path)?;
+
+    store.set("uploads", serde_json::to_value(records).unwrap());
+    crate::store_recovery::save(app, path, &store)
+}
+
+// Uploads that were running when the app quit come back interrupted
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let store = crate::store_recovery::open(app, crate::accounts::store_path(app, UPLOADS_STORE))?;
+
+    let mut records: Vec<UploadRecord> = store
+        .get("uploads")
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+
+    for record in records
+        .iter_mut()
+        .filter(|record| record.status == UploadStatus::InProgress)
+    {
+        record.status = UploadStatus::Interrupted;
+        record.uploaded = 0;
+    }
+
+    save_records(app, &records)?;
+    *app.state::<UploadState>().records.lock().unwrap() = records;
+
+    Ok(())
+}
+
+fn update_record(
+    app: &AppHandle,
+    id: &str,
+    update: impl FnOnce(&mut UploadRecord),
+) -> Option<UploadRecord> {
+    let state = app.state::<UploadState>();
+    let mut records = state.records.lock().unwrap();
+    let record = records.iter_mut().find(|record| record.id == id)?;
+    update(record);
+    record.updated_at = chrono::Utc::now().timestamp_millis();
+    let record = record.clone();
+    let _ = save_records(app, &records);
+    drop(records);
+
+    let _ = app.emit("upload-updated", &record);
+    Some(record)
+}
+
+fn get_record(app: &AppHandle, id: &str) -> Result<UploadRecord, String> {
+    app.state::<UploadState>()
+        .records
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|record| record.id == id)
+        .cloned()
+        .ok_or_else(|| format!("Unknown upload: {}", id))
+}
+
+// Progress goes out as "upload-progress"; this keeps `list_uploads` current
+fn report_progress(app: &AppHandle, id: &str, uploaded: u64, total: u64) {
+    {
+        let state = app.state::<UploadState>();
+        let mut records = state.records.lock().unwrap();
+        if let Some(record) = records.iter_mut().find(|record| record.id == id) {
+            record.uploaded = uploaded;
+        }
+    }
+    let _ = app.emit(
+        "upload-progress",
+        UploadProgress {
+            upload_id: id,
+            uploaded,
+            total,
+        },
+    );
+}
+
+fn validate_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Unsupported URL: {}", url));
+    }
+    Ok(())
+}
+
+// The file as a stream of chunks, reporting progress as they're sent and
+// stopping once the upload is cancelled
+fn file_body(
+    app: &AppHandle,
+    record: &UploadRecord,
+    file: tokio::fs::File,
+    cancel: &Arc<AtomicBool>,
+) -> reqwest::Body {
+    let (app, id, total, cancel) = (app.clone(), record.id.clone(), record.total, cancel.clone());
+    let chunks = futures_util::stream::try_unfold(
+        (file, 0u64, Instant::now()),
+        move |(mut file, sent, reported)| {
+            let (app, id, cancel) = (app.clone(), id.clone(), cancel.clone());
+            async move {
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(std::io::Error::other("Cancelled"));
+                }
+                let mut chunk = vec![0u8; CHUNK_SIZE];
+                let read = file.read(&mut chunk).await?;
+                if read == 0 {
+                    return Ok(None);
+                }
+                chunk.truncate(read);
+
+                let sent = sent + read as u64;
+                let reported = if sent == total || reported.elapsed() >= PROGRESS_INTERVAL {
+                    report_progress(&app, &id, sent, total);
+                    Instant::now()
+                } else {
+                    reported
+                };
+                Ok(Some((chunk, (file, sent, reported))))
+            }
+        },
+    );
+    reqwest::Body::wrap_stream(chunks)
+}
+
+async fn send(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    record: &UploadRecord,
+    cancel: &Arc<AtomicBool>,
+) -> Result<String, AttemptError> {
+    let content_type = mime_guess::from_path(&record.path)
+        .first_or_octet_stream()
+        .essence_str()
+        .to_string();
+    let file = tokio::fs::File::open(&record.path)
+        .await
+        .map_err(|e| AttemptError::Fatal(e.to_string()))?;
+
+    let response = client
+        .post(&record.url)
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .header(reqwest::header::CONTENT_LENGTH, record.total)
+        .body(file_body(app, record, file, cancel))
+        .send()
+        .await
+        .map_err(|e| AttemptError::Retry(e.to_string()))?;
+
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if status.is_success() {
+        return Ok(body);
+    }
+
+    let error = format!("Upload rejected: HTTP {}", status);
+    if status.is_server_error()
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+    {
+        Err(AttemptError::Retry(error))
+    } else {
+        Err(AttemptError::Fatal(error))
+    }
+}
+
+async fn upload(
+    app: &AppHandle,
+    record: &UploadRecord,
+    cancel: &Arc<AtomicBool>,
+) -> Result<String, String> {
+    let client = crate::net::client(app)?;
+
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err("Cancelled".to_string());
+        }
+
+        match send(app, &client, record, cancel).await {
+            Ok(body) => return Ok(body),
+            Err(AttemptError::Retry(_)) if attempt < MAX_ATTEMPTS => {
+                attempt += 1;
+                report_progress(app, &record.id, 0, record.total);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+            Err(AttemptError::Retry(error) | AttemptError::Fatal(error)) => return Err(error),
+        }
+    }
+}
+
+fn spawn_upload(app: AppHandle, record: UploadRecord) {
+    let cancel = Arc::new(AtomicBool::new(false));
+    app.state::<UploadState>()
+        .cancels
+        .lock()
+        .unwrap()
+        .insert(record.id.clone(), cancel.clone());
+
+    tauri::async_runtime::spawn(async move {
+        let result = upload(&app, &record, &cancel).await;
+
+        app.state::<UploadState>()
+            .cancels
+            .lock()
+            .unwrap()
+            .remove(&record.id);
+
+        match result {
+            Ok(response) => {
+                update_record(&app, &record.id, |record| {
+                    record.status = UploadStatus::Completed;
+                    record.uploaded = record.total;
+                    record.response = Some(response);
+                });
+                if let Some(chat_id) = record.chat_id.clone() {
+                    shared_files::record(
+                        &app,
+                        SharedFile {
+                            id: record.id.clone(),
+                            chat_id,
+                            peer_id: None,
+                            direction: SharedDirection::Sent,
+                            file_name: record.file_name.clone(),
+                            path: record.path.clone(),
+                            size: record.total,
+                            shared_at: chrono::Utc::now().timestamp_millis(),
+                        },
+                    );
+                }
+            }
+            Err(_) if cancel.load(Ordering::Relaxed) => {
+                update_record(&app, &record.id, |record| {
+                    record.status = UploadStatus::Cancelled;
+                });
+            }
+            Err(error) => {
+                update_record(&app, &record.id, |record| {
+                    record.status = UploadStatus::Failed;
+                    record.error = Some(error);
+                });
+            }
+        }
+    });
+}
+
+async fn file_size(path: &PathBuf) -> Result<u64, String> {
+    let metadata = tokio::fs::metadata(path).await.map_err(|e| e.to_string())?;
+    if !metadata.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+    if metadata.len() == 0 {
+        return Err("File is empty".to_string());
+    }
+    Ok(metadata.len())
+}
+
+// `url` takes the whole file as one POST, like a Convex upload URL; the
+// server's reply ends up in the record's `response`
+#[tauri::command]
+pub async fn start_upload(
+    app_handle: AppHandle,
+    url: String,
+    path: String,
+    chat_id: Option<String>,
+) -> Result<UploadRecord, String> {
+    validate_url(&url)?;
+    let path = PathBuf::from(path);
+    let total = file_size(&path).await?;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let record = UploadRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        url,
+        chat_id,
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        path,
+        uploaded: 0,
+        total,
+        status: UploadStatus::InProgress,
+        error: None,
+        response: None,
+        created_at: now,
+        updated_at: now,
+    };
+    {
+        let state = app_handle.state::<UploadState>();
+        let mut records = state.records.lock().unwrap();
+        records.push(record.clone());
+        save_records(&app_handle, &records)?;
+    }
+    let _ = app_handle.emit("upload-updated", &record);
+
+    spawn_upload(app_handle.clone(), record.clone());
+
+    Ok(record)
+}
+
+// Starts an interrupted or failed upload over. Upload URLs can expire, so a
+// fresh `url` can be passed in.
+#[tauri::command]
+pub async fn retry_upload(
+    app_handle: AppHandle,
+    upload_id: String,
+    url: Option<String>,
+) -> Result<UploadRecord, String> {
+    let record = get_record(&app_handle, &upload_id)?;
+    if !matches!(
+        record.status,
+        UploadStatus::Interrupted | UploadStatus::Failed
+    ) {
+        return Err("Only interrupted or failed uploads can be retried".to_string());
+    }
+    if let Some(url) = &url {
+        validate_url(url)?;
+    }
+    let total = file_size(&record.path).await?;
+
+    let record = update_record(&app_handle, &upload_id, |record| {
+        record.status = UploadStatus::InProgress;
+        record.error = None;
+        record.uploaded = 0;
+        record.total = total;
+        if let Some(url) = url {
+            record.url = url;
+        }
+    })
+    .ok_or_else(|| format!("Unknown upload: {}", upload_id))?;
+    spawn_upload(app_handle.clone(), record.clone());
+
+    Ok(record)
+}
+
+#[tauri::command]
+pub async fn cancel_upload(app_handle: AppHandle, upload_id: String) -> Result<(), String> {
+    let record = get_record(&app_handle, &upload_id)?;
+    let running = app_handle
+        .state::<UploadState>()
+        .cancels
+        .lock()
+        .unwrap()
+        .get(&upload_id)
+        .map(|cancel| cancel.store(true, Ordering::Relaxed))
+        .is_some();
+
+    // Not running: nothing to stop, just settle the record
+    if !running
+        && matches!(
+            record.status,
+            UploadStatus::Interrupted | UploadStatus::Failed
+        )
+    {
+        update_record(&app_handle, &upload_id, |record| {
+            record.status = UploadStatus::Cancelled;
+        });
+    }
+
+    Ok(())
+}
+
+// Lets a reopened window pick up the uploads that kept running without it
+#[tauri::command]
+pub async fn list_uploads(
+    app_handle: AppHandle,
+    chat_id: Option<String>,
+) -> Result<Vec<UploadRecord>, String> {
+    let records = app_handle
+        .state::<UploadState>()
+        .records
+        .lock()
+        .unwrap()
+        .clone();
+
+    Ok(records
+        .into_iter()
+        .filter(|record| {
+            chat_id
+                .as_ref()
+                .is_none_or(|chat_id| record.chat_id.as_ref() == Some(chat_id))
+        })
+        .collect())
+}
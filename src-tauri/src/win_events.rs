@@ -0,0 +1,143 @@
+// Hidden window that receives Windows power, session and theme broadcasts.
+// It's a top-level window that's never shown, since message-only windows
+// don't get broadcasts like WM_SETTINGCHANGE.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Once, OnceLock};
+use windows_sys::core::GUID;
+use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::System::Power::{RegisterPowerSettingNotification, POWERBROADCAST_SETTING};
+use windows_sys::Win32::System::RemoteDesktop::{
+    WTSRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION,
+};
+use windows_sys::Win32::System::SystemServices::{
+    GUID_CONSOLE_DISPLAY_STATE, GUID_LIDSWITCH_STATE_CHANGE,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW,
+    TranslateMessage, DEVICE_NOTIFY_WINDOW_HANDLE, MSG, PBT_POWERSETTINGCHANGE,
+    WM_DWMCOLORIZATIONCOLORCHANGED, WM_POWERBROADCAST, WM_SETTINGCHANGE, WM_WTSSESSION_CHANGE,
+    WNDCLASSW, WTS_CONSOLE_CONNECT, WTS_CONSOLE_DISCONNECT, WTS_REMOTE_CONNECT,
+    WTS_REMOTE_DISCONNECT,
+};
+
+pub static LID_CLOSED: AtomicBool = AtomicBool::new(false);
+pub static DISPLAYS_OFF: AtomicBool = AtomicBool::new(false);
+pub static SESSION_ACTIVE: AtomicBool = AtomicBool::new(true);
+
+static START: Once = Once::new();
+static THEME_LISTENER: OnceLock<Box<dyn Fn() + Send + Sync>> = OnceLock::new();
+
+// Spawns the message loop thread the first time it's called
+pub fn start() {
+    START.call_once(|| {
+        std::thread::spawn(|| unsafe { run_message_loop() });
+    });
+}
+
+// Called on the message loop thread when the light/dark theme or the accent
+// color may have changed
+pub fn on_theme_change(listener: impl Fn() + Send + Sync + 'static) {
+    let _ = THEME_LISTENER.set(Box::new(listener));
+    start();
+}
+
+fn same_guid(a: &GUID, b: &GUID) -> bool {
+    a.data1 == b.data1 && a.data2 == b.data2 && a.data3 == b.data3 && a.data4 == b.data4
+}
+
+unsafe fn run_message_loop() {
+    let instance = GetModuleHandleW(std::ptr::null());
+    let class_name = windows_sys::w!("BootlegMsnSystemEvents");
+
+    let class = WNDCLASSW {
+        lpfnWndProc: Some(window_proc),
+        hInstance: instance,
+        lpszClassName: class_name,
+        ..Default::default()
+    };
+    if RegisterClassW(&class) == 0 {
+        return;
+    }
+
+    let hwnd = CreateWindowExW(
+        0,
+        class_name,
+        class_name,
+        0,
+        0,
+        0,
+        0,
+        0,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        instance,
+        std::ptr::null(),
+    );
+    if hwnd.is_null() {
+        return;
+    }
+
+    // Windows sends the current value immediately after registering
+    RegisterPowerSettingNotification(
+        hwnd,
+        &GUID_CONSOLE_DISPLAY_STATE,
+        DEVICE_NOTIFY_WINDOW_HANDLE,
+    );
+    RegisterPowerSettingNotification(
+        hwnd,
+        &GUID_LIDSWITCH_STATE_CHANGE,
+        DEVICE_NOTIFY_WINDOW_HANDLE,
+    );
+    // Fast user switching disconnects our session from the console
+    WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION);
+
+    let mut msg: MSG = std::mem::zeroed();
+    while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
+        TranslateMessage(&msg);
+        DispatchMessageW(&msg);
+    }
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_POWERBROADCAST && wparam == PBT_POWERSETTINGCHANGE as WPARAM {
+        let setting = &*(lparam as *const POWERBROADCAST_SETTING);
+        if setting.DataLength as usize >= std::mem::size_of::<u32>() {
+            let value = std::ptr::read_unaligned(setting.Data.as_ptr() as *const u32);
+            // Display state: 0 = off, 1 = on, 2 = dimmed. Lid: 0 = closed, 1 = open.
+            if same_guid(&setting.PowerSetting, &GUID_CONSOLE_DISPLAY_STATE) {
+                DISPLAYS_OFF.store(value == 0, Ordering::Relaxed);
+            } else if same_guid(&setting.PowerSetting, &GUID_LIDSWITCH_STATE_CHANGE) {
+                LID_CLOSED.store(value == 0, Ordering::Relaxed);
+            }
+        }
+        return 1;
+    }
+
+    if msg == WM_WTSSESSION_CHANGE {
+        match wparam as u32 {
+            WTS_CONSOLE_DISCONNECT | WTS_REMOTE_DISCONNECT => {
+                SESSION_ACTIVE.store(false, Ordering::Relaxed)
+            }
+            WTS_CONSOLE_CONNECT | WTS_REMOTE_CONNECT => {
+                SESSION_ACTIVE.store(true, Ordering::Relaxed)
+            }
+            _ => {}
+        }
+        return 0;
+    }
+
+    if msg == WM_SETTINGCHANGE || msg == WM_DWMCOLORIZATIONCOLORCHANGED {
+        // Theme changes come as WM_SETTINGCHANGE for "ImmersiveColorSet"
+        if let Some(listener) = THEME_LISTENER.get() {
+            listener();
+        }
+    }
+
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
@@ -0,0 +1,404 @@
+// Winks: short full-screen animations sent to a contact. A wink is a zip
+// holding `wink.json` plus the files it names:
+// { "name": "Kiss", "animation": "kiss.webm", "thumbnail": "kiss.png", "sound": "kiss.mp3" }
+// An optional "sha256": { "<file>": "<hex>" } map is checked on install.
+// A wink's id is its animation's SHA-256, so both sides of a chat know it by
+// the same id. Files are served at `wink://localhost/<id>%2F<file>`; pass a
+// wink's paths to `convertFileSrc(path, "wink")` for the URLs.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, UriSchemeResponder};
+use zip::ZipArchive;
+
+use crate::{checksum, sound};
+
+pub const SCHEME: &str = "wink";
+const MANIFEST: &str = "wink.json";
+const MAX_MANIFEST_BYTES: u64 = 64 * 1024;
+const MAX_WINK_BYTES: u64 = 10 * 1024 * 1024;
+// Installing fails once the installed winks would take more than this
+const MAX_TOTAL_BYTES: u64 = 200 * 1024 * 1024;
+const MAX_NAME_LENGTH: usize = 64;
+const ANIMATION_EXTENSIONS: [&str; 5] = ["gif", "webp", "png", "webm", "mp4"];
+const THUMBNAIL_EXTENSIONS: [&str; 4] = ["png", "gif", "jpg", "webp"];
+const SOUND_EXTENSIONS: [&str; 3] = ["wav", "mp3", "ogg"];
+
+// Size and hash of each file, recorded on install
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileRecord {
+    size: u64,
+    sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Manifest {
+    name: String,
+    animation: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thumbnail: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sound: Option<String>,
+    // Expected hashes in a package; the recorded files once installed
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    sha256: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    files: HashMap<String, FileRecord>,
+}
+
+// Paths are "<id>/<file>", for the wink:// protocol
+#[derive(Debug, Clone, Serialize)]
+pub struct Wink {
+    id: String,
+    name: String,
+    animation: String,
+    thumbnail: Option<String>,
+    sound: Option<String>,
+    size: u64,
+    // False when a file is missing or no longer matches its hash
+    intact: bool,
+}
+
+fn winks_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("winks"))
+}
+
+fn is_wink_id(id: &str) -> bool {
+    id.len() == 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+}
+
+fn wink_dir(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    if !is_wink_id(id) {
+        return Err(format!("Unknown wink: {}", id));
+    }
+    Ok(winks_dir(app)?.join(id))
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest, String> {
+    let file = std::fs::File::open(dir.join(MANIFEST)).map_err(|e| e.to_string())?;
+    serde_json::from_reader(file).map_err(|e| e.to_string())
+}
+
+fn is_plain_file_name(name: &str) -> bool {
+    Path::new(name).file_name().and_then(|file| file.to_str()) == Some(name)
+}
+
+fn validate_file_name(name: &str, extensions: &[&str], kind: &str) -> Result<(), String> {
+    if !is_plain_file_name(name) || name == MANIFEST {
+        return Err(format!("Invalid file name: {}", name));
+    }
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    if !extension.is_some_and(|extension| extensions.contains(&extension.as_str())) {
+        return Err(format!(
+            "The {} must be one of: {}",
+            kind,
+            extensions.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+// Reads one entry, trusting neither its declared size nor its contents
+fn read_entry(
+    archive: &mut ZipArchive<std::fs::File>,
+    name: &str,
+    limit: u64,
+) -> Result<Vec<u8>, String> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|_| format!("The wink is missing {}", name))?;
+    if entry.size() > limit {
+        return Err(format!("{} is larger than {} KB", name, limit / 1024));
+    }
+
+    let mut bytes = Vec::new();
+    entry
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > limit {
+        return Err(format!("{} is larger than {} KB", name, limit / 1024));
+    }
+    Ok(bytes)
+}
+
+fn installed_size(winks: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(winks) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| read_manifest(&entry.path()).ok())
+        .map(|manifest| manifest.files.values().map(|file| file.size).sum::<u64>())
+        .sum()
+}
+
+fn install(archive_path: &Path, winks: &Path) -> Result<(String, Manifest), String> {
+    let file = std::fs::File::open(archive_path).map_err(|e| e.to_string())?;
+    if file.metadata().map_err(|e| e.to_string())?.len() > MAX_WINK_BYTES {
+        return Err(format!(
+            "Winks can be at most {} MB",
+            MAX_WINK_BYTES / 1024 / 1024
+        ));
+    }
+    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+
+    let manifest = read_entry(&mut archive, MANIFEST, MAX_MANIFEST_BYTES)?;
+    let mut manifest: Manifest = serde_json::from_slice(&manifest).map_err(|e| e.to_string())?;
+    manifest.name = manifest.name.trim().chars().take(MAX_NAME_LENGTH).collect();
+    if manifest.name.is_empty() {
+        return Err("The wink has no name".to_string());
+    }
+    validate_file_name(&manifest.animation, &ANIMATION_EXTENSIONS, "animation")?;
+    if let Some(thumbnail) = &manifest.thumbnail {
+        validate_file_name(thumbnail, &THUMBNAIL_EXTENSIONS, "thumbnail")?;
+    }
+    if let Some(sound) = &manifest.sound {
+        validate_file_name(sound, &SOUND_EXTENSIONS, "sound")?;
+    }
+
+    let mut names = vec![manifest.animation.clone()];
+    names.extend(manifest.thumbnail.clone());
+    names.extend(manifest.sound.clone());
+    names.dedup();
+    let mut files = Vec::new();
+    let mut records = HashMap::new();
+    for name in names {
+        let bytes = read_entry(&mut archive, &name, MAX_WINK_BYTES)?;
+        let sha256 = checksum::sha256_bytes(&bytes);
+        if manifest
+            .sha256
+            .get(&name)
+            .is_some_and(|expected| !expected.eq_ignore_ascii_case(&sha256))
+        {
+            return Err(format!("{} is damaged; its checksum doesn't match", name));
+        }
+        records.insert(
+            name.clone(),
+            FileRecord {
+                size: bytes.len() as u64,
+                sha256,
+            },
+        );
+        files.push((name, bytes));
+    }
+    if let Some(thumbnail) = &manifest.thumbnail {
+        let (_, bytes) = files.iter().find(|(name, _)| name == thumbnail).unwrap();
+        image::load_from_memory(bytes)
+            .map_err(|_| format!("{} isn't a readable image", thumbnail))?;
+    }
+    if let Some(name) = &manifest.sound {
+        let (_, bytes) = files.iter().find(|(file, _)| file == name).unwrap();
+        sound::decode(bytes.clone()).map_err(|_| format!("{} could not be played", name))?;
+    }
+
+    let id = records[&manifest.animation].sha256.clone();
+    // The same animation is the same wink, whatever the package calls it
+    if let Ok(installed) = read_manifest(&winks.join(&id)) {
+        return Ok((id, installed));
+    }
+    let size: u64 = records.values().map(|file| file.size).sum();
+    if installed_size(winks) + size > MAX_TOTAL_BYTES {
+        return Err(format!(
+            "Installed winks can take at most {} MB; remove some to make room",
+            MAX_TOTAL_BYTES / 1024 / 1024
+        ));
+    }
+    manifest.sha256.clear();
+    manifest.files = records;
+
+    // Unpacked next to the others and renamed into place once complete
+    let partial = winks.join(format!("{}.part", id));
+    let result = (|| {
+        std::fs::create_dir_all(&partial).map_err(|e| e.to_string())?;
+        for (name, bytes) in &files {
+            std::fs::write(partial.join(name), bytes).map_err(|e| e.to_string())?;
+        }
+        let manifest_file =
+            std::fs::File::create(partial.join(MANIFEST)).map_err(|e| e.to_string())?;
+        serde_json::to_writer_pretty(manifest_file, &manifest).map_err(|e| e.to_string())?;
+        std::fs::rename(&partial, winks.join(&id)).map_err(|e| e.to_string())
+    })();
+    if let Err(error) = result {
+        let _ = std::fs::remove_dir_all(&partial);
+        return Err(error);
+    }
+
+    Ok((id, manifest))
+}
+
+// Blocking; hashes every file
+fn is_intact(dir: &Path, manifest: &Manifest) -> bool {
+    manifest.files.iter().all(|(name, record)| {
+        checksum::sha256_file(&dir.join(name)).is_ok_and(|sha256| sha256 == record.sha256)
+    })
+}
+
+fn describe(id: String, manifest: Manifest, intact: bool) -> Wink {
+    let path = |file: &String| format!("{}/{}", id, file);
+    Wink {
+        animation: path(&manifest.animation),
+        thumbnail: manifest.thumbnail.as_ref().map(path),
+        sound: manifest.sound.as_ref().map(path),
+        size: manifest.files.values().map(|file| file.size).sum(),
+        name: manifest.name,
+        intact,
+        id,
+    }
+}
+
+// Only files the manifest lists, at the size recorded on install
+fn file_path(app: &AppHandle, path: &str) -> Option<PathBuf> {
+    let decoded = percent_encoding::percent_decode_str(path.trim_start_matches('/'))
+        .decode_utf8()
+        .ok()?;
+    let (id, file) = decoded.split_once('/')?;
+    let dir = wink_dir(app, id).ok()?;
+    let record = read_manifest(&dir).ok()?.files.remove(file)?;
+    let path = dir.join(file);
+    (std::fs::metadata(&path).ok()?.len() == record.size).then_some(path)
+}
+
+// "bytes=<start>-<end>", with either end left out, as a range within `size`.
+// `None` when it asks for nothing the file has.
+fn parse_range(value: &str, size: u64) -> Option<Range<u64>> {
+    let (start, end) = value.strip_prefix("bytes=")?.split_once('-')?;
+    let range = match (start.trim(), end.trim()) {
+        ("", suffix) => size.saturating_sub(suffix.parse().ok()?)..size,
+        (start, "") => start.parse().ok()?..size,
+        (start, end) => start.parse().ok()?..end.parse::<u64>().ok()?.saturating_add(1).min(size),
+    };
+    (range.start < range.end).then_some(range)
+}
+
+fn read_range(path: &Path, range: &Range<u64>) -> std::io::Result<Vec<u8>> {
+    let mut file = std::fs::File::open(path)?;
+    file.seek(SeekFrom::Start(range.start))?;
+    let mut bytes = Vec::new();
+    file.take(range.end - range.start).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+// Media elements fetch video in ranges, and WebKit won't play it from a
+// scheme that can't serve them
+fn respond(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let empty = |status: StatusCode| Response::builder().status(status).body(Vec::new());
+    let Some(path) = file_path(app, request.uri().path()) else {
+        return empty(StatusCode::NOT_FOUND).unwrap();
+    };
+    let Ok(size) = std::fs::metadata(&path).map(|metadata| metadata.len()) else {
+        return empty(StatusCode::NOT_FOUND).unwrap();
+    };
+
+    let requested = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    let (status, range) = match requested.map(|value| parse_range(value, size)) {
+        None => (StatusCode::OK, 0..size),
+        Some(Some(range)) => (StatusCode::PARTIAL_CONTENT, range),
+        Some(None) => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+                .body(Vec::new())
+                .unwrap()
+        }
+    };
+    let Ok(bytes) = read_range(&path, &range) else {
+        return empty(StatusCode::NOT_FOUND).unwrap();
+    };
+
+    let mime = mime_guess::from_path(&path).first_or_octet_stream();
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, mime.as_ref())
+        .header(header::ACCEPT_RANGES, "bytes")
+        // Ids are content hashes, so a wink's files never change
+        .header(header::CACHE_CONTROL, "max-age=31536000, immutable")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+    if status == StatusCode::PARTIAL_CONTENT {
+        response = response.header(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", range.start, range.end - 1, size),
+        );
+    }
+    response.body(bytes).unwrap()
+}
+
+// Handler for the wink:// protocol, registered on the app builder. Files are
+// read off the webview's thread.
+pub fn handle_request(app: &AppHandle, request: Request<Vec<u8>>, responder: UriSchemeResponder) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || responder.respond(respond(&app, &request)));
+}
+
+#[tauri::command]
+pub async fn list_winks(app_handle: AppHandle) -> Result<Vec<Wink>, String> {
+    let winks = winks_dir(&app_handle)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let Ok(entries) = std::fs::read_dir(&winks) else {
+            return Ok(Vec::new());
+        };
+        let mut winks: Vec<Wink> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let id = entry.file_name().to_str()?.to_string();
+                if !is_wink_id(&id) {
+                    return None;
+                }
+                let manifest = read_manifest(&entry.path()).ok()?;
+                let intact = is_intact(&entry.path(), &manifest);
+                Some(describe(id, manifest, intact))
+            })
+            .collect();
+        winks.sort_by_key(|wink| wink.name.to_lowercase());
+        Ok(winks)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// For a wink a contact sent; errors if it isn't installed here
+#[tauri::command]
+pub async fn get_wink(app_handle: AppHandle, wink_id: String) -> Result<Wink, String> {
+    let dir = wink_dir(&app_handle, &wink_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let manifest = read_manifest(&dir).map_err(|_| format!("Unknown wink: {}", wink_id))?;
+        let intact = is_intact(&dir, &manifest);
+        Ok(describe(wink_id, manifest, intact))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn install_wink(app_handle: AppHandle, path: String) -> Result<Wink, String> {
+    let winks = winks_dir(&app_handle)?;
+    std::fs::create_dir_all(&winks).map_err(|e| e.to_string())?;
+
+    let (id, manifest) =
+        tauri::async_runtime::spawn_blocking(move || install(Path::new(&path), &winks))
+            .await
+            .map_err(|e| e.to_string())??;
+    Ok(describe(id, manifest, true))
+}
+
+#[tauri::command]
+pub async fn uninstall_wink(app_handle: AppHandle, wink_id: String) -> Result<(), String> {
+    let dir = wink_dir(&app_handle, &wink_id)?;
+    std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())
+}